@@ -0,0 +1,34 @@
+// With the ffi feature the C header is generated from the functions in src/ffi.rs into the
+// output directory, a test checks that include/melg_chess.h is the same
+fn main() {
+    #[cfg(feature = "ffi")]
+    {
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        let config = cbindgen::Config {
+            language: cbindgen::Language::C,
+            include_guard: Some("MELG_CHESS_H".to_string()),
+            autogen_warning: Some(
+                "/* Generated by cbindgen from src/ffi.rs, don't edit by hand */".to_string(),
+            ),
+            enumeration: cbindgen::EnumConfig {
+                rename_variants: cbindgen::RenameRule::QualifiedScreamingSnakeCase,
+                ..Default::default()
+            },
+            usize_is_size_t: true,
+            ..Default::default()
+        };
+
+        cbindgen::Builder::new()
+            .with_src(format!("{}/src/ffi.rs", crate_dir))
+            .with_config(config)
+            .generate()
+            .expect("Unable to generate the C header")
+            .write_to_file(format!("{}/melg_chess.h", out_dir));
+    }
+
+    #[cfg(not(feature = "ffi"))]
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,94 @@
+#ifndef MELG_CHESS_H
+#define MELG_CHESS_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The state of a game, or that a move was rejected
+ */
+typedef enum MelgState {
+  MELG_STATE_IN_PROGRESS,
+  MELG_STATE_CHECK,
+  MELG_STATE_CHECKMATE,
+  MELG_STATE_GAME_OVER,
+  MELG_STATE_ILLEGAL_MOVE,
+} MelgState;
+
+/**
+ * A game behind an opaque pointer, made by `melg_game_new` or `melg_game_from_fen` and
+ * released with `melg_game_free`
+ */
+typedef struct MelgGame MelgGame;
+
+/**
+ * Start a game from the starting position
+ */
+struct MelgGame *melg_game_new(void);
+
+/**
+ * Start a game from a position in Forsyth–Edwards Notation, returns NULL if it can't be parsed
+ *
+ * # Safety
+ * `fen` must be NULL or a NUL terminated string.
+ */
+struct MelgGame *melg_game_from_fen(const char *fen);
+
+/**
+ * Release a game, NULL is ignored
+ *
+ * # Safety
+ * `game` must be NULL or a game that hasn't been released yet.
+ */
+void melg_game_free(struct MelgGame *game);
+
+/**
+ * Play a move in coordinate notation such as "e2e4" or "e7e8q", or in Standard Algebraic
+ * Notation, and return the resulting state, `MELG_STATE_ILLEGAL_MOVE` if it can't be played
+ *
+ * # Safety
+ * `game` must be NULL or a live game, `text` NULL or a NUL terminated string.
+ */
+enum MelgState melg_game_make_move(struct MelgGame *game, const char *text);
+
+/**
+ * Take back the last move, returns false if there is none
+ *
+ * # Safety
+ * `game` must be NULL or a live game.
+ */
+bool melg_game_undo(struct MelgGame *game);
+
+/**
+ * The current state of a game
+ *
+ * # Safety
+ * `game` must be a live game.
+ */
+enum MelgState melg_game_state(const struct MelgGame *game);
+
+/**
+ * Write the position in Forsyth–Edwards Notation into a buffer of `size` bytes, see
+ * `melg_game_legal_moves` for how the buffer is filled
+ *
+ * # Safety
+ * `game` must be a live game and `buffer` NULL or writable for `size` bytes.
+ */
+size_t melg_game_fen(const struct MelgGame *game, char *buffer, size_t size);
+
+/**
+ * Write the legal moves in coordinate notation, separated by spaces, into a buffer of `size`
+ * bytes. The text is cut short if it doesn't fit and always ends with a NUL. Returns the length
+ * of the whole text, so a buffer of at least one more byte than that holds all of it.
+ *
+ * # Safety
+ * `game` must be a live game and `buffer` NULL or writable for `size` bytes.
+ */
+size_t melg_game_legal_moves(struct MelgGame *game, char *buffer, size_t size);
+
+#endif  /* MELG_CHESS_H */
@@ -0,0 +1,395 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::{view_to_tile, Colour, Game, Move, Piece};
+
+// Pieces drawn on a 16 by 16 grid, '#' is the outline, 'o' the colour of the piece and '.'
+// shows the tile underneath
+const SPRITE_SIZE: usize = 16;
+
+static KING_SPRITE: [&str; SPRITE_SIZE] = [
+    ".......##.......",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "......#oo#......",
+    "..####.##.####..",
+    ".#oooo#oo#oooo#.",
+    ".#oooooooooooo#.",
+    ".#oooooooooooo#.",
+    "..#oooooooooo#..",
+    "...#oooooooo#...",
+    "....########....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "..############..",
+    "..#oooooooooo#..",
+    "..############..",
+];
+
+static QUEEN_SPRITE: [&str; SPRITE_SIZE] = [
+    "................",
+    "..#...#..#...#..",
+    ".#o#.#o##o#.#o#.",
+    "..#o#oo##oo#o#..",
+    "..#oooooooooo#..",
+    "...#oooooooo#...",
+    "...#oooooooo#...",
+    "....#oooooo#....",
+    "....########....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "..############..",
+    "..#oooooooooo#..",
+    "..############..",
+];
+
+static ROOK_SPRITE: [&str; SPRITE_SIZE] = [
+    "................",
+    "................",
+    "...##.####.##...",
+    "...#o##oo##o#...",
+    "...#oooooooo#...",
+    "....########....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...##########...",
+    "..#oooooooooo#..",
+    "..############..",
+    "................",
+];
+
+static BISHOP_SPRITE: [&str; SPRITE_SIZE] = [
+    "................",
+    ".......##.......",
+    "......#oo#......",
+    ".....#oo#o#.....",
+    "....#oo#ooo#....",
+    "....#o#oooo#....",
+    "....#oooooo#....",
+    ".....#oooo#.....",
+    "......#oo#......",
+    ".....######.....",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "..############..",
+    "................",
+    "................",
+];
+
+static KNIGHT_SPRITE: [&str; SPRITE_SIZE] = [
+    "................",
+    "......##........",
+    ".....#o##.......",
+    "....#oooo##.....",
+    "...#oo#oooo#....",
+    "..#ooooooooo#...",
+    "..#ooooooooo#...",
+    "...##.#ooooo#...",
+    ".....#oooooo#...",
+    "....#ooooooo#...",
+    "...#oooooooo#...",
+    "...#oooooooo#...",
+    "..############..",
+    "..#oooooooooo#..",
+    "..############..",
+    "................",
+];
+
+static PAWN_SPRITE: [&str; SPRITE_SIZE] = [
+    "................",
+    "................",
+    "................",
+    "......####......",
+    ".....#oooo#.....",
+    ".....#oooo#.....",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "......#oo#......",
+    "......#oo#......",
+    ".....#oooo#.....",
+    "....#oooooo#....",
+    "...#oooooooo#...",
+    "...##########...",
+    "................",
+    "................",
+];
+
+// Indices of the colours in the palette of a frame
+const LIGHT_TILE: u8 = 0;
+const DARK_TILE: u8 = 1;
+const LAST_MOVE: u8 = 2;
+const WHITE_PIECE: u8 = 3;
+const BLACK_PIECE: u8 = 4;
+const OUTLINE: u8 = 5;
+
+/// Colours of an animated board as red, green and blue.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationTheme {
+    pub light_tile: (u8, u8, u8),
+    pub dark_tile: (u8, u8, u8),
+    pub last_move: (u8, u8, u8),
+    pub white_piece: (u8, u8, u8),
+    pub black_piece: (u8, u8, u8),
+    pub outline: (u8, u8, u8),
+}
+
+impl Default for AnimationTheme {
+    fn default() -> AnimationTheme {
+        AnimationTheme {
+            light_tile: (240, 217, 181),
+            dark_tile: (181, 136, 99),
+            last_move: (205, 210, 106),
+            white_piece: (255, 255, 255),
+            black_piece: (60, 60, 60),
+            outline: (0, 0, 0),
+        }
+    }
+}
+
+/// Settings for an animation of a game, with one frame per position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationOptions {
+    /// Width and height of a tile in pixels, from 1 to 1024.
+    pub tile_size: u32,
+    /// The colour shown at the bottom of the board.
+    pub orientation: Colour,
+    /// How long each position is shown.
+    pub delay: Duration,
+    /// Highlight the tiles the last move was played from and to.
+    pub last_move: bool,
+    pub theme: AnimationTheme,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> AnimationOptions {
+        AnimationOptions {
+            tile_size: 32,
+            orientation: Colour::White,
+            delay: Duration::from_secs(1),
+            last_move: true,
+            theme: AnimationTheme::default(),
+        }
+    }
+}
+
+/// Write an animated GIF of every position of a game, from the position it started from to the
+/// current one. The animation loops forever.
+pub fn write_gif<W: Write>(game: &Game, options: &AnimationOptions, output: W) -> io::Result<()> {
+    let size = board_size(options)? as u16;
+    let mut encoder = gif::Encoder::new(output, size, size, &palette(&options.theme))
+        .map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+
+    // GIF delays are in hundredths of a second
+    let delay = (options.delay.as_millis() / 10).min(u16::MAX as u128) as u16;
+    for pixels in frames(game, options) {
+        let frame = gif::Frame {
+            width: size,
+            height: size,
+            delay,
+            buffer: pixels.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+
+    Ok(())
+}
+
+/// Write an animated PNG of every position of a game, from the position it started from to the
+/// current one. The animation loops forever.
+pub fn write_apng<W: Write>(game: &Game, options: &AnimationOptions, output: W) -> io::Result<()> {
+    let size = board_size(options)?;
+    let frames = frames(game, options);
+
+    let mut encoder = png::Encoder::new(output, size, size);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette(&options.theme));
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(io::Error::other)?;
+    let delay = options.delay.as_millis().min(u16::MAX as u128) as u16;
+    encoder
+        .set_frame_delay(delay, 1000)
+        .map_err(io::Error::other)?;
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    for pixels in frames {
+        writer.write_image_data(&pixels).map_err(io::Error::other)?;
+    }
+    writer.finish().map_err(io::Error::other)
+}
+
+fn board_size(options: &AnimationOptions) -> io::Result<u32> {
+    if options.tile_size == 0 || options.tile_size > 1024 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the tile size must be from 1 to 1024 pixels",
+        ));
+    }
+
+    Ok(options.tile_size * 8)
+}
+
+fn palette(theme: &AnimationTheme) -> Vec<u8> {
+    [
+        theme.light_tile,
+        theme.dark_tile,
+        theme.last_move,
+        theme.white_piece,
+        theme.black_piece,
+        theme.outline,
+    ]
+    .iter()
+    .flat_map(|&(red, green, blue)| [red, green, blue])
+    .collect()
+}
+
+// The positions before every move in the history, then the current one, each with the move
+// that led to it
+fn frames(game: &Game, options: &AnimationOptions) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    let mut last_move = None;
+
+    for snapshot in game.history.iter() {
+        frames.push(draw_board(&snapshot.board, last_move, options));
+        last_move = Some(snapshot.played_move);
+    }
+    frames.push(draw_board(&game.board, last_move, options));
+
+    frames
+}
+
+// One palette index per pixel, row by row from the top
+fn draw_board(
+    board: &[[Option<(Piece, Colour)>; 8]; 8],
+    last_move: Option<Move>,
+    options: &AnimationOptions,
+) -> Vec<u8> {
+    let tile_size = options.tile_size as usize;
+    let size = tile_size * 8;
+    let mut pixels = vec![0; size * size];
+
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (index % size, index / size);
+        let tile = view_to_tile(options.orientation, (x / tile_size, y / tile_size));
+
+        // Each pixel of the tile is scaled to the nearest one of the sprite
+        let sprite_x = (x % tile_size) * SPRITE_SIZE / tile_size;
+        let sprite_y = (y % tile_size) * SPRITE_SIZE / tile_size;
+        let shape = board[tile.0][tile.1]
+            .map(|(piece, colour)| (sprite(piece)[sprite_y].as_bytes()[sprite_x], colour));
+
+        *pixel = match shape {
+            Some((b'#', _)) => OUTLINE,
+            Some((b'o', Colour::White)) => WHITE_PIECE,
+            Some((b'o', Colour::Black)) => BLACK_PIECE,
+            _ if options.last_move
+                && last_move.is_some_and(|_move| _move.from == tile || _move.to == tile) =>
+            {
+                LAST_MOVE
+            }
+            _ if (tile.0 + tile.1).is_multiple_of(2) => DARK_TILE,
+            _ => LIGHT_TILE,
+        };
+    }
+
+    pixels
+}
+
+fn sprite(piece: Piece) -> &'static [&'static str; SPRITE_SIZE] {
+    match piece {
+        Piece::King => &KING_SPRITE,
+        Piece::Queen => &QUEEN_SPRITE,
+        Piece::Rook => &ROOK_SPRITE,
+        Piece::Bishop => &BISHOP_SPRITE,
+        Piece::Knight => &KNIGHT_SPRITE,
+        Piece::Pawn => &PAWN_SPRITE,
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_pgn;
+
+    #[test]
+    fn test_sprites() {
+        for piece in [
+            Piece::King,
+            Piece::Queen,
+            Piece::Rook,
+            Piece::Bishop,
+            Piece::Knight,
+            Piece::Pawn,
+        ] {
+            assert!(sprite(piece)
+                .iter()
+                .all(|row| row.len() == SPRITE_SIZE
+                    && row.bytes().all(|pixel| b"#o.".contains(&pixel))));
+        }
+
+        // The last move is highlighted where no piece covers it, the board is turned around
+        let mut game = Game::new();
+        game.make_move("e2".to_string(), "e4".to_string());
+        let options = AnimationOptions {
+            tile_size: 16,
+            orientation: Colour::Black,
+            ..AnimationOptions::default()
+        };
+        let frames = frames(&game, &options);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0][0], LIGHT_TILE);
+        assert!(!frames[0].contains(&LAST_MOVE));
+
+        // e2 is in the fourth column and second row from the top when black is at the bottom
+        let e2 = 16 * 128 + 3 * 16;
+        assert_eq!(frames[1][e2], LAST_MOVE);
+        assert_eq!(frames[1][e2 + 7 * 128 + 7], LAST_MOVE);
+        assert_eq!(frames[1][3 * 16 * 128 + 3 * 16 + 7 * 128 + 7], WHITE_PIECE);
+    }
+
+    #[test]
+    fn test_write_animations() {
+        let game = parse_pgn("1. e4 e5 2. Nf3 Nc6 *")[0].to_game().unwrap();
+        let options = AnimationOptions::default();
+
+        let mut gif = Vec::new();
+        write_gif(&game, &options, &mut gif).unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (256, 256));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 100);
+            frames += 1;
+        }
+        assert_eq!(frames, 5);
+
+        let mut apng = Vec::new();
+        write_apng(&game, &options, &mut apng).unwrap();
+        let decoder = png::Decoder::new(io::Cursor::new(apng));
+        let reader = decoder.read_info().unwrap();
+        let animation = reader.info().animation_control().unwrap();
+        assert_eq!((animation.num_frames, animation.num_plays), (5, 0));
+
+        let bad = AnimationOptions {
+            tile_size: 0,
+            ..options
+        };
+        assert!(write_gif(&game, &bad, Vec::new()).is_err());
+    }
+}
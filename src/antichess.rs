@@ -0,0 +1,117 @@
+use crate::{Colour, Game, MoveFilter, Piece};
+
+impl Game {
+    // Captures are compulsory in Antichess, so a move that doesn't capture is only legal when
+    // none of the colour's pieces can capture. The king is an ordinary piece and may be left
+    // where it can be taken.
+    pub(crate) fn is_legal_antichess(&mut self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let colour = self.board[from.0][from.1].unwrap().1;
+        self.is_capture_move(from, to) || !self.can_capture(colour)
+    }
+
+    // A pawn moving sideways captures, even when the tile is empty after a double step
+    fn is_capture_move(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (piece, colour) = self.board[from.0][from.1].unwrap();
+
+        matches!(self.board[to.0][to.1], Some((_, target)) if target != colour)
+            || (piece == Piece::Pawn && from.0 != to.0)
+    }
+
+    fn can_capture(&mut self, colour: Colour) -> bool {
+        for _x in 0..8 {
+            for _y in 0..8 {
+                let piece = match self.board[_x][_y] {
+                    Some((piece, owner)) if owner == colour => piece,
+                    _ => continue,
+                };
+
+                let moves = piece.get_available_moves((_x, _y), MoveFilter::All, self);
+                if moves.iter().any(|&to| self.is_capture_move((_x, _y), to)) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    pub(crate) fn has_pieces(&self, colour: Colour) -> bool {
+        self.board
+            .iter()
+            .flatten()
+            .any(|tile| matches!(tile, Some((_, owner)) if *owner == colour))
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::{Outcome, Variant};
+    use crate::{Colour, Game, GameState};
+
+    fn antichess(fen: &str) -> Game {
+        Game::from_variant_fen(Variant::Antichess, fen.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_forced_captures() {
+        let mut game = Game::new_variant(Variant::Antichess);
+        assert_eq!(
+            game.get_fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1"
+        );
+        game.make_move("e2".to_string(), "e4".to_string());
+        game.make_move("d7".to_string(), "d5".to_string());
+
+        // Only the pawn can take, so nothing else may move
+        assert_eq!(game.make_move("g1".to_string(), "f3".to_string()), None);
+        assert_eq!(
+            game.get_possible_moves("e4".to_string()),
+            Some(vec!["d5".to_string()])
+        );
+        assert_eq!(
+            game.make_move("e4".to_string(), "d5".to_string()),
+            Some(GameState::InProgress)
+        );
+
+        // The king is an ordinary piece that can be given away
+        let mut game = antichess("4k3/R7/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert!(game
+            .get_possible_moves("e8".to_string())
+            .unwrap()
+            .contains(&"e7".to_string()));
+    }
+
+    #[test]
+    fn test_antichess_outcomes() {
+        // Promoting to a king is allowed
+        let mut game = antichess("8/1P6/8/8/8/8/8/7k w - - 0 1");
+        game.make_move("b7".to_string(), "b8".to_string());
+        game.set_promotion("King".to_string());
+        assert_eq!(game.get_fen(), "1K6/8/8/8/8/8/8/7k b - - 0 1");
+
+        // Losing the last piece wins
+        let mut game = antichess("8/8/8/8/8/8/1k6/R7 w - - 0 1");
+        game.make_move("a1".to_string(), "b1".to_string());
+        assert_eq!(
+            game.make_move("b2".to_string(), "b1".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "loses all pieces"))
+        );
+
+        // So does being stalemated
+        let mut game = antichess("8/8/8/8/8/p7/P7/7k w - - 0 1");
+        assert_eq!(game.get_game_state(), GameState::GameOver);
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "is stalemated"))
+        );
+    }
+}
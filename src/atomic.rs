@@ -0,0 +1,124 @@
+use crate::{add_i32_usize, check_for_checked, Colour, Game, Move, Piece, KING_OFFSETS};
+
+impl Game {
+    // In Atomic a capture explodes, taking the capturing piece and every piece around the
+    // target with it, apart from pawns
+    pub(crate) fn explode(&mut self, center: (usize, usize)) {
+        self.board[center.0][center.1] = None;
+
+        for &(x_offset, y_offset) in KING_OFFSETS.iter() {
+            let tile = match (
+                add_i32_usize(center.0, x_offset),
+                add_i32_usize(center.1, y_offset),
+            ) {
+                (Some(x_position), Some(y_position)) if x_position < 8 && y_position < 8 => {
+                    (x_position, y_position)
+                }
+                _ => continue,
+            };
+
+            if matches!(self.board[tile.0][tile.1], Some((piece, _)) if piece != Piece::Pawn) {
+                self.board[tile.0][tile.1] = None;
+                self.remove_castling_rook(tile);
+            }
+        }
+    }
+
+    // Kings can't capture as they would explode, and a move may not blow up or leave the own
+    // king in check, unless it blows up the opponent's king
+    pub(crate) fn is_legal_atomic(&mut self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (piece, colour) = self.board[from.0][from.1].unwrap();
+        if piece == Piece::King
+            && matches!(self.board[to.0][to.1], Some((_, target)) if target != colour)
+        {
+            return false;
+        }
+
+        self.push_move(Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        });
+        let legal = if !self.has_king(colour) {
+            false
+        } else if !self.has_king(colour.opponent()) {
+            true
+        } else {
+            !check_for_checked(colour, self)
+        };
+        self.pop_move();
+
+        legal
+    }
+
+    // Kings standing next to each other can't give check, as taking one explodes both
+    pub(crate) fn kings_touch(&self, king: (usize, usize)) -> bool {
+        let colour = self.board[king.0][king.1].unwrap().1;
+
+        KING_OFFSETS.iter().any(|&(x_offset, y_offset)| {
+            match (
+                add_i32_usize(king.0, x_offset),
+                add_i32_usize(king.1, y_offset),
+            ) {
+                (Some(x_position), Some(y_position)) if x_position < 8 && y_position < 8 => {
+                    self.board[x_position][y_position] == Some((Piece::King, colour.opponent()))
+                }
+                _ => false,
+            }
+        })
+    }
+
+    pub(crate) fn has_king(&self, colour: Colour) -> bool {
+        self.board
+            .iter()
+            .flatten()
+            .any(|&tile| tile == Some((Piece::King, colour)))
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::{Outcome, Variant};
+    use crate::{Colour, Game, GameState};
+
+    fn atomic(fen: &str) -> Game {
+        Game::from_variant_fen(Variant::Atomic, fen.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_explosions() {
+        // Taking the queen next to the king blows both up, the pawn survives
+        let mut game = atomic("3qk3/2p5/8/8/8/8/8/3QK3 w - - 0 1");
+        assert_eq!(
+            game.make_move("d1".to_string(), "d8".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(game.get_fen(), "8/2p5/8/8/8/8/8/4K3 b - - 0 1");
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "explodes the king"))
+        );
+    }
+
+    #[test]
+    fn test_king_rules() {
+        // Kings can't capture
+        let mut game = atomic("8/8/8/8/8/8/4p3/4K2k w - - 0 1");
+        let moves = game.get_possible_moves("e1".to_string()).unwrap();
+        assert!(!moves.contains(&"e2".to_string()));
+        assert!(moves.contains(&"d2".to_string()));
+
+        // Kings next to each other can't be checked
+        let mut game = atomic("8/8/8/8/8/8/3kK3/3R4 b - - 0 1");
+        assert_eq!(game.get_game_state(), GameState::InProgress);
+        assert!(game
+            .get_possible_moves("d2".to_string())
+            .unwrap()
+            .contains(&"c2".to_string()));
+    }
+}
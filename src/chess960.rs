@@ -0,0 +1,141 @@
+use crate::polyglot::split_mix;
+use crate::{Colour, Game, Piece, KINGSIDE, QUEENSIDE};
+
+// Where the two knights go among the five tiles left after placing the bishops and the queen
+const KNIGHT_TILES: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+impl Game {
+    /// Initialises a Chess960 board with the starting position of the given number (0-959),
+    /// returns None if the number is out of range. Position 518 is the normal starting position.
+    pub fn new_chess960(index: u16) -> Option<Game> {
+        if index >= 960 {
+            return None;
+        }
+
+        let pieces = chess960_back_rank(index as usize);
+        let mut game = Game::empty();
+        game.chess960 = true;
+
+        for (colour, main_row, pawn_row) in [(Colour::White, 0, 1), (Colour::Black, 7, 6)] {
+            for (_x, &piece) in pieces.iter().enumerate() {
+                game.board[_x][main_row] = Some((piece, colour));
+                game.board[_x][pawn_row] = Some((Piece::Pawn, colour));
+            }
+
+            // The king always stands between the two rooks
+            let mut rooks = (0..8).filter(|&_x| pieces[_x] == Piece::Rook);
+            game.castling_rooks[colour as usize][QUEENSIDE] = rooks.next();
+            game.castling_rooks[colour as usize][KINGSIDE] = rooks.next();
+        }
+
+        Some(game)
+    }
+
+    /// Initialises a Chess960 board with a starting position picked from a random seed.
+    pub fn new_chess960_random(seed: u64) -> Game {
+        Game::new_chess960((split_mix(seed) % 960) as u16).unwrap()
+    }
+}
+
+// The back rank of a starting position, using Scharnagl's numbering
+fn chess960_back_rank(index: usize) -> [Piece; 8] {
+    let mut pieces: [Option<Piece>; 8] = [None; 8];
+    let mut rest = index;
+
+    // Light squared bishop on b, d, f or h, then dark squared bishop on a, c, e or g
+    pieces[2 * (rest % 4) + 1] = Some(Piece::Bishop);
+    rest /= 4;
+    pieces[2 * (rest % 4)] = Some(Piece::Bishop);
+    rest /= 4;
+
+    let empty = |pieces: &[Option<Piece>; 8]| -> Vec<usize> {
+        (0..8).filter(|&_x| pieces[_x].is_none()).collect()
+    };
+
+    pieces[empty(&pieces)[rest % 6]] = Some(Piece::Queen);
+    rest /= 6;
+
+    let tiles = empty(&pieces);
+    let (first, second) = KNIGHT_TILES[rest];
+    pieces[tiles[first]] = Some(Piece::Knight);
+    pieces[tiles[second]] = Some(Piece::Knight);
+
+    // Rook, king and rook fill the last three tiles from left to right
+    for (tile, piece) in empty(&pieces)
+        .into_iter()
+        .zip([Piece::Rook, Piece::King, Piece::Rook])
+    {
+        pieces[tile] = Some(piece);
+    }
+
+    pieces.map(Option::unwrap)
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, GameState};
+
+    #[test]
+    fn test_chess960_positions() {
+        assert_eq!(
+            Game::new_chess960(518).unwrap().get_fen(),
+            Game::new().get_fen()
+        );
+        assert_eq!(
+            Game::new_chess960(0).unwrap().get_fen(),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+        assert_eq!(
+            Game::new_chess960(959).unwrap().get_shredder_fen(),
+            "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w CAca - 0 1"
+        );
+        assert!(Game::new_chess960(960).is_none());
+
+        let fen = Game::new_chess960_random(7).get_fen();
+        assert_eq!(Game::from_fen(fen.clone()).unwrap().get_fen(), fen);
+    }
+
+    #[test]
+    fn test_chess960_castling() {
+        // King on b1 castles queenside with the rook on a1, ending on c1 and d1, but can't
+        // castle kingside through the attacked e1
+        let mut game = Game::from_fen("4r1k1/8/8/8/8/8/8/RK5R w HA - 0 1".to_string()).unwrap();
+        assert_eq!(game.get_fen(), "4r1k1/8/8/8/8/8/8/RK5R w KQ - 0 1");
+
+        let moves = game.get_possible_moves("b1".to_string()).unwrap();
+        assert!(moves.contains(&"a1".to_string()));
+        assert!(!moves.contains(&"h1".to_string()));
+        assert_eq!(
+            game.make_move("b1".to_string(), "a1".to_string()),
+            Some(GameState::InProgress)
+        );
+        assert_eq!(game.get_fen(), "4r1k1/8/8/8/8/8/8/2KR3R b - - 1 1");
+        game.undo_move();
+        assert_eq!(game.get_fen(), "4r1k1/8/8/8/8/8/8/RK5R w KQ - 0 1");
+
+        // The king passes over its rook to castle kingside
+        let mut game = Game::from_fen("6k1/8/8/8/8/8/8/RK4R1 w KQ - 0 1".to_string()).unwrap();
+        assert_eq!(game.parse_san("O-O").map(|_move| _move.to), Some((6, 0)));
+        game.make_move("b1".to_string(), "g1".to_string());
+        assert_eq!(game.get_fen(), "6k1/8/8/8/8/8/8/R4RK1 b - - 1 1");
+
+        // An inner rook is written with its file in X-FEN
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1".to_string()).unwrap();
+        assert_eq!(game.get_fen(), "4k3/8/8/8/8/8/8/1R2K1RR w G - 0 1");
+    }
+}
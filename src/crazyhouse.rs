@@ -0,0 +1,235 @@
+use crate::{check_for_checked, parse_piece, parse_square, Colour, Game, GameState, Move, Piece};
+
+// Pieces in the order they are listed in a pocket
+pub(crate) const POCKET_PIECES: [Piece; 5] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+];
+
+impl Game {
+    /// If the current game state is InProgress and the drop is legal, put a piece from
+    /// the pocket on an empty tile and return the resulting state of the game.
+    /// Possible pieces: "Queen", "Rook", "Bishop", "Knight", "Pawn"
+    pub fn make_drop(&mut self, _piece: String, _to: String) -> Option<GameState> {
+        if self.state == GameState::Checkmate || self.state == GameState::GameOver {
+            return None;
+        }
+
+        // A pawn on the last row has to be promoted first
+        if self.move_made {
+            return None;
+        }
+
+        let piece = match _piece.as_str() {
+            "Pawn" => Piece::Pawn,
+            _ => parse_piece(&_piece)?,
+        };
+        let to = parse_square(&_to)?;
+        let _move = Move {
+            from: to,
+            to,
+            promotion: None,
+            drop: Some(piece),
+        };
+
+        if !self.get_drop_moves(self.turn, true).contains(&_move) {
+            return None;
+        }
+
+        Some(self.play_move(_move))
+    }
+
+    /// Get the pieces that a colour has captured and can drop, most valuable first.
+    pub fn get_pocket(&self, colour: Colour) -> Vec<Piece> {
+        POCKET_PIECES
+            .iter()
+            .flat_map(|&piece| {
+                std::iter::repeat_n(
+                    piece,
+                    self.pockets[colour as usize][piece as usize] as usize,
+                )
+            })
+            .collect()
+    }
+
+    // All drops that don't leave the colour's own king in check, or with counter_check that
+    // check both kings
+    pub(crate) fn get_drop_moves(&mut self, colour: Colour, counter_check: bool) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+
+        if self.move_made {
+            return moves;
+        }
+
+        for &piece in POCKET_PIECES.iter() {
+            if self.pockets[colour as usize][piece as usize] == 0 {
+                continue;
+            }
+
+            for _x in 0..8 {
+                for _y in 0..8 {
+                    // Pawns can never stand on the first or last row
+                    if self.board[_x][_y].is_some()
+                        || (piece == Piece::Pawn && (_y == 0 || _y == 7))
+                    {
+                        continue;
+                    }
+
+                    if !self.is_self_check_drop((piece, colour), (_x, _y), counter_check) {
+                        moves.push(Move {
+                            from: (_x, _y),
+                            to: (_x, _y),
+                            promotion: None,
+                            drop: Some(piece),
+                        });
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    // Test a drop the same way as is_self_check tests a move
+    fn is_self_check_drop(
+        &mut self,
+        piece: (Piece, Colour),
+        to: (usize, usize),
+        counter_check: bool,
+    ) -> bool {
+        self.board[to.0][to.1] = Some(piece);
+
+        let self_checked = check_for_checked(piece.1, self);
+        let checked_opponent =
+            counter_check && self_checked && check_for_checked(piece.1.opponent(), self);
+
+        self.board[to.0][to.1] = None;
+
+        self_checked && !checked_opponent
+    }
+
+    // Put a piece from the pocket on the board, after the position has been saved by push_move
+    pub(crate) fn push_drop(&mut self, _move: Move, piece: Piece) {
+        let colour = self.turn;
+        self.board[_move.to.0][_move.to.1] = Some((piece, colour));
+        self.pockets[colour as usize][piece as usize] -= 1;
+
+        self.double_move_position = (100, 100);
+        self.halfmove_clock += 1;
+
+        let variant = self.variant;
+        variant.after_move(self, _move, None);
+        self.change_turn();
+    }
+
+    // Crazyhouse gives the captured piece to the capturer, a promoted piece as a pawn
+    pub(crate) fn update_pockets(&mut self, _move: Move, captured: Option<(Piece, Colour)>) {
+        let (from, to) = (_move.from, _move.to);
+
+        if let Some((piece, colour)) = captured {
+            let piece = if self.promoted[to.0][to.1] {
+                Piece::Pawn
+            } else {
+                piece
+            };
+            self.pockets[colour.opponent() as usize][piece as usize] += 1;
+        }
+
+        // The mark follows the piece, and a pawn on the last row is about to be promoted
+        let promoting =
+            matches!(self.board[to.0][to.1], Some((Piece::Pawn, _))) && (to.1 == 0 || to.1 == 7);
+        self.promoted[to.0][to.1] =
+            self.promoted[from.0][from.1] || promoting || _move.promotion.is_some();
+        self.promoted[from.0][from.1] = false;
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::Variant;
+    use crate::{Colour, Game, GameState, Piece};
+
+    #[test]
+    fn test_captures_and_drops() {
+        let mut game = Game::new_variant(Variant::Crazyhouse);
+        for (from, to) in [("e2", "e4"), ("d7", "d5"), ("e4", "d5"), ("d8", "d5")] {
+            game.make_move(from.to_string(), to.to_string());
+        }
+
+        assert_eq!(game.get_pocket(Colour::White), vec![Piece::Pawn]);
+        assert_eq!(
+            game.get_fen(),
+            "rnb1kbnr/ppp1pppp/8/3q4/8/8/PPPP1PPP/RNBQKBNR[Pp] w KQkq - 0 3"
+        );
+
+        // Pawns can't be dropped on the last row, and only on empty tiles
+        assert_eq!(game.make_drop("Pawn".to_string(), "d8".to_string()), None);
+        assert_eq!(game.make_drop("Pawn".to_string(), "d5".to_string()), None);
+        assert_eq!(game.make_drop("Knight".to_string(), "f3".to_string()), None);
+        assert_eq!(
+            game.make_drop("Pawn".to_string(), "e6".to_string()),
+            Some(GameState::InProgress)
+        );
+        assert_eq!(
+            game.get_fen(),
+            "rnb1kbnr/ppp1pppp/4P3/3q4/8/8/PPPP1PPP/RNBQKBNR[p] b KQkq - 1 3"
+        );
+
+        game.undo_move();
+        assert_eq!(game.get_pocket(Colour::White), vec![Piece::Pawn]);
+    }
+
+    #[test]
+    fn test_promoted_pieces() {
+        let fen = "1Q~2k3/8/8/8/8/8/7K/1r6[] b - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, fen.to_string()).unwrap();
+        assert_eq!(game.get_fen(), fen);
+
+        // The queen was a pawn, so that is what black gets
+        game.make_move("b1".to_string(), "b8".to_string());
+        assert_eq!(game.get_fen(), "1r2k3/8/8/8/8/8/7K/8[p] w - - 0 2");
+
+        // Ninth row pockets are read as well, but not in variants without pockets
+        let fen = "4k3/8/8/8/8/8/8/4K3/Nq w - - 0 1";
+        let game = Game::from_variant_fen(Variant::Crazyhouse, fen.to_string()).unwrap();
+        assert_eq!(game.get_pocket(Colour::Black), vec![Piece::Queen]);
+        assert!(Game::from_fen(fen.to_string()).is_none());
+    }
+
+    #[test]
+    fn test_drops_stop_mate() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1[n] w - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("a1".to_string(), "a8".to_string()),
+            Some(GameState::Check)
+        );
+
+        assert!(game.parse_san("N@a5").is_none());
+        let _move = game.parse_san("N@f8").unwrap();
+        assert_eq!(game.format_san(_move), "N@f8");
+        assert_eq!(game.get_outcome(), None);
+    }
+
+    #[test]
+    fn test_no_drops_before_promotion() {
+        let fen = "4k3/P7/8/8/8/8/8/4K3[N] w - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Crazyhouse, fen.to_string()).unwrap();
+        game.make_move("a7".to_string(), "a8".to_string());
+        assert!(game.move_made);
+
+        assert!(game.get_drop_moves(Colour::White, false).is_empty());
+        assert_eq!(game.make_drop("Knight".to_string(), "d4".to_string()), None);
+        assert_eq!(game.get_pocket(Colour::White), vec![Piece::Knight]);
+
+        game.set_promotion("Queen".to_string());
+        assert_eq!(game.get_fen(), "Q~3k3/8/8/8/8/8/8/4K3[N] b - - 0 1");
+    }
+}
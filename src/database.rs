@@ -0,0 +1,661 @@
+use std::cmp::Reverse;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::{Colour, Game, Move, Piece};
+
+// Files start with this, the last byte being the version of the format
+const MAGIC: &[u8; 8] = b"MELGDB\0\x01";
+
+// Every index entry is a big endian key (8 bytes), game number (4) and move code (2)
+const INDEX_ENTRY_SIZE: usize = 14;
+
+// Move code of the last position of a game, which has no move after it
+const NO_MOVE: u16 = u16::MAX;
+
+static RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// Pieces for promotions and drops, numbered from 1 in move codes
+static CODE_PIECES: [Piece; 6] = [
+    Piece::Queen,
+    Piece::Rook,
+    Piece::Bishop,
+    Piece::Knight,
+    Piece::Pawn,
+    Piece::King,
+];
+
+// A game as it is stored, the moves as codes up to the first illegal one
+#[derive(Clone, Debug, PartialEq)]
+struct StoredGame {
+    tags: Vec<(String, String)>,
+    result: u8,
+    moves: Vec<u16>,
+}
+
+// A position reached in a game and the move played from it
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct IndexEntry {
+    key: u64,
+    game: u32,
+    code: u16,
+}
+
+/// How often a move was played from a position and how it did.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MoveStatistics {
+    pub chess_move: Move,
+    pub games: u32,
+    pub white_wins: u32,
+    pub draws: u32,
+    pub black_wins: u32,
+    /// Points scored by the side that played the move from 0 to 1, over the games with a
+    /// result.
+    pub score: f64,
+    /// Average rating of the players who played the move, from the WhiteElo and BlackElo tags.
+    pub average_rating: Option<u32>,
+}
+
+/// A collection of games that can be searched by position, stored in a compact binary
+/// format. Every position of every game is indexed by its Zobrist key, so transpositions are
+/// found as well.
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    games: Vec<StoredGame>,
+    index: Vec<IndexEntry>,
+}
+
+impl Database {
+    /// An empty database.
+    pub fn new() -> Database {
+        Database::default()
+    }
+
+    /// Read a database from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Database> {
+        Database::from_bytes(&fs::read(path)?)
+    }
+
+    /// Read a database from the contents of a file written by `save`.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Database> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a melg-chess game database"));
+        }
+
+        let mut database = Database::new();
+        for _ in 0..reader.u32()? {
+            let mut tags = Vec::new();
+            for _ in 0..reader.u16()? {
+                tags.push((reader.string()?, reader.string()?));
+            }
+
+            let result = reader.take(1)?[0];
+            if result as usize >= RESULTS.len() {
+                return Err(invalid_data("unknown game result"));
+            }
+
+            let mut moves = Vec::new();
+            for _ in 0..reader.u32()? {
+                moves.push(reader.u16()?);
+            }
+
+            database.games.push(StoredGame {
+                tags,
+                result,
+                moves,
+            });
+        }
+
+        let entries = reader.u32()? as usize;
+        if bytes.len() - reader.position != entries * INDEX_ENTRY_SIZE {
+            return Err(invalid_data("index has the wrong size"));
+        }
+        for _ in 0..entries {
+            database.index.push(IndexEntry {
+                key: reader.u64()?,
+                game: reader.u32()?,
+                code: reader.u16()?,
+            });
+        }
+
+        // Play out every game to make sure its moves are legal and the index is the one they
+        // give
+        let mut expected = Vec::new();
+        for (number, stored) in database.games.iter().enumerate() {
+            replay(stored, |game, code| {
+                expected.push(IndexEntry {
+                    key: game.get_zobrist_key(),
+                    game: number as u32,
+                    code,
+                })
+            })
+            .ok_or_else(|| invalid_data("game with an illegal move"))?;
+        }
+        sort_entries(&mut expected);
+        sort_entries(&mut database.index);
+        if database.index != expected {
+            return Err(invalid_data("index doesn't match the games"));
+        }
+
+        Ok(database)
+    }
+
+    /// Write the database in its binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+
+        bytes.extend_from_slice(&(self.games.len() as u32).to_be_bytes());
+        for stored in self.games.iter() {
+            bytes.extend_from_slice(&(stored.tags.len() as u16).to_be_bytes());
+            for (name, value) in stored.tags.iter() {
+                for text in [name, value] {
+                    bytes.extend_from_slice(&(text.len() as u16).to_be_bytes());
+                    bytes.extend_from_slice(text.as_bytes());
+                }
+            }
+
+            bytes.push(stored.result);
+            bytes.extend_from_slice(&(stored.moves.len() as u32).to_be_bytes());
+            for code in stored.moves.iter() {
+                bytes.extend_from_slice(&code.to_be_bytes());
+            }
+        }
+
+        bytes.extend_from_slice(&(self.index.len() as u32).to_be_bytes());
+        for entry in self.index.iter() {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.game.to_be_bytes());
+            bytes.extend_from_slice(&entry.code.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Write the database to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// The number of games in the database.
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Add a game with its moves up to the first illegal one. Returns false if the game was
+    /// skipped because its starting position can't be set up, or tags are too long to store.
+    pub fn add_game(&mut self, pgn_game: &PgnGame) -> bool {
+        let added = self.push_game(pgn_game);
+        sort_entries(&mut self.index);
+        added
+    }
+
+    /// Add all games in a PGN file, returns the number of games that were added.
+    pub fn add_pgn(&mut self, text: &str) -> usize {
+        let added = parse_pgn(text)
+            .iter()
+            .filter(|pgn_game| self.push_game(pgn_game))
+            .count();
+
+        // Sorting once for all games keeps adding a large file fast
+        sort_entries(&mut self.index);
+        added
+    }
+
+    // Add a game like add_game, leaving its index entries unsorted at the end of the index
+    fn push_game(&mut self, pgn_game: &PgnGame) -> bool {
+        let mut game = match pgn_game.get_start() {
+            Some(game) => game,
+            None => return false,
+        };
+
+        let too_long = pgn_game.tags.len() > u16::MAX as usize
+            || pgn_game.tags.iter().any(|(name, value)| {
+                name.len() > u16::MAX as usize || value.len() > u16::MAX as usize
+            });
+        if too_long {
+            return false;
+        }
+
+        let number = self.games.len() as u32;
+        let mut stored = StoredGame {
+            tags: pgn_game.tags.clone(),
+            result: RESULTS
+                .iter()
+                .position(|&result| result == pgn_game.result)
+                .unwrap_or(RESULTS.len() - 1) as u8,
+            moves: Vec::new(),
+        };
+
+        for san in pgn_game.moves.iter() {
+            let _move = match game.parse_san(san) {
+                Some(_move) => _move,
+                None => break,
+            };
+
+            let code = encode_move(_move);
+            self.index.push(IndexEntry {
+                key: game.get_zobrist_key(),
+                game: number,
+                code,
+            });
+            stored.moves.push(code);
+            game.play_move(_move);
+        }
+
+        self.index.push(IndexEntry {
+            key: game.get_zobrist_key(),
+            game: number,
+            code: NO_MOVE,
+        });
+        self.games.push(stored);
+
+        true
+    }
+
+    /// Get a game by its number, counting from 0 in the order the games were added.
+    pub fn get_game(&self, number: usize) -> Option<PgnGame> {
+        let stored = self.games.get(number)?;
+        let mut pgn_game = PgnGame {
+            tags: stored.tags.clone(),
+            moves: Vec::new(),
+            result: RESULTS[stored.result as usize].to_string(),
+        };
+
+        let mut moves = Vec::new();
+        replay(stored, |game, code| {
+            if let Some(_move) = decode_move(code) {
+                moves.push(game.format_san(_move));
+            }
+        })?;
+        pgn_game.moves = moves;
+
+        Some(pgn_game)
+    }
+
+    /// The numbers of the games that reach the current position of a game, in the order they
+    /// were added.
+    pub fn find_games(&self, game: &Game) -> Vec<usize> {
+        let mut numbers: Vec<usize> = self
+            .entries(game.get_zobrist_key())
+            .iter()
+            .map(|entry| entry.game as usize)
+            .collect();
+
+        numbers.sort_unstable();
+        numbers.dedup();
+        numbers
+    }
+
+    /// Statistics of the moves played from the current position of a game, the most played
+    /// first.
+    pub fn get_move_statistics(&self, game: &Game) -> Vec<MoveStatistics> {
+        let mut statistics: Vec<MoveStatistics> = Vec::new();
+        let mut ratings: Vec<(u64, u32)> = Vec::new();
+
+        let entries = self.entries(game.get_zobrist_key());
+        for (_i, entry) in entries.iter().enumerate() {
+            // A game that comes back to the position and plays the same move counts once, the
+            // order of the index puts its entries next to each other
+            if _i > 0 && entries[_i - 1].game == entry.game && entries[_i - 1].code == entry.code {
+                continue;
+            }
+
+            let chess_move = match decode_move(entry.code) {
+                Some(chess_move) => chess_move,
+                None => continue,
+            };
+            let index = match statistics
+                .iter()
+                .position(|statistic| statistic.chess_move == chess_move)
+            {
+                Some(index) => index,
+                None => {
+                    statistics.push(MoveStatistics {
+                        chess_move,
+                        games: 0,
+                        white_wins: 0,
+                        draws: 0,
+                        black_wins: 0,
+                        score: 0.0,
+                        average_rating: None,
+                    });
+                    ratings.push((0, 0));
+                    statistics.len() - 1
+                }
+            };
+
+            let stored = &self.games[entry.game as usize];
+            let statistic = &mut statistics[index];
+            statistic.games += 1;
+            match RESULTS[stored.result as usize] {
+                "1-0" => statistic.white_wins += 1,
+                "0-1" => statistic.black_wins += 1,
+                "1/2-1/2" => statistic.draws += 1,
+                _ => {}
+            }
+
+            let rating_tag = match game.turn {
+                Colour::White => "WhiteElo",
+                Colour::Black => "BlackElo",
+            };
+            let rating = stored
+                .tags
+                .iter()
+                .find(|(name, _)| name == rating_tag)
+                .and_then(|(_, value)| value.parse::<u32>().ok());
+            if let Some(rating) = rating {
+                ratings[index].0 += rating as u64;
+                ratings[index].1 += 1;
+            }
+        }
+
+        for (statistic, &(total, count)) in statistics.iter_mut().zip(ratings.iter()) {
+            let (wins, losses) = match game.turn {
+                Colour::White => (statistic.white_wins, statistic.black_wins),
+                Colour::Black => (statistic.black_wins, statistic.white_wins),
+            };
+            let decided = wins + statistic.draws + losses;
+            if decided > 0 {
+                statistic.score = (wins as f64 + statistic.draws as f64 / 2.0) / decided as f64;
+            }
+            if count > 0 {
+                statistic.average_rating = Some((total / count as u64) as u32);
+            }
+        }
+
+        statistics.sort_by_key(|statistic| Reverse(statistic.games));
+        statistics
+    }
+
+    fn entries(&self, key: u64) -> &[IndexEntry] {
+        let first = self.index.partition_point(|entry| entry.key < key);
+        let last = self.index.partition_point(|entry| entry.key <= key);
+        &self.index[first..last]
+    }
+}
+
+// Lookups depend on the entries being sorted by key, the rest of the order makes indexes of the
+// same games equal
+fn sort_entries(entries: &mut [IndexEntry]) {
+    entries.sort_unstable_by_key(|entry| (entry.key, entry.game, entry.code));
+}
+
+// Play the moves of a stored game from its starting position, passing every position to
+// visit with the code of the move played from it, NO_MOVE for the last one. None if the
+// position can't be set up or a move isn't legal.
+fn replay<F: FnMut(&mut Game, u16)>(stored: &StoredGame, mut visit: F) -> Option<()> {
+    let start = PgnGame {
+        tags: stored.tags.clone(),
+        moves: Vec::new(),
+        result: RESULTS[stored.result as usize].to_string(),
+    };
+    let mut game = start.get_start()?;
+
+    for &code in stored.moves.iter() {
+        let _move = decode_move(code)?;
+        if !game.get_legal_moves().contains(&_move) {
+            return None;
+        }
+        visit(&mut game, code);
+        game.play_move(_move);
+    }
+    visit(&mut game, NO_MOVE);
+
+    Some(())
+}
+
+// From and to tiles in 6 bits each, then the piece of a promotion or drop in 3 bits and
+// whether it's a drop
+fn encode_move(_move: Move) -> u16 {
+    let tile = |(x, y): (usize, usize)| (x + y * 8) as u16;
+    let piece = |piece: Option<Piece>| match piece {
+        Some(piece) => CODE_PIECES.iter().position(|&code| code == piece).unwrap() as u16 + 1,
+        None => 0,
+    };
+
+    match _move.drop {
+        Some(drop) => tile(_move.to) << 6 | piece(Some(drop)) << 12 | 1 << 15,
+        None => tile(_move.from) | tile(_move.to) << 6 | piece(_move.promotion) << 12,
+    }
+}
+
+// The reverse of encode_move, None for the end of a game
+fn decode_move(code: u16) -> Option<Move> {
+    if code == NO_MOVE {
+        return None;
+    }
+
+    let tile = |bits: u16| ((bits & 7) as usize, ((bits >> 3) & 7) as usize);
+    let piece = match (code >> 12) & 7 {
+        0 => None,
+        index => Some(*CODE_PIECES.get(index as usize - 1)?),
+    };
+    let to = tile(code >> 6);
+
+    if code >> 15 == 1 {
+        Some(Move {
+            from: to,
+            to,
+            promotion: None,
+            drop: piece,
+        })
+    } else {
+        Some(Move {
+            from: tile(code),
+            to,
+            promotion: piece,
+            drop: None,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Reads big endian numbers and strings, failing at the end of the data
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| invalid_data("game database ends too early"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.u16()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::{decode_move, encode_move, Database, INDEX_ENTRY_SIZE};
+    use crate::pgn::parse_pgn;
+    use crate::{Game, Move, Piece};
+
+    const GAMES: &str = r#"[White "A"]
+[Black "B"]
+[Result "1-0"]
+[WhiteElo "2000"]
+[BlackElo "1900"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[White "C"]
+[Black "D"]
+[Result "1/2-1/2"]
+[WhiteElo "2200"]
+
+1. Nf3 Nc6 2. e4 e5 3. Bb5 1/2-1/2
+
+[White "E"]
+[Black "F"]
+[Result "0-1"]
+
+1. d4 d5 0-1
+"#;
+
+    #[test]
+    fn test_move_codes() {
+        for text in ["e2e4", "e7e8q", "a2a1n", "e1g1", "N@f3", "P@a2"] {
+            let _move = Move::from_uci(text).unwrap();
+            assert_eq!(decode_move(encode_move(_move)), Some(_move));
+        }
+
+        let promotion = Move {
+            from: (7, 6),
+            to: (7, 7),
+            promotion: Some(Piece::King),
+            drop: None,
+        };
+        assert_eq!(decode_move(encode_move(promotion)), Some(promotion));
+    }
+
+    #[test]
+    fn test_position_search() {
+        let mut database = Database::new();
+        assert_eq!(database.add_pgn(GAMES), 3);
+
+        // Adding the games one at a time gives the same index
+        let mut one_by_one = Database::new();
+        for pgn_game in parse_pgn(GAMES) {
+            assert!(one_by_one.add_game(&pgn_game));
+        }
+        assert_eq!(one_by_one.to_bytes(), database.to_bytes());
+
+        // The first two games transpose into the same position
+        let mut game = Game::new();
+        for uci in ["e2e4", "e7e5", "g1f3", "b8c6"] {
+            game.make_uci_move(uci.to_string());
+        }
+        assert_eq!(database.find_games(&game), vec![0, 1]);
+        assert_eq!(database.find_games(&Game::new()), vec![0, 1, 2]);
+
+        let statistics = database.get_move_statistics(&Game::new());
+        assert_eq!(statistics.len(), 3);
+        let e4 = statistics
+            .iter()
+            .find(|statistic| statistic.chess_move.to_uci() == "e2e4")
+            .unwrap();
+        assert_eq!((e4.games, e4.white_wins, e4.draws), (1, 1, 0));
+        assert_eq!(e4.score, 1.0);
+        assert_eq!(e4.average_rating, Some(2000));
+
+        let statistics = database.get_move_statistics(&game);
+        assert_eq!(statistics.len(), 1);
+        assert_eq!(statistics[0].chess_move.to_uci(), "f1b5");
+        assert_eq!(statistics[0].score, 0.5);
+
+        // Black's ratings are used when black is to move
+        game.make_uci_move("f1b5".to_string());
+        assert!(database.get_move_statistics(&game).is_empty());
+        let mut game = Game::new();
+        game.make_uci_move("e2e4".to_string());
+        assert_eq!(
+            database.get_move_statistics(&game)[0].average_rating,
+            Some(1900)
+        );
+    }
+
+    #[test]
+    fn test_repeated_position() {
+        let mut database = Database::new();
+        database.add_pgn("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. e4 1-0\n");
+
+        let statistics = database.get_move_statistics(&Game::new());
+        assert_eq!(statistics.len(), 1);
+        assert_eq!((statistics[0].games, statistics[0].white_wins), (1, 1));
+
+        // Different moves from the same position count for each move
+        let mut game = Game::new();
+        for uci in ["g1f3", "g8f6"] {
+            game.make_uci_move(uci.to_string());
+        }
+        let statistics = database.get_move_statistics(&game);
+        assert_eq!(statistics.len(), 2);
+        assert!(statistics.iter().all(|statistic| statistic.games == 1));
+    }
+
+    #[test]
+    fn test_database_file() {
+        let mut database = Database::new();
+        database.add_pgn(GAMES);
+
+        let bytes = database.to_bytes();
+        let restored = Database::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored.get_game(1), database.get_game(1));
+        assert_eq!(
+            restored.get_game(1).unwrap().moves,
+            vec!["Nf3", "Nc6", "e4", "e5", "Bb5"]
+        );
+        assert_eq!(restored.get_game(2).unwrap().result, "0-1");
+        assert_eq!(restored.find_games(&Game::new()), vec![0, 1, 2]);
+        assert_eq!(restored.get_game(3), None);
+
+        // The positions before each of the 11 moves and the 3 final positions are indexed
+        let index_start = bytes.len() - 14 * INDEX_ENTRY_SIZE;
+        assert_eq!(bytes[index_start - 4..index_start], 14u32.to_be_bytes());
+
+        assert!(Database::from_bytes(b"not a database").is_err());
+        assert!(Database::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_illegal_moves() {
+        let mut database = Database::new();
+        database.add_pgn(GAMES);
+
+        // 1. e4 of the first game becomes a pawn jumping three tiles
+        let e4 = encode_move(Move::from_uci("e2e4").unwrap()).to_be_bytes();
+        let e5 = encode_move(Move::from_uci("e2e5").unwrap()).to_be_bytes();
+        let mut bytes = database.to_bytes();
+        let position = bytes.windows(2).position(|code| code == e4).unwrap();
+        bytes[position..position + 2].copy_from_slice(&e5);
+        let error = Database::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+        // An index that doesn't belong to the games
+        let mut bytes = database.to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(Database::from_bytes(&bytes).is_err());
+
+        database.games[2].moves[1] = database.games[2].moves[0];
+        assert_eq!(database.get_game(2), None);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::variant::Variant;
+use crate::Game;
+
+// One opening per line: the ECO code, the name and the moves from the starting position in
+// Standard Algebraic Notation, separated by tabs. The codes run from A00 to E99 and the names
+// use the "Family: Variation" layout of the lichess-org/chess-openings dataset (CC0), spelled in
+// British English. It isn't a verified copy of that dataset or of the ECO, so some lines may be
+// missing or named differently
+static OPENINGS: &str = include_str!("eco.tsv");
+
+/// An opening from the Encyclopaedia of Chess Openings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening {
+    /// The code of the opening such as "C42".
+    pub eco: String,
+    /// The name of the opening such as "Petrov's Defence".
+    pub name: String,
+}
+
+impl Opening {
+    /// The code followed by the name, such as "C42 Petrov's Defence".
+    pub fn to_text(&self) -> String {
+        format!("{} {}", self.eco, self.name)
+    }
+}
+
+/// Get the opening of the current position of a standard game, or None if the position isn't in
+/// the opening table.
+pub fn classify_position(game: &Game) -> Option<Opening> {
+    if game.variant != Variant::Standard {
+        return None;
+    }
+
+    table().get(&game.get_zobrist_key()).cloned()
+}
+
+/// Get the opening of a standard game from the last position of the game that is in the opening
+/// table. Positions are matched rather than moves, so move orders that transpose into an
+/// opening are found as well.
+pub fn classify(game: &Game) -> Option<Opening> {
+    let mut game = game.clone();
+
+    loop {
+        if let Some(opening) = classify_position(&game) {
+            return Some(opening);
+        }
+        game.undo_move()?;
+    }
+}
+
+// The openings by the Zobrist key of their position, read from the table the first time it's
+// needed. If a position appears twice the first line is used.
+fn table() -> &'static HashMap<u64, Opening> {
+    static TABLE: OnceLock<HashMap<u64, Opening>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = HashMap::new();
+
+        for line in OPENINGS.lines() {
+            let mut fields = line.split('\t');
+            let (eco, name, moves) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(eco), Some(name), Some(moves)) => (eco, name, moves),
+                _ => continue,
+            };
+
+            if let Some(game) = play_line(moves) {
+                table.entry(game.get_zobrist_key()).or_insert(Opening {
+                    eco: eco.to_string(),
+                    name: name.to_string(),
+                });
+            }
+        }
+
+        table
+    })
+}
+
+// Play the moves of a line of the table, None if one of them is illegal
+fn play_line(moves: &str) -> Option<Game> {
+    let mut game = Game::new();
+
+    for san in moves.split_whitespace() {
+        let _move = game.parse_san(san)?;
+        game.play_move(_move);
+    }
+
+    Some(game)
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{classify, classify_position, play_line, table, OPENINGS};
+    use crate::variant::Variant;
+    use crate::Game;
+
+    #[test]
+    fn test_opening_table() {
+        // Every line is legal and reaches a position of its own, sorted by code
+        let mut positions: HashMap<u64, &str> = HashMap::new();
+        let mut last_eco = "A00";
+        for line in OPENINGS.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            assert_eq!(fields.len(), 3, "{}", line);
+            assert!(fields[0] >= last_eco, "{}", line);
+            last_eco = fields[0];
+
+            let game = play_line(fields[2]).unwrap_or_else(|| panic!("{}", line));
+            if let Some(first) = positions.insert(game.get_zobrist_key(), line) {
+                panic!("{} reaches the same position as {}", line, first);
+            }
+        }
+        assert_eq!(table().len(), OPENINGS.lines().count());
+    }
+
+    #[test]
+    fn test_classify() {
+        let mut game = Game::new();
+        assert_eq!(classify(&game), None);
+
+        for uci in ["e2e4", "e7e5", "g1f3", "g8f6", "b1c3"] {
+            game.make_uci_move(uci.to_string());
+        }
+        assert_eq!(classify_position(&game), None);
+        assert_eq!(classify(&game).unwrap().to_text(), "C42 Petrov's Defence");
+
+        // The Semi-Slav reached through the Queen's Gambit Declined
+        let mut game = Game::new();
+        for uci in [
+            "d2d4", "d7d5", "c2c4", "e7e6", "b1c3", "g8f6", "g1f3", "c7c6",
+        ] {
+            game.make_uci_move(uci.to_string());
+        }
+        assert_eq!(classify(&game).unwrap().to_text(), "D43 Semi-Slav Defence");
+
+        let mut game = Game::new_variant(Variant::Atomic);
+        game.make_uci_move("e2e4".to_string());
+        assert_eq!(classify(&game), None);
+    }
+
+    #[test]
+    fn test_classify_deep_lines() {
+        assert!(OPENINGS.lines().count() > 4000);
+
+        // Moves past the end of the table keep the name of the last known position
+        let game = play_line(
+            "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6 Nf3 O-O Be2 e5 O-O Nc6 d5 Ne7 b4 Nh5 Re1 f5 Ng5 Nf6 f3",
+        )
+        .unwrap();
+        assert_eq!(
+            classify(&game).unwrap().to_text(),
+            "E97 King's Indian Defence: Orthodox Variation, Bayonet Attack, Nh5 Re1"
+        );
+
+        let game =
+            play_line("d4 d5 c4 e6 Nc3 Nf6 Nf3 Be7 Bf4 O-O e3 c5 dxc5 Bxc5 Qc2 Nc6 a3 Qa5 Rd1")
+                .unwrap();
+        assert_eq!(
+            classify(&game).unwrap().to_text(),
+            "D37 Queen's Gambit Declined: Harrwitz Attack, Main Line"
+        );
+
+        let game = play_line("d4 d5 c4 e6 Nc3 Nf6 Nf3 a6").unwrap();
+        assert_eq!(
+            classify(&game).unwrap().to_text(),
+            "D37 Queen's Gambit Declined"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use crate::search::{self, SearchLimits};
+use crate::Game;
+
+/// A position read from Extended Position Description, the first four fields of FEN followed
+/// by operations such as `bm Nf3; id "WAC.001";`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Epd {
+    /// Piece placement, side to move, castling rights and en passant tile.
+    pub position: String,
+    /// Opcodes with their operands in the order they were written, without the quotes around
+    /// strings.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    /// Get the operands of an operation.
+    pub fn get_operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Get the name of the position from the "id" operation.
+    pub fn id(&self) -> Option<&str> {
+        self.get_operation("id")?.first().map(|id| id.as_str())
+    }
+
+    /// The position as a game, with the clocks from the "hmvc" and "fmvn" operations.
+    pub fn to_game(&self) -> Option<Game> {
+        let clock = |opcode| match self.get_operation(opcode) {
+            Some(operands) => operands.first().cloned(),
+            None => None,
+        };
+
+        Game::from_fen(format!(
+            "{} {} {}",
+            self.position,
+            clock("hmvc").unwrap_or_else(|| "0".to_string()),
+            clock("fmvn").unwrap_or_else(|| "1".to_string())
+        ))
+    }
+
+    /// Write the position as a line of EPD.
+    pub fn to_epd(&self) -> String {
+        let mut text = self.position.clone();
+
+        for (opcode, operands) in self.operations.iter() {
+            text.push(' ');
+            text.push_str(opcode);
+
+            // Names and comments are always strings, other operands only when they have to be
+            let strings = opcode == "id" || (opcode.len() == 2 && opcode.starts_with('c'));
+            for operand in operands {
+                if strings
+                    || operand.is_empty()
+                    || operand.contains(|c: char| c.is_whitespace() || c == ';')
+                {
+                    text.push_str(&format!(" \"{}\"", operand));
+                } else {
+                    text.push_str(&format!(" {}", operand));
+                }
+            }
+            text.push(';');
+        }
+
+        text
+    }
+}
+
+/// Parse a line of Extended Position Description, returns None if it can't be parsed.
+pub fn parse_epd(line: &str) -> Option<Epd> {
+    let mut rest = line.trim();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+
+    let mut epd = Epd {
+        position: fields.join(" "),
+        operations: Vec::new(),
+    };
+    Game::from_fen(epd.position.clone())?;
+
+    let mut chars = rest.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        // Opcodes start with a letter and carry on with letters, digits and underscores
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|&c| c.is_ascii_alphanumeric() || c == '_') {
+            opcode.push(c);
+        }
+        if !opcode.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        // Operands run up to a semicolon, the last one of a line may be left out
+        let mut operands = Vec::new();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            match chars.next() {
+                None | Some(';') => break,
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            c => operand.push(c),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = c.to_string();
+                    while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+
+        epd.operations.push((opcode, operands));
+    }
+
+    Some(epd)
+}
+
+/// How the search did on one position of a test suite.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpdResult {
+    /// The "id" operation of the position.
+    pub id: Option<String>,
+    /// The move the search chose in Standard Algebraic Notation.
+    pub best_move: Option<String>,
+    /// If the move is one of the best moves ("bm") and none of the moves to avoid ("am").
+    pub solved: bool,
+}
+
+/// The results of running the search on a test suite.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SuiteReport {
+    pub results: Vec<EpdResult>,
+    /// Positions without any best moves or moves to avoid that can be played.
+    pub skipped: usize,
+}
+
+impl SuiteReport {
+    /// The number of positions that were solved.
+    pub fn solved(&self) -> usize {
+        self.results.iter().filter(|result| result.solved).count()
+    }
+}
+
+/// Search every position of a test suite with the same limits, calling `on_result` after each
+/// one, and count the positions where the search found a best move ("bm") and avoided the moves
+/// to avoid ("am").
+pub fn run_suite(
+    positions: &[Epd],
+    limits: SearchLimits,
+    on_result: &mut dyn FnMut(&EpdResult),
+) -> SuiteReport {
+    let mut report = SuiteReport::default();
+
+    for epd in positions {
+        let mut game = match epd.to_game() {
+            Some(game) => game,
+            None => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+
+        let mut moves = |opcode| -> Vec<_> {
+            epd.get_operation(opcode)
+                .unwrap_or(&[])
+                .iter()
+                .filter_map(|san| game.parse_san(san))
+                .collect()
+        };
+        let best_moves = moves("bm");
+        let avoid_moves = moves("am");
+        if best_moves.is_empty() && avoid_moves.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+
+        let best_move = search::search(&game, limits).best_move;
+        let result = EpdResult {
+            id: epd.id().map(|id| id.to_string()),
+            best_move: best_move.map(|_move| game.format_san(_move)),
+            solved: best_move.is_some_and(|_move| {
+                (best_moves.is_empty() || best_moves.contains(&_move))
+                    && !avoid_moves.contains(&_move)
+            }),
+        };
+
+        on_result(&result);
+        report.results.push(result);
+    }
+
+    report
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_epd, run_suite};
+    use crate::search::SearchLimits;
+
+    #[test]
+    fn test_parse_epd() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"mate; in 3\"; hmvc 4; fmvn 20;";
+        let epd = parse_epd(line).unwrap();
+        assert_eq!(
+            epd.position,
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - -"
+        );
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.get_operation("bm"), Some(&["Qg6".to_string()][..]));
+        assert_eq!(
+            epd.get_operation("c0"),
+            Some(&["mate; in 3".to_string()][..])
+        );
+        assert_eq!(
+            epd.to_game().unwrap().get_fen(),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 4 20"
+        );
+        assert_eq!(epd.to_epd(), line);
+
+        // Several operands, and no semicolon at the end
+        let epd = parse_epd("4k3/8/8/8/8/8/8/4K2R w K - bm O-O Rh8+").unwrap();
+        assert_eq!(
+            epd.get_operation("bm"),
+            Some(&["O-O".to_string(), "Rh8+".to_string()][..])
+        );
+
+        assert_eq!(parse_epd(""), None);
+        assert_eq!(parse_epd("8/8/8/8 w - -"), None);
+        assert_eq!(
+            parse_epd("4k3/8/8/8/8/8/8/4K3 w - - id \"unterminated;"),
+            None
+        );
+        assert_eq!(parse_epd("4k3/8/8/8/8/8/8/4K3 w - - 1bm e4;"), None);
+    }
+
+    #[test]
+    fn test_run_suite() {
+        let suite: Vec<_> = [
+            "6k1/5ppp/8/8/8/8/8/R6K w - - bm Ra8#; id \"back rank\";",
+            "6k1/5ppp/8/8/8/8/8/R6K w - - am Ra8; id \"avoid mate\";",
+            "6k1/5ppp/8/8/8/8/8/R6K w - - id \"no moves\";",
+            "6k1/5ppp/8/8/8/8/8/R6K w - - bm Qd4; id \"impossible move\";",
+        ]
+        .iter()
+        .map(|line| parse_epd(line).unwrap())
+        .collect();
+
+        let limits = SearchLimits {
+            depth: Some(2),
+            ..SearchLimits::default()
+        };
+        let mut seen = Vec::new();
+        let report = run_suite(&suite, limits, &mut |result| seen.push(result.clone()));
+
+        assert_eq!(report.results, seen);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.skipped, 2);
+        assert_eq!(report.solved(), 1);
+        assert_eq!(report.results[0].id, Some("back rank".to_string()));
+        assert_eq!(report.results[0].best_move, Some("Ra8#".to_string()));
+        assert!(!report.results[1].solved);
+    }
+}
@@ -0,0 +1,740 @@
+use crate::{Colour, GameState};
+
+/// The starting position of Capablanca chess on a 10x8 board.
+pub const CAPABLANCA_FEN: &str = "rnabqkbcnr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNABQKBCNR w";
+
+// Files are named by a single letter
+const MAX_WIDTH: usize = 26;
+
+// One part of a piece's movement, such as "fmW" for a pawn stepping forwards
+#[derive(Clone, Debug, PartialEq)]
+struct Atom {
+    // Steps written from white's side, black's go the other way
+    leaps: Vec<(i32, i32)>,
+    // How many leaps the piece can make in a row, 0 being as many as fit on the board
+    range: usize,
+    moves: bool,
+    captures: bool,
+    // Grasshoppers ride to a piece and land right behind it
+    hops: bool,
+    // Lame leaps can't jump over pieces on the way
+    lame: bool,
+    // Only allowed for a piece that hasn't moved yet
+    initial: bool,
+}
+
+/// A piece whose moves are written in Betza notation, e.g. "BN" for an archbishop that
+/// moves as a bishop or as a knight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FairyPiece {
+    pub name: String,
+    /// Letter used in FEN, upper case for white and lower case for black.
+    pub letter: char,
+    pub betza: String,
+    /// A royal piece can't be left in check, and mating it wins.
+    pub royal: bool,
+    /// A piece that promotes when it reaches the last rank, like a pawn.
+    pub promotes: bool,
+    atoms: Vec<Atom>,
+}
+
+impl FairyPiece {
+    /// Define a piece by its moves in Betza notation, returns None if they can't be parsed.
+    /// The atoms W, F, D, N, A, H, C, Z and G leap, K is WF and R, B and Q ride. Doubling an
+    /// atom makes it ride and a number limits how far. The modifiers are m (only moves),
+    /// c (only captures), f, b, v and s (directions), i (first move only), n (can't jump)
+    /// and g (hops over a piece like a grasshopper).
+    pub fn new(name: &str, letter: char, betza: &str) -> Option<FairyPiece> {
+        if !letter.is_ascii_alphabetic() {
+            return None;
+        }
+
+        Some(FairyPiece {
+            name: name.to_string(),
+            letter: letter.to_ascii_uppercase(),
+            betza: betza.to_string(),
+            royal: false,
+            promotes: false,
+            atoms: parse_betza(betza)?,
+        })
+    }
+}
+
+/// The standard pieces along with the archbishop (A), chancellor (C), amazon (M), camel (L)
+/// and grasshopper (G).
+pub fn fairy_pieces() -> Vec<FairyPiece> {
+    let mut pieces: Vec<FairyPiece> = [
+        ("King", 'K', "K"),
+        ("Queen", 'Q', "Q"),
+        ("Rook", 'R', "R"),
+        ("Bishop", 'B', "B"),
+        ("Knight", 'N', "N"),
+        ("Pawn", 'P', "fmWfcFifmnD"),
+        ("Archbishop", 'A', "BN"),
+        ("Chancellor", 'C', "RN"),
+        ("Amazon", 'M', "QN"),
+        ("Camel", 'L', "C"),
+        ("Grasshopper", 'G', "gQ"),
+    ]
+    .iter()
+    .map(|&(name, letter, betza)| FairyPiece::new(name, letter, betza).unwrap())
+    .collect();
+
+    pieces[0].royal = true;
+    pieces[5].promotes = true;
+    pieces
+}
+
+// Steps of a Betza atom before they are turned in every direction, and how far it rides
+fn atom_leaps(letter: char) -> Option<(Vec<(i32, i32)>, usize)> {
+    let leap = match letter {
+        'K' => return Some((vec![(1, 0), (1, 1)], 1)),
+        'Q' => return Some((vec![(1, 0), (1, 1)], 0)),
+        'R' => return Some((vec![(1, 0)], 0)),
+        'B' => return Some((vec![(1, 1)], 0)),
+        'W' => (1, 0),
+        'F' => (1, 1),
+        'D' => (2, 0),
+        'N' => (2, 1),
+        'A' => (2, 2),
+        'H' => (3, 0),
+        'C' => (3, 1),
+        'Z' => (3, 2),
+        'G' => (3, 3),
+        _ => return None,
+    };
+
+    Some((vec![leap], 1))
+}
+
+fn parse_betza(betza: &str) -> Option<Vec<Atom>> {
+    let chars: Vec<char> = betza.chars().collect();
+    let mut atoms: Vec<Atom> = Vec::new();
+    let mut _i = 0;
+
+    while _i < chars.len() {
+        let start = _i;
+        while _i < chars.len() && chars[_i].is_ascii_lowercase() {
+            _i += 1;
+        }
+        let modifiers: String = chars[start.._i].iter().collect();
+        if !modifiers
+            .chars()
+            .all(|modifier| "mcgnifbvs".contains(modifier))
+        {
+            return None;
+        }
+
+        let letter = *chars.get(_i)?;
+        _i += 1;
+        let (bases, mut range) = atom_leaps(letter)?;
+
+        // A doubled atom rides, a number says how far
+        if chars.get(_i) == Some(&letter) {
+            range = 0;
+            _i += 1;
+        } else {
+            let start = _i;
+            while _i < chars.len() && chars[_i].is_ascii_digit() {
+                _i += 1;
+            }
+            if _i > start {
+                range = chars[start.._i].iter().collect::<String>().parse().ok()?;
+            }
+        }
+
+        // Only straight and diagonal leaps have a path that pieces can block
+        let lame = modifiers.contains('n');
+        if lame && bases.iter().any(|&(x, y)| y != 0 && x != y) {
+            return None;
+        }
+
+        let directions: Vec<char> = modifiers.chars().filter(|m| "fbvs".contains(*m)).collect();
+        let mut leaps: Vec<(i32, i32)> = Vec::new();
+        for &(x, y) in bases.iter() {
+            for &(x_step, y_step) in [(x, y), (y, x)].iter() {
+                for &(x_sign, y_sign) in [(1, 1), (1, -1), (-1, 1), (-1, -1)].iter() {
+                    let leap = (x_step * x_sign, y_step * y_sign);
+                    let wanted = directions.is_empty()
+                        || directions.iter().any(|&direction| match direction {
+                            'f' => leap.1 > 0,
+                            'b' => leap.1 < 0,
+                            'v' => leap.0 == 0,
+                            _ => leap.1 == 0,
+                        });
+
+                    if wanted && !leaps.contains(&leap) {
+                        leaps.push(leap);
+                    }
+                }
+            }
+        }
+
+        let (moves, captures) = match (modifiers.contains('m'), modifiers.contains('c')) {
+            (false, false) => (true, true),
+            only => only,
+        };
+
+        atoms.push(Atom {
+            leaps,
+            range,
+            moves,
+            captures,
+            hops: modifiers.contains('g'),
+            lame,
+            initial: modifiers.contains('i'),
+        });
+    }
+
+    if atoms.is_empty() {
+        None
+    } else {
+        Some(atoms)
+    }
+}
+
+/// A game on a board of any size played with fairy pieces, for trying out new variants.
+/// It's separate from the 8x8 `Game` and has no castling or en passant. Stalemate ends the
+/// game as GameOver.
+#[derive(Clone, Debug)]
+pub struct FairyGame {
+    width: usize,
+    height: usize,
+    pieces: Vec<FairyPiece>,
+    // Index of the piece in pieces, the board is indexed by file and then rank
+    board: Vec<Vec<Option<(usize, Colour)>>>,
+    moved: Vec<Vec<bool>>,
+    turn: Colour,
+    state: GameState,
+    // Tile of a piece that reached the last rank and waits for set_promotion
+    promotion: Option<(usize, usize)>,
+}
+
+impl FairyGame {
+    /// Initialises a board from a position in Forsyth–Edwards Notation using the letters of
+    /// the pieces, the size of the board is given by the rows. Only the placement and the
+    /// colour to move are read, and all pieces count as not having moved. Returns None if
+    /// the position can't be parsed.
+    pub fn from_fen(pieces: Vec<FairyPiece>, fen: String) -> Option<FairyGame> {
+        let mut fields = fen.split_whitespace();
+        let rows: Vec<&str> = fields.next()?.split('/').collect();
+
+        let mut parsed_rows: Vec<Vec<Option<(usize, Colour)>>> = Vec::new();
+        for row in rows.iter() {
+            let mut tiles: Vec<Option<(usize, Colour)>> = Vec::new();
+            let mut empty = String::new();
+
+            for letter in row.chars() {
+                // Empty tiles can be counted with more than one digit, as in "10"
+                if letter.is_ascii_digit() {
+                    empty.push(letter);
+                    continue;
+                }
+                if !empty.is_empty() {
+                    push_empty(&mut tiles, &empty)?;
+                    empty.clear();
+                }
+
+                let index = pieces
+                    .iter()
+                    .position(|piece| piece.letter == letter.to_ascii_uppercase())?;
+                let colour = if letter.is_ascii_uppercase() {
+                    Colour::White
+                } else {
+                    Colour::Black
+                };
+                tiles.push(Some((index, colour)));
+            }
+            if !empty.is_empty() {
+                push_empty(&mut tiles, &empty)?;
+            }
+
+            parsed_rows.push(tiles);
+        }
+
+        // All rows need as many tiles as the first one
+        let (width, height) = (parsed_rows[0].len(), parsed_rows.len());
+        if width == 0 || width > MAX_WIDTH || parsed_rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+
+        // The top row comes first
+        let board: Vec<Vec<Option<(usize, Colour)>>> = (0..width)
+            .map(|_x| {
+                (0..height)
+                    .map(|_y| parsed_rows[height - 1 - _y][_x])
+                    .collect()
+            })
+            .collect();
+
+        let turn = match fields.next().unwrap_or("w") {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => return None,
+        };
+
+        let mut game = FairyGame {
+            width,
+            height,
+            pieces,
+            board,
+            moved: vec![vec![false; height]; width],
+            turn,
+            state: GameState::InProgress,
+            promotion: None,
+        };
+        game.update_state(turn);
+
+        Some(game)
+    }
+
+    /// Initialises a game of Capablanca chess, with an archbishop and a chancellor on a
+    /// 10x8 board.
+    pub fn capablanca() -> FairyGame {
+        FairyGame::from_fen(fairy_pieces(), CAPABLANCA_FEN.to_string()).unwrap()
+    }
+
+    /// Get the current position in Forsyth–Edwards Notation, with only the placement and the
+    /// colour to move.
+    pub fn get_fen(&self) -> String {
+        let mut rows: Vec<String> = Vec::new();
+
+        for _y in (0..self.height).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+
+            for _x in 0..self.width {
+                match self.board[_x][_y] {
+                    Some((index, colour)) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+
+                        let letter = self.pieces[index].letter;
+                        row.push(match colour {
+                            Colour::White => letter,
+                            Colour::Black => letter.to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+
+            rows.push(row);
+        }
+
+        let turn = match self.turn {
+            Colour::White => "w",
+            Colour::Black => "b",
+        };
+
+        format!("{} {}", rows.join("/"), turn)
+    }
+
+    /// Get the number of files and ranks of the board.
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Get the current game state.
+    pub fn get_game_state(&self) -> GameState {
+        self.state
+    }
+
+    /// If a piece of the side to move is standing on the given tile, returns all possible new
+    /// positions of that piece.
+    pub fn get_possible_moves(&mut self, _position: String) -> Option<Vec<String>> {
+        if self.state == GameState::Checkmate || self.state == GameState::GameOver {
+            return None;
+        }
+
+        let position = self.parse_square(&_position)?;
+        if !matches!(self.board[position.0][position.1], Some((_, colour)) if colour == self.turn) {
+            return None;
+        }
+
+        Some(
+            self.get_legal_moves(position)
+                .into_iter()
+                .map(format_square)
+                .collect(),
+        )
+    }
+
+    /// If the current game state is InProgress or Check and the move is legal, move a piece
+    /// and return the resulting state of the game. A pawn reaching the last rank has to be
+    /// promoted with set_promotion before the next move.
+    pub fn make_move(&mut self, _from: String, _to: String) -> Option<GameState> {
+        if self.state == GameState::Checkmate
+            || self.state == GameState::GameOver
+            || self.promotion.is_some()
+        {
+            return None;
+        }
+
+        let from = self.parse_square(&_from)?;
+        let to = self.parse_square(&_to)?;
+
+        if !matches!(self.board[from.0][from.1], Some((_, colour)) if colour == self.turn)
+            || !self.get_legal_moves(from).contains(&to)
+        {
+            return None;
+        }
+
+        self.board[to.0][to.1] = self.board[from.0][from.1].take();
+        self.moved[to.0][to.1] = true;
+        self.moved[from.0][from.1] = false;
+
+        // The turn only changes once the piece has been promoted
+        let (index, colour) = self.board[to.0][to.1].unwrap();
+        let last_rank = if colour == Colour::White {
+            self.height - 1
+        } else {
+            0
+        };
+        if self.pieces[index].promotes && to.1 == last_rank {
+            self.promotion = Some(to);
+        } else {
+            self.turn = self.turn.opponent();
+        }
+        self.update_state(colour.opponent());
+
+        Some(self.state)
+    }
+
+    /// Promote a pawn that has reached the last rank to the piece with the given name, such
+    /// as "Queen" or "Chancellor". Royal pieces and pawns can't be chosen. Returns the
+    /// resulting state of the game, or None if no pawn is waiting to be promoted.
+    pub fn set_promotion(&mut self, _piece: String) -> Option<GameState> {
+        let tile = self.promotion?;
+        let index = self
+            .pieces
+            .iter()
+            .position(|piece| piece.name == _piece && !piece.royal && !piece.promotes)?;
+
+        let colour = self.turn;
+        self.board[tile.0][tile.1] = Some((index, colour));
+        self.promotion = None;
+
+        self.turn = colour.opponent();
+        self.update_state(self.turn);
+
+        Some(self.state)
+    }
+
+    fn update_state(&mut self, colour: Colour) {
+        self.state = match (self.is_in_check(colour), self.has_legal_moves(colour)) {
+            (false, true) => GameState::InProgress,
+            (true, true) => GameState::Check,
+            (true, false) => GameState::Checkmate,
+            // Stalemate
+            (false, false) => GameState::GameOver,
+        };
+    }
+
+    fn parse_square(&self, input: &str) -> Option<(usize, usize)> {
+        let mut chars = input.chars();
+        let file = (chars.next()? as usize).checked_sub('a' as usize)?;
+
+        // Offset by one as ranks start at 1 whilst the board starts at 0
+        let rank = chars.as_str().parse::<usize>().ok()?.checked_sub(1)?;
+
+        if file >= self.width || rank >= self.height {
+            return None;
+        }
+
+        Some((file, rank))
+    }
+
+    fn offset(&self, tile: (usize, usize), leap: (i32, i32)) -> Option<(usize, usize)> {
+        let x_position = tile.0 as i32 + leap.0;
+        let y_position = tile.1 as i32 + leap.1;
+
+        if x_position < 0
+            || y_position < 0
+            || x_position >= self.width as i32
+            || y_position >= self.height as i32
+        {
+            return None;
+        }
+
+        Some((x_position as usize, y_position as usize))
+    }
+
+    // Check that no piece stands between the tile and where the leap ends
+    fn is_path_clear(&self, tile: (usize, usize), leap: (i32, i32)) -> bool {
+        let steps = leap.0.abs().max(leap.1.abs());
+        let step = (leap.0.signum(), leap.1.signum());
+
+        (1..steps).all(|_i| match self.offset(tile, (step.0 * _i, step.1 * _i)) {
+            Some((x_position, y_position)) => self.board[x_position][y_position].is_none(),
+            None => false,
+        })
+    }
+
+    // Moves of the piece on a tile without looking at check
+    fn get_piece_moves(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let (index, colour) = match self.board[from.0][from.1] {
+            Some(piece) => piece,
+            None => return Vec::new(),
+        };
+        let direction = if colour == Colour::White { 1 } else { -1 };
+        let mut moves: Vec<(usize, usize)> = Vec::new();
+
+        for atom in self.pieces[index].atoms.iter() {
+            if atom.initial && self.moved[from.0][from.1] {
+                continue;
+            }
+
+            for &(x_leap, y_leap) in atom.leaps.iter() {
+                let leap = (x_leap, y_leap * direction);
+                let mut tile = from;
+                let mut steps = 0;
+                let mut hopped = false;
+
+                loop {
+                    if atom.lame && !self.is_path_clear(tile, leap) {
+                        break;
+                    }
+                    tile = match self.offset(tile, leap) {
+                        Some(tile) => tile,
+                        None => break,
+                    };
+                    steps += 1;
+
+                    // Look for the piece to hop over before landing right behind it
+                    if atom.hops && !hopped {
+                        if self.board[tile.0][tile.1].is_some() {
+                            hopped = true;
+                        } else if steps == atom.range {
+                            break;
+                        }
+                        continue;
+                    }
+
+                    match self.board[tile.0][tile.1] {
+                        None => {
+                            if atom.moves {
+                                moves.push(tile);
+                            }
+                        }
+                        Some((_, owner)) => {
+                            if owner != colour && atom.captures {
+                                moves.push(tile);
+                            }
+                            break;
+                        }
+                    }
+
+                    if hopped || steps == atom.range {
+                        break;
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    // Moves of the piece on a tile that don't leave a royal piece of its colour in check
+    fn get_legal_moves(&mut self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let colour = match self.board[from.0][from.1] {
+            Some((_, colour)) => colour,
+            None => return Vec::new(),
+        };
+
+        let mut moves = self.get_piece_moves(from);
+        moves.retain(|&to| {
+            let captured = self.board[to.0][to.1];
+            self.board[to.0][to.1] = self.board[from.0][from.1].take();
+
+            let legal = !self.is_in_check(colour);
+
+            self.board[from.0][from.1] = self.board[to.0][to.1];
+            self.board[to.0][to.1] = captured;
+            legal
+        });
+
+        moves
+    }
+
+    fn has_legal_moves(&mut self, colour: Colour) -> bool {
+        for _x in 0..self.width {
+            for _y in 0..self.height {
+                if matches!(self.board[_x][_y], Some((_, owner)) if owner == colour)
+                    && !self.get_legal_moves((_x, _y)).is_empty()
+                {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn is_in_check(&self, colour: Colour) -> bool {
+        for _x in 0..self.width {
+            for _y in 0..self.height {
+                if !matches!(self.board[_x][_y], Some((_, owner)) if owner != colour) {
+                    continue;
+                }
+
+                let attacks_royal = self.get_piece_moves((_x, _y)).iter().any(|&(x, y)| {
+                    matches!(self.board[x][y], Some((index, owner))
+                        if owner == colour && self.pieces[index].royal)
+                });
+
+                if attacks_royal {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+fn format_square(position: (usize, usize)) -> String {
+    format!("{}{}", (b'a' + position.0 as u8) as char, position.1 + 1)
+}
+
+// Add the counted empty tiles to a row, checking the width before making room for them
+fn push_empty(tiles: &mut Vec<Option<(usize, Colour)>>, empty: &str) -> Option<()> {
+    let width = tiles.len().checked_add(empty.parse::<usize>().ok()?)?;
+    if width > MAX_WIDTH {
+        return None;
+    }
+
+    tiles.resize(width, None);
+    Some(())
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{fairy_pieces, FairyGame, FairyPiece, CAPABLANCA_FEN};
+    use crate::GameState;
+
+    fn move_count(fen: &str, tile: &str) -> usize {
+        let mut game = FairyGame::from_fen(fairy_pieces(), fen.to_string()).unwrap();
+        game.get_possible_moves(tile.to_string()).unwrap().len()
+    }
+
+    #[test]
+    fn test_betza_pieces() {
+        assert!(FairyPiece::new("Pawn", 'P', "fmWfcFifmnD").is_some());
+        assert!(FairyPiece::new("Nightrider", 'X', "NN").is_some());
+        assert!(FairyPiece::new("Unknown", 'X', "Y").is_none());
+        assert!(FairyPiece::new("Lame knight", 'X', "nN").is_none());
+
+        assert_eq!(move_count("8/8/8/8/3A4/8/8/8 w", "d4"), 21);
+        assert_eq!(move_count("8/8/8/8/3C4/8/8/8 w", "d4"), 22);
+        assert_eq!(move_count("8/8/8/8/3M4/8/8/8 w", "d4"), 35);
+        assert_eq!(move_count("8/8/8/8/8/8/8/L7 w", "a1"), 2);
+
+        // The grasshopper needs a piece to hop over
+        let mut game =
+            FairyGame::from_fen(fairy_pieces(), "8/8/8/8/8/3p4/8/3G4 w".to_string()).unwrap();
+        assert_eq!(
+            game.get_possible_moves("d1".to_string()),
+            Some(vec!["d4".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_capablanca() {
+        let mut game = FairyGame::capablanca();
+        assert_eq!(game.get_size(), (10, 8));
+        assert_eq!(game.get_fen(), CAPABLANCA_FEN);
+
+        assert_eq!(
+            game.get_possible_moves("j2".to_string()),
+            Some(vec!["j3".to_string(), "j4".to_string()])
+        );
+        assert_eq!(game.get_possible_moves("k2".to_string()), None);
+
+        // Boards are at most 26 files wide
+        for fen in [
+            "27/27 w",
+            "99999999999999/k w",
+            "99999999999999999999999999/k w",
+            "K99999999999999/k w",
+        ]
+        .iter()
+        {
+            assert!(FairyGame::from_fen(fairy_pieces(), fen.to_string()).is_none());
+        }
+        assert!(FairyGame::from_fen(fairy_pieces(), "K25/25k w".to_string()).is_some());
+        assert_eq!(
+            game.make_move("e2".to_string(), "e4".to_string()),
+            Some(GameState::InProgress)
+        );
+        assert_eq!(
+            game.get_fen(),
+            "rnabqkbcnr/pppppppppp/10/10/4P5/10/PPPP1PPPPP/RNABQKBCNR b"
+        );
+
+        // Only the side to move has moves, as in Game
+        assert_eq!(game.get_possible_moves("e4".to_string()), None);
+        assert_eq!(
+            game.get_possible_moves("e7".to_string()),
+            Some(vec!["e6".to_string(), "e5".to_string()])
+        );
+
+        // Pawns only step twice on their first move
+        game.make_move("i7".to_string(), "i6".to_string());
+        assert_eq!(game.make_move("e4".to_string(), "e6".to_string()), None);
+    }
+
+    #[test]
+    fn test_fairy_checkmate() {
+        let fen = "k9/10/1K8/10/10/10/10/2C7 w";
+        let mut game = FairyGame::from_fen(fairy_pieces(), fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("c1".to_string(), "c8".to_string()),
+            Some(GameState::Checkmate)
+        );
+        assert_eq!(game.make_move("a8".to_string(), "b8".to_string()), None);
+        assert_eq!(game.get_possible_moves("a8".to_string()), None);
+    }
+
+    #[test]
+    fn test_fairy_stalemate() {
+        let fen = "k9/10/1K8/10/10/10/10/2Q7 w";
+        let mut game = FairyGame::from_fen(fairy_pieces(), fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("c1".to_string(), "c7".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(game.make_move("a8".to_string(), "b8".to_string()), None);
+    }
+
+    #[test]
+    fn test_fairy_promotion() {
+        let fen = "k9/4P5/10/10/10/10/10/K9 w";
+        let mut game = FairyGame::from_fen(fairy_pieces(), fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("e7".to_string(), "e8".to_string()),
+            Some(GameState::InProgress)
+        );
+
+        // Nothing else can move until the pawn is promoted
+        assert_eq!(game.make_move("a1".to_string(), "a2".to_string()), None);
+        assert_eq!(game.set_promotion("King".to_string()), None);
+        assert_eq!(game.set_promotion("Pawn".to_string()), None);
+        assert_eq!(
+            game.set_promotion("Chancellor".to_string()),
+            Some(GameState::Check)
+        );
+        assert_eq!(game.get_fen(), "k3C5/10/10/10/10/10/10/K9 b");
+        assert_eq!(game.set_promotion("Queen".to_string()), None);
+    }
+}
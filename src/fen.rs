@@ -0,0 +1,428 @@
+use crate::variant::Variant;
+use crate::{format_square, parse_square, Colour, Game, Piece, KINGSIDE, QUEENSIDE};
+
+// Letter used for a piece in FEN, upper case for white and lower case for black
+pub(crate) fn piece_to_char(piece: (Piece, Colour)) -> char {
+    let letter = match piece.0 {
+        Piece::King => 'k',
+        Piece::Queen => 'q',
+        Piece::Rook => 'r',
+        Piece::Knight => 'n',
+        Piece::Bishop => 'b',
+        Piece::Pawn => 'p',
+    };
+
+    match piece.1 {
+        Colour::White => letter.to_ascii_uppercase(),
+        Colour::Black => letter,
+    }
+}
+
+pub(crate) fn char_to_piece(letter: char) -> Option<(Piece, Colour)> {
+    let piece = match letter.to_ascii_lowercase() {
+        'k' => Piece::King,
+        'q' => Piece::Queen,
+        'r' => Piece::Rook,
+        'n' => Piece::Knight,
+        'b' => Piece::Bishop,
+        'p' => Piece::Pawn,
+        _ => return None,
+    };
+
+    let colour = if letter.is_ascii_uppercase() {
+        Colour::White
+    } else {
+        Colour::Black
+    };
+
+    Some((piece, colour))
+}
+
+impl Game {
+    /// Initialises a board from a position in Forsyth–Edwards Notation,
+    /// returns None if the position can't be parsed.
+    pub fn from_fen(fen: String) -> Option<Game> {
+        Game::from_variant_fen(Variant::Standard, fen)
+    }
+
+    /// Initialises a board from a position in Forsyth–Edwards Notation that is played by the
+    /// rules of a variant, returns None if the position can't be parsed.
+    pub fn from_variant_fen(variant: Variant, fen: String) -> Option<Game> {
+        let mut game = Game::empty();
+        game.variant = variant;
+        let mut fields = fen.split_whitespace();
+
+        // Piece placement, starting at the top row. Pockets come last, either in brackets
+        // as in "RNBQKBNR[Qp]" or as a ninth row.
+        let mut placement = fields.next()?;
+        let mut pocket = None;
+        if let Some(start) = placement.find('[') {
+            pocket = Some(placement[start + 1..].strip_suffix(']')?);
+            placement = &placement[..start];
+        }
+
+        let mut rows: Vec<&str> = placement.split('/').collect();
+        if rows.len() == 9 && pocket.is_none() {
+            pocket = rows.pop();
+        }
+
+        if rows.len() != 8 || (pocket.is_some() && !variant.has_pockets()) {
+            return None;
+        }
+
+        for letter in pocket.unwrap_or("").chars() {
+            let (piece, colour) = char_to_piece(letter)?;
+            if piece == Piece::King {
+                return None;
+            }
+            game.pockets[colour as usize][piece as usize] += 1;
+        }
+
+        for (_i, row) in rows.iter().enumerate() {
+            let y_position = 7 - _i;
+            let mut x_position = 0;
+
+            for letter in row.chars() {
+                if let Some(empty) = letter.to_digit(10) {
+                    x_position += empty as usize;
+                    continue;
+                }
+
+                // A tilde marks the piece before it as a promoted pawn
+                if letter == '~' && variant.has_pockets() {
+                    if x_position == 0
+                        || x_position > 8
+                        || game.board[x_position - 1][y_position].is_none()
+                    {
+                        return None;
+                    }
+                    game.promoted[x_position - 1][y_position] = true;
+                    continue;
+                }
+
+                let piece = char_to_piece(letter)?;
+
+                // Pawns can never stand on the last row, and only in Horde on the first
+                if x_position >= 8
+                    || (piece.0 == Piece::Pawn && !variant.allows_pawn_on(piece.1, y_position))
+                {
+                    return None;
+                }
+
+                game.board[x_position][y_position] = Some(piece);
+                x_position += 1;
+            }
+
+            if x_position != 8 {
+                return None;
+            }
+        }
+
+        // Both sides need exactly one king, unless it's an ordinary piece in the variant
+        for colour in [Colour::White, Colour::Black] {
+            if !variant.is_royal(colour) {
+                continue;
+            }
+
+            let kings = game
+                .board
+                .iter()
+                .flatten()
+                .filter(|&&tile| tile == Some((Piece::King, colour)))
+                .count();
+
+            if kings != 1 {
+                return None;
+            }
+        }
+
+        game.turn = match fields.next().unwrap_or("w") {
+            "w" => Colour::White,
+            "b" => Colour::Black,
+            _ => return None,
+        };
+
+        // Castling rights are written as KQkq, where K and Q mean the outermost rook on that side
+        // of the king (X-FEN), or as the files of the rooks (Shredder-FEN). They are only kept if
+        // the king is on its first row and the rook is still there.
+        for letter in fields.next().unwrap_or("-").chars() {
+            if letter == '-' {
+                continue;
+            }
+
+            let colour = if letter.is_ascii_uppercase() {
+                Colour::White
+            } else {
+                Colour::Black
+            };
+            let row = if colour == Colour::White { 0 } else { 7 };
+            let rook = Some((Piece::Rook, colour));
+
+            let king_file =
+                match (0..8).find(|&_x| game.board[_x][row] == Some((Piece::King, colour))) {
+                    Some(king_file) => king_file,
+                    None => continue,
+                };
+
+            let rook_file = match letter.to_ascii_lowercase() {
+                'k' => (king_file + 1..8)
+                    .rev()
+                    .find(|&_x| game.board[_x][row] == rook),
+                'q' => (0..king_file).find(|&_x| game.board[_x][row] == rook),
+                file @ 'a'..='h' => Some(file as usize - 'a' as usize)
+                    .filter(|&_x| _x != king_file && game.board[_x][row] == rook),
+                _ => return None,
+            };
+
+            if let Some(rook_file) = rook_file {
+                let side = if rook_file > king_file {
+                    KINGSIDE
+                } else {
+                    QUEENSIDE
+                };
+                game.castling_rooks[colour as usize][side] = Some(rook_file);
+
+                // Castling from anywhere else than the normal starting tiles is Chess960
+                if king_file != 4 || (rook_file != 0 && rook_file != 7) {
+                    game.chess960 = true;
+                }
+            }
+        }
+
+        // The en passant tile is the one the pawn passed, store the pawn that can be taken
+        match fields.next().unwrap_or("-") {
+            "-" => {}
+            tile => {
+                let passed = parse_square(tile)?;
+                let (pawn_row, pawn_colour) = match passed.1 {
+                    2 => (3, Colour::White),
+                    5 => (4, Colour::Black),
+                    _ => return None,
+                };
+
+                if game.board[passed.0][pawn_row] == Some((Piece::Pawn, pawn_colour))
+                    && pawn_colour != game.turn
+                {
+                    game.double_move_position = (passed.0, pawn_row);
+                }
+            }
+        }
+
+        // Three-check writes the checks each side has left as "2+3" before the clocks, or
+        // the checks given as "+1+0" after them
+        let mut fields: Vec<&str> = fields.collect();
+        if variant == Variant::ThreeCheck {
+            let counters = match fields.iter().position(|field| field.contains('+')) {
+                Some(index) => fields.remove(index),
+                None => "+0+0",
+            };
+            let left = !counters.starts_with('+');
+
+            let mut counts = counters.trim_start_matches('+').split('+');
+            for colour in [Colour::White, Colour::Black] {
+                let count: u32 = counts.next()?.parse().ok()?;
+                game.checks[colour as usize] = if left { 3 - count.min(3) } else { count };
+            }
+        }
+
+        game.halfmove_clock = fields.first().unwrap_or(&"0").parse().ok()?;
+        // Moves are counted from 1
+        game.fullmove_number = fields
+            .get(1)
+            .unwrap_or(&"1")
+            .parse()
+            .ok()
+            .filter(|&number| number > 0)?;
+
+        game.update_state(game.turn);
+
+        Some(game)
+    }
+
+    /// Get the current position in Forsyth–Edwards Notation.
+    pub fn get_fen(&self) -> String {
+        let mut rows: Vec<String> = Vec::new();
+
+        for _y in (0..8).rev() {
+            let mut row = String::new();
+            let mut empty = 0;
+
+            for _x in 0..8 {
+                match self.board[_x][_y] {
+                    Some(piece) => {
+                        if empty > 0 {
+                            row.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        row.push(piece_to_char(piece));
+
+                        if self.promoted[_x][_y] {
+                            row.push('~');
+                        }
+                    }
+                    None => empty += 1,
+                }
+            }
+
+            if empty > 0 {
+                row.push_str(&empty.to_string());
+            }
+
+            rows.push(row);
+        }
+
+        let mut placement = rows.join("/");
+        if self.variant.has_pockets() {
+            placement.push('[');
+            for colour in [Colour::White, Colour::Black] {
+                for piece in self.get_pocket(colour) {
+                    placement.push(piece_to_char((piece, colour)));
+                }
+            }
+            placement.push(']');
+        }
+
+        let turn = match self.turn {
+            Colour::White => "w",
+            Colour::Black => "b",
+        };
+
+        let castling = self.get_castling_field(false);
+
+        let en_passant = match self.double_move_position {
+            (100, 100) => "-".to_string(),
+            (x_position, 3) => format_square((x_position, 2)),
+            (x_position, _) => format_square((x_position, 5)),
+        };
+
+        let mut en_passant_and_checks = en_passant;
+        if self.variant == Variant::ThreeCheck {
+            en_passant_and_checks.push_str(&format!(
+                " {}+{}",
+                3 - self.checks[Colour::White as usize].min(3),
+                3 - self.checks[Colour::Black as usize].min(3)
+            ));
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            turn,
+            castling,
+            en_passant_and_checks,
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+
+    /// Get the current position in Shredder-FEN, which writes castling rights as the files
+    /// of the rooks, e.g. HAha instead of KQkq.
+    pub fn get_shredder_fen(&self) -> String {
+        let mut fields: Vec<String> = self.get_fen().split(' ').map(String::from).collect();
+        fields[2] = self.get_castling_field(true);
+        fields.join(" ")
+    }
+
+    // Castling rights in X-FEN, with a file letter only for a rook that isn't the outermost one,
+    // or in Shredder-FEN with file letters for all rooks
+    fn get_castling_field(&self, shredder: bool) -> String {
+        let mut castling = String::new();
+
+        for colour in [Colour::White, Colour::Black] {
+            let row = if colour == Colour::White { 0 } else { 7 };
+            let rook = Some((Piece::Rook, colour));
+
+            for side in [KINGSIDE, QUEENSIDE] {
+                let rook_file = match self.castling_rooks[colour as usize][side] {
+                    Some(rook_file) => rook_file,
+                    None => continue,
+                };
+
+                // Rooks further out on the same side make K or Q ambiguous
+                let outermost = if side == KINGSIDE {
+                    (rook_file + 1..8).all(|_x| self.board[_x][row] != rook)
+                } else {
+                    (0..rook_file).all(|_x| self.board[_x][row] != rook)
+                };
+
+                let letter = if shredder || !outermost {
+                    (b'a' + rook_file as u8) as char
+                } else if side == KINGSIDE {
+                    'k'
+                } else {
+                    'q'
+                };
+
+                castling.push(match colour {
+                    Colour::White => letter.to_ascii_uppercase(),
+                    Colour::Black => letter,
+                });
+            }
+        }
+
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        castling
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::Variant;
+    use crate::{Game, GameState};
+
+    #[test]
+    fn test_fen_round_trip() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Game::new().get_fen(), start);
+        assert_eq!(Game::from_fen(start.to_string()).unwrap().get_fen(), start);
+
+        let mut game = Game::new();
+        game.make_move("e2".to_string(), "e4".to_string());
+        assert_eq!(
+            game.get_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        let position = "r3k2r/8/8/8/8/8/8/4K3 b kq - 3 40";
+        assert_eq!(
+            Game::from_fen(position.to_string()).unwrap().get_fen(),
+            position
+        );
+    }
+
+    #[test]
+    fn test_invalid_fen() {
+        assert!(Game::from_fen("".to_string()).is_none());
+        assert!(Game::from_fen("8/8/8/8/8/8/8/8 w - - 0 1".to_string()).is_none());
+        assert!(
+            Game::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1".to_string()).is_none()
+        );
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/P3K3 w - - 0 1".to_string()).is_none());
+        assert!(Game::from_fen("4k3/8/8/8/8/8/8/4K2Q w - - 0 0".to_string()).is_none());
+
+        // A tilde after a row that is already too long
+        for fen in [
+            "4k3/8/8/8/8/8/8/9~ w - - 0 1",
+            "4k3/8/8/8/8/8/8/Q8~ w - - 0 1",
+        ] {
+            assert!(Game::from_variant_fen(Variant::Crazyhouse, fen.to_string()).is_none());
+        }
+    }
+
+    #[test]
+    fn test_fen_game_state() {
+        // Fool's mate
+        let game = Game::from_fen(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string(),
+        )
+        .unwrap();
+        assert_eq!(game.get_game_state(), GameState::Checkmate);
+    }
+}
@@ -0,0 +1,254 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+use crate::play::find_move;
+use crate::xboard::game_result;
+use crate::{Game, GameState};
+
+/// A game behind an opaque pointer, made by `melg_game_new` or `melg_game_from_fen` and
+/// released with `melg_game_free`
+pub struct MelgGame {
+    game: Game,
+}
+
+/// The state of a game, or that a move was rejected
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MelgState {
+    InProgress,
+    Check,
+    Checkmate,
+    GameOver,
+    IllegalMove,
+}
+
+impl From<GameState> for MelgState {
+    fn from(state: GameState) -> MelgState {
+        match state {
+            GameState::InProgress => MelgState::InProgress,
+            GameState::Check => MelgState::Check,
+            GameState::Checkmate => MelgState::Checkmate,
+            GameState::GameOver => MelgState::GameOver,
+        }
+    }
+}
+
+/// Start a game from the starting position
+#[no_mangle]
+pub extern "C" fn melg_game_new() -> *mut MelgGame {
+    Box::into_raw(Box::new(MelgGame { game: Game::new() }))
+}
+
+/// Start a game from a position in Forsyth–Edwards Notation, returns NULL if it can't be parsed
+///
+/// # Safety
+/// `fen` must be NULL or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_from_fen(fen: *const c_char) -> *mut MelgGame {
+    match read_string(fen).and_then(|fen| Game::from_fen(fen.to_string())) {
+        Some(game) => Box::into_raw(Box::new(MelgGame { game })),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Release a game, NULL is ignored
+///
+/// # Safety
+/// `game` must be NULL or a game that hasn't been released yet.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_free(game: *mut MelgGame) {
+    if !game.is_null() {
+        drop(Box::from_raw(game));
+    }
+}
+
+/// Play a move in coordinate notation such as "e2e4" or "e7e8q", or in Standard Algebraic
+/// Notation, and return the resulting state, `MELG_STATE_ILLEGAL_MOVE` if it can't be played
+///
+/// # Safety
+/// `game` must be NULL or a live game, `text` NULL or a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_make_move(
+    game: *mut MelgGame,
+    text: *const c_char,
+) -> MelgState {
+    let (game, text) = match (game.as_mut(), read_string(text)) {
+        (Some(game), Some(text)) => (&mut game.game, text),
+        _ => return MelgState::IllegalMove,
+    };
+    if game_result(game).is_some() {
+        return MelgState::IllegalMove;
+    }
+
+    match find_move(game, text) {
+        Some(_move) => game.play_move(_move).into(),
+        None => MelgState::IllegalMove,
+    }
+}
+
+/// Take back the last move, returns false if there is none
+///
+/// # Safety
+/// `game` must be NULL or a live game.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_undo(game: *mut MelgGame) -> bool {
+    match game.as_mut() {
+        Some(game) => game.game.undo_move().is_some(),
+        None => false,
+    }
+}
+
+/// The current state of a game
+///
+/// # Safety
+/// `game` must be a live game.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_state(game: *const MelgGame) -> MelgState {
+    (*game).game.get_game_state().into()
+}
+
+/// Write the position in Forsyth–Edwards Notation into a buffer of `size` bytes, see
+/// `melg_game_legal_moves` for how the buffer is filled
+///
+/// # Safety
+/// `game` must be a live game and `buffer` NULL or writable for `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_fen(
+    game: *const MelgGame,
+    buffer: *mut c_char,
+    size: usize,
+) -> usize {
+    write_string(&(*game).game.get_fen(), buffer, size)
+}
+
+/// Write the legal moves in coordinate notation, separated by spaces, into a buffer of `size`
+/// bytes. The text is cut short if it doesn't fit and always ends with a NUL. Returns the length
+/// of the whole text, so a buffer of at least one more byte than that holds all of it.
+///
+/// # Safety
+/// `game` must be a live game and `buffer` NULL or writable for `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn melg_game_legal_moves(
+    game: *mut MelgGame,
+    buffer: *mut c_char,
+    size: usize,
+) -> usize {
+    let game = &mut (*game).game;
+    let moves = game
+        .get_legal_moves()
+        .into_iter()
+        .map(|_move| _move.to_uci())
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    write_string(&moves, buffer, size)
+}
+
+unsafe fn read_string<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        return None;
+    }
+    CStr::from_ptr(text).to_str().ok()
+}
+
+unsafe fn write_string(text: &str, buffer: *mut c_char, size: usize) -> usize {
+    if !buffer.is_null() && size > 0 {
+        let length = text.len().min(size - 1);
+        ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, length);
+        *buffer.add(length) = 0;
+    }
+
+    text.len()
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    fn read_buffer(buffer: &[c_char]) -> String {
+        unsafe { CStr::from_ptr(buffer.as_ptr()) }
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_c_api() {
+        unsafe {
+            let game = melg_game_new();
+            let mut buffer = [0 as c_char; 128];
+
+            // The length is returned even when the buffer is too small
+            let length = melg_game_legal_moves(game, buffer.as_mut_ptr(), 5);
+            assert_eq!(read_buffer(&buffer).len(), 4);
+            assert_eq!(length, 20 * 5 - 1);
+            melg_game_legal_moves(game, buffer.as_mut_ptr(), buffer.len());
+            assert!(read_buffer(&buffer).split(' ').any(|_move| _move == "g1f3"));
+
+            let _move = CString::new("e2e4").unwrap();
+            assert_eq!(
+                melg_game_make_move(game, _move.as_ptr()),
+                MelgState::InProgress
+            );
+            let _move = CString::new("Nf6").unwrap();
+            assert_eq!(
+                melg_game_make_move(game, _move.as_ptr()),
+                MelgState::InProgress
+            );
+            let _move = CString::new("e4e6").unwrap();
+            assert_eq!(
+                melg_game_make_move(game, _move.as_ptr()),
+                MelgState::IllegalMove
+            );
+            assert!(melg_game_undo(game));
+
+            melg_game_fen(game, buffer.as_mut_ptr(), buffer.len());
+            assert_eq!(
+                read_buffer(&buffer),
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+            );
+            melg_game_free(game);
+        }
+    }
+
+    #[test]
+    fn test_c_api_bad_input() {
+        unsafe {
+            let fen = CString::new("8/8/8").unwrap();
+            assert!(melg_game_from_fen(fen.as_ptr()).is_null());
+            assert!(melg_game_from_fen(ptr::null()).is_null());
+            melg_game_free(ptr::null_mut());
+
+            let fen = CString::new("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").unwrap();
+            let game = melg_game_from_fen(fen.as_ptr());
+            assert_eq!(
+                melg_game_make_move(game, ptr::null()),
+                MelgState::IllegalMove
+            );
+            let _move = CString::new("Ra8").unwrap();
+            assert_eq!(
+                melg_game_make_move(game, _move.as_ptr()),
+                MelgState::Checkmate
+            );
+            assert_eq!(melg_game_state(game), MelgState::Checkmate);
+            assert_eq!(melg_game_legal_moves(game, ptr::null_mut(), 0), 0);
+            melg_game_free(game);
+        }
+    }
+
+    // The committed header has to be regenerated when the C API changes
+    #[test]
+    fn test_header_up_to_date() {
+        assert_eq!(
+            include_str!("../include/melg_chess.h"),
+            include_str!(concat!(env!("OUT_DIR"), "/melg_chess.h")),
+            "include/melg_chess.h is out of date, copy it from the build's OUT_DIR"
+        );
+    }
+}
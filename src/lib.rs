@@ -1,6 +1,46 @@
-use std::{cmp::min, vec};
+use std::cmp::{max, min};
+
+#[cfg(feature = "animation")]
+pub mod animation;
+mod antichess;
+mod atomic;
+mod chess960;
+mod crazyhouse;
+pub mod database;
+pub mod eco;
+pub mod epd;
+pub mod fairy;
+mod fen;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod pgn;
+pub mod play;
+pub mod polyglot;
+#[cfg(feature = "python")]
+pub mod python;
+mod racing_kings;
+mod san;
+#[cfg(feature = "serde")]
+mod saved;
+pub mod search;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod svg;
+pub mod syzygy;
+pub mod text;
+#[cfg(feature = "tui")]
+pub mod tui;
+mod uci;
+pub mod variant;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod xboard;
+mod zobrist;
+
+use variant::{Outcome, Variant};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameState {
     InProgress,
     Check,
@@ -8,17 +48,29 @@ pub enum GameState {
     GameOver,
 }
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Colour {
     White,
     Black,
 }
 
+impl Colour {
+    /// Get the colour of the other player.
+    pub fn opponent(&self) -> Colour {
+        match self {
+            Colour::White => Colour::Black,
+            Colour::Black => Colour::White,
+        }
+    }
+}
+
 // Rook = Torn
 // Knight = Häst
 // Bishop = Löpare
 // Pawn = Bonde
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Piece {
     King,
     Queen,
@@ -28,14 +80,39 @@ pub enum Piece {
     Pawn,
 }
 
+/// A move of a piece between two tiles, given as (file, rank) indices from 0 to 7.
+/// Castling is described as the king moving two tiles towards the rook, or in Chess960 as
+/// the king moving onto the rook.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Move {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    pub promotion: Option<Piece>,
+    /// A piece put on the board from the pocket in Crazyhouse, `from` is then the same as `to`.
+    pub drop: Option<Piece>,
+}
+
+// Which of the moves a piece can make get_available_moves keeps
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum MoveFilter {
+    // Every move, without castling, as used to look for attacks
+    All,
+    // Moves the variant allows, where a move that leaves the own king in check is still allowed
+    // if it checks the opponent's king as well. This is the rule of make_move.
+    CounterCheck,
+    // Moves the variant allows by the standard rules, as used by the engine and the protocols
+    Legal,
+}
+
 impl Piece {
     fn get_available_moves(
         &self,
         position: (usize, usize),
-        check_for_check: bool,
+        filter: MoveFilter,
         game: &mut Game,
     ) -> Vec<(usize, usize)> {
-        let available_moves: Vec<(usize, usize)> = match self {
+        let mut available_moves: Vec<(usize, usize)> = match self {
             Piece::King => self.get_king_movement(position, game),
             Piece::Queen => {
                 let mut movements: Vec<(usize, usize)> =
@@ -51,47 +128,21 @@ impl Piece {
             Piece::Pawn => self.get_pawn_movement(position, game),
         };
 
-        let mut valid_moves: Vec<(usize, usize)> = Default::default();
-
-        if check_for_check {
-            for _move in available_moves.iter() {
-                // Test move
-                let piece = game.board[position.0][position.1]
-                    .as_ref()
-                    .unwrap()
-                    .to_owned();
-                let target_piece = game.board[_move.0][_move.1];
-
-                game.board[_move.0][_move.1] = Some(piece);
-                game.board[position.0][position.1] = None;
-
-                // Check for check
-                let self_checked = check_for_checked(piece.1, game);
-
-                let checked_opponent = check_for_checked(
-                    if piece.1 == Colour::White {
-                        Colour::Black
-                    } else {
-                        Colour::White
-                    },
-                    game,
-                );
+        if filter != MoveFilter::All {
+            // Castling never captures, so it's only needed when looking for legal moves
+            if *self == Piece::King {
+                available_moves.append(&mut self.get_castling_movement(position, game));
+            }
 
-                // Revert move
-                game.board[position.0][position.1] = Some(piece);
+            // Only keep moves that the variant allows, normally those that don't leave the own
+            // king in check unless they check the opponent
+            let mut valid_moves: Vec<(usize, usize)> = Default::default();
+            let variant = game.variant;
 
-                if target_piece.is_none() {
-                    game.board[_move.0][_move.1] = None;
-                } else {
-                    game.board[_move.0][_move.1] = Some(target_piece.unwrap());
-                }
-
-                // Only add valid moves
-                if self_checked && !checked_opponent {
-                    continue;
+            for _move in available_moves.iter() {
+                if variant.allows_move(game, position, *_move, filter == MoveFilter::CounterCheck) {
+                    valid_moves.push(_move.to_owned());
                 }
-
-                valid_moves.push(_move.to_owned());
             }
 
             return valid_moves;
@@ -101,56 +152,78 @@ impl Piece {
     }
 
     fn get_king_movement(&self, position: (usize, usize), game: &mut Game) -> Vec<(usize, usize)> {
-        let mut movements: Vec<(usize, usize)> = Vec::default();
         let colour = game.board[position.0][position.1].as_ref().unwrap().1;
 
         // Circle around the king
-        let offsets = vec![
-            (-1, 0),
-            (-1, 1),
-            (0, 1),
-            (1, 1),
-            (1, 0),
-            (1, -1),
-            (0, -1),
-            (-1, -1),
-        ];
-
-        movements.append(&mut self.get_movements_from_array(position, offsets, colour, game));
-
-        movements
+        self.get_movements_from_array(position, &KING_OFFSETS, colour, game)
     }
 
-    fn get_knight_movement(
+    // Castling puts the king on the g or c file and the rook next to it, using a rook that
+    // hasn't moved yet. In Chess960 the move is written as the king taking its own rook.
+    fn get_castling_movement(
         &self,
         position: (usize, usize),
         game: &mut Game,
     ) -> Vec<(usize, usize)> {
         let mut movements: Vec<(usize, usize)> = Vec::default();
         let colour = game.board[position.0][position.1].as_ref().unwrap().1;
+        let row = if colour == Colour::White { 0 } else { 7 };
 
-        // Circle around the king
-        let offsets = vec![
-            (-2, 1),
-            (-1, 2),
-            (1, 2),
-            (2, 1),
-            (2, -1),
-            (1, -2),
-            (-1, -2),
-            (-2, -1),
-        ];
-
-        movements.append(&mut self.get_movements_from_array(position, offsets, colour, game));
+        // Castling is not allowed out of check
+        if position.1 != row || check_for_checked(colour, game) {
+            return movements;
+        }
+
+        for side in [KINGSIDE, QUEENSIDE] {
+            let rook_file = match game.castling_rooks[colour as usize][side] {
+                Some(rook_file) => rook_file,
+                None => continue,
+            };
+
+            if game.board[rook_file][row] != Some((Piece::Rook, colour)) {
+                continue;
+            }
+
+            let (king_target, rook_target) = if side == KINGSIDE { (6, 5) } else { (2, 3) };
+            let span = |a: usize, b: usize| min(a, b)..=max(a, b);
+
+            // All tiles the king and rook pass must be empty, apart from the two of them
+            let blocked = span(position.0, king_target)
+                .chain(span(rook_file, rook_target))
+                .any(|_x| _x != position.0 && _x != rook_file && game.board[_x][row].is_some());
+
+            // The king may not pass through an attacked tile
+            let attacked = span(position.0, king_target)
+                .any(|_x| _x != position.0 && is_attacked((_x, row), colour.opponent(), game));
+
+            if !blocked && !attacked {
+                if game.chess960 {
+                    movements.push((rook_file, row));
+                } else {
+                    movements.push((king_target, row));
+                }
+            }
+        }
 
         movements
     }
 
+    fn get_knight_movement(
+        &self,
+        position: (usize, usize),
+        game: &mut Game,
+    ) -> Vec<(usize, usize)> {
+        let colour = game.board[position.0][position.1].as_ref().unwrap().1;
+
+        // Jump in an L shape
+        self.get_movements_from_array(position, &KNIGHT_OFFSETS, colour, game)
+    }
+
     // Convert an array to single specific moves
     fn get_movements_from_array(
         &self,
         position: (usize, usize),
-        offsets: Vec<(i32, i32)>,
+        offsets: &[(i32, i32)],
         colour: Colour,
         game: &mut Game,
     ) -> Vec<(usize, usize)> {
@@ -160,10 +233,10 @@ impl Piece {
         for _offset in offsets.iter() {
             let x_position = add_i32_usize(position.0, _offset.0);
             let y_position = add_i32_usize(position.1, _offset.1);
-            if !x_position.is_none() && !y_position.is_none() {
+            if let (Some(x_position), Some(y_position)) = (x_position, y_position) {
                 // Passive move
                 movements.append(&mut self.get_specific_movement(
-                    (x_position.unwrap(), y_position.unwrap()),
+                    (x_position, y_position),
                     colour,
                     game,
                     MovementMode::Both,
@@ -183,11 +256,10 @@ impl Piece {
             direction = -1;
         }
 
-        let mut y_position = add_i32_usize(position.1, direction);
-        if !y_position.is_none() {
+        if let Some(y_position) = add_i32_usize(position.1, direction) {
             // Passive move
             let mut forward_move: Vec<(usize, usize)> = self.get_specific_movement(
-                (position.0, y_position.unwrap()),
+                (position.0, y_position),
                 colour,
                 game,
                 MovementMode::OnlyEmpty,
@@ -196,49 +268,30 @@ impl Piece {
             movements.append(&mut forward_move);
 
             // Attack moves
-            let x_position = add_i32_usize(position.0, -1);
-            if !x_position.is_none() {
+            if let Some(x_position) = add_i32_usize(position.0, -1) {
                 movements.append(&mut self.get_specific_movement(
-                    (x_position.unwrap(), y_position.unwrap()),
+                    (x_position, y_position),
                     colour,
                     game,
                     MovementMode::OnlyDifferent,
                 ));
             }
 
-            let x_position = add_i32_usize(position.0, 1);
             movements.append(&mut self.get_specific_movement(
-                (x_position.unwrap(), y_position.unwrap()),
+                (position.0 + 1, y_position),
                 colour,
                 game,
                 MovementMode::OnlyDifferent,
             ));
 
-            // Special move - first move two steps forwards
-            if forward_move_valid {
-                if (colour == Colour::White && position.1 == 1)
-                    || (colour == Colour::Black && position.1 == 6)
-                {
-                    y_position = add_i32_usize(position.1, direction * 2);
-                    if !y_position.is_none() {
-                        movements.append(&mut self.get_specific_movement(
-                            (position.0, y_position.unwrap()),
-                            colour,
-                            game,
-                            MovementMode::OnlyEmpty,
-                        ));
-                    }
-                }
-            }
-
-            // Special moves - en passant
-            let x_position = add_i32_usize(position.0, -1);
-            if !x_position.is_none()
-                && !movements.contains(&(x_position.unwrap(), y_position.unwrap()))
+            // Special move - first move two steps forwards, Horde pawns also start on the first row
+            if forward_move_valid
+                && ((colour == Colour::White && position.1 <= 1)
+                    || (colour == Colour::Black && position.1 == 6))
             {
-                if game.double_move_position == (x_position.unwrap(), position.1) {
+                if let Some(double_y_position) = add_i32_usize(position.1, direction * 2) {
                     movements.append(&mut self.get_specific_movement(
-                        (x_position.unwrap(), y_position.unwrap()),
+                        (position.0, double_y_position),
                         colour,
                         game,
                         MovementMode::OnlyEmpty,
@@ -246,19 +299,31 @@ impl Piece {
                 }
             }
 
-            let x_position = add_i32_usize(position.0, 1);
-            if !x_position.is_none()
-                && !movements.contains(&(x_position.unwrap(), y_position.unwrap()))
-            {
-                if game.double_move_position == (x_position.unwrap(), position.1) {
+            // Special moves - en passant
+            if let Some(x_position) = add_i32_usize(position.0, -1) {
+                if !movements.contains(&(x_position, y_position))
+                    && game.double_move_position == (x_position, position.1)
+                {
                     movements.append(&mut self.get_specific_movement(
-                        (x_position.unwrap(), y_position.unwrap()),
+                        (x_position, y_position),
                         colour,
                         game,
                         MovementMode::OnlyEmpty,
                     ));
                 }
             }
+
+            let x_position = position.0 + 1;
+            if !movements.contains(&(x_position, y_position))
+                && game.double_move_position == (x_position, position.1)
+            {
+                movements.append(&mut self.get_specific_movement(
+                    (x_position, y_position),
+                    colour,
+                    game,
+                    MovementMode::OnlyEmpty,
+                ));
+            }
         }
 
         movements
@@ -412,20 +477,17 @@ impl Piece {
 
         // Loop until reaching position at the edge of the board
         for _offset in 1..(min_distance_to_edge + 1) {
-            let x_position: usize;
-            let y_position: usize;
-
-            if x_negative {
-                x_position = position.0 - _offset;
+            let x_position = if x_negative {
+                position.0 - _offset
             } else {
-                x_position = position.0 + _offset;
-            }
+                position.0 + _offset
+            };
 
-            if y_negative {
-                y_position = position.1 - _offset;
+            let y_position = if y_negative {
+                position.1 - _offset
             } else {
-                y_position = position.1 + _offset;
-            }
+                position.1 + _offset
+            };
 
             if game.board[x_position][y_position].as_ref().is_none() {
                 positions.push((x_position, y_position));
@@ -458,12 +520,16 @@ impl Piece {
             return positions;
         }
 
+        let target_colour = game.board[position.0][position.1]
+            .as_ref()
+            .map(|piece| piece.1);
+
         if ((movement_mode == MovementMode::OnlyEmpty || movement_mode == MovementMode::Both)
-            && game.board[position.0][position.1].as_ref().is_none())
+            && target_colour.is_none())
             || ((movement_mode == MovementMode::OnlyDifferent
                 || movement_mode == MovementMode::Both)
-                && !game.board[position.0][position.1].as_ref().is_none()
-                && game.board[position.0][position.1].as_ref().unwrap().1 != colour)
+                && target_colour.is_some()
+                && target_colour != Some(colour))
         {
             positions.push(position);
         }
@@ -472,31 +538,110 @@ impl Piece {
     }
 }
 
+// Tiles around the king
+static KING_OFFSETS: [(i32, i32); 8] = [
+    (-1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+];
+
+// Tiles a knight can jump to
+static KNIGHT_OFFSETS: [(i32, i32); 8] = [
+    (-2, 1),
+    (-1, 2),
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+];
+
+// Indices into Game::castling_rooks
+const KINGSIDE: usize = 0;
+const QUEENSIDE: usize = 1;
+
+// Where the king and rook end up when castling
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Castling {
+    side: usize,
+    rook_from: usize,
+    rook_to: usize,
+    king_to: usize,
+}
+
+// Check if a tile is attacked by any piece of the given colour
+fn is_attacked(position: (usize, usize), attacker: Colour, game: &Game) -> bool {
+    // Look at the tile at an offset, None if it's outside of the board
+    let tile_at = |offset: (i32, i32)| -> Option<Option<(Piece, Colour)>> {
+        let x_position = add_i32_usize(position.0, offset.0)?;
+        let y_position = add_i32_usize(position.1, offset.1)?;
+        if x_position >= 8 || y_position >= 8 {
+            return None;
+        }
+        Some(game.board[x_position][y_position])
+    };
+
+    // Knights and kings only reach the tiles next to them
+    if KNIGHT_OFFSETS
+        .iter()
+        .any(|&offset| tile_at(offset) == Some(Some((Piece::Knight, attacker))))
+        || KING_OFFSETS
+            .iter()
+            .any(|&offset| tile_at(offset) == Some(Some((Piece::King, attacker))))
+    {
+        return true;
+    }
+
+    // Pawns attack diagonally forwards, so look one row back from the attacker's point of view
+    let pawn_direction = if attacker == Colour::White { -1 } else { 1 };
+    if tile_at((-1, pawn_direction)) == Some(Some((Piece::Pawn, attacker)))
+        || tile_at((1, pawn_direction)) == Some(Some((Piece::Pawn, attacker)))
+    {
+        return true;
+    }
+
+    // Slide outwards in every direction until a piece blocks the way
+    for _direction in KING_OFFSETS.iter() {
+        let diagonal = _direction.0 != 0 && _direction.1 != 0;
+
+        for _distance in 1..8 {
+            let piece = match tile_at((_direction.0 * _distance, _direction.1 * _distance)) {
+                Some(Some(piece)) => piece,
+                Some(None) => continue,
+                None => break,
+            };
+
+            let slides = if diagonal {
+                piece.0 == Piece::Bishop || piece.0 == Piece::Queen
+            } else {
+                piece.0 == Piece::Rook || piece.0 == Piece::Queen
+            };
+
+            if slides && piece.1 == attacker {
+                return true;
+            }
+
+            break;
+        }
+    }
+
+    false
+}
+
 // Check if a colour is checked in the current board
-fn check_for_checked(colour_to_be_checked: Colour, game: &mut Game) -> bool {
-    // Loop through board and see if any opponent piece has a move that takes the king. Checked colour is the colour to check if they can check the opponent
+fn check_for_checked(colour_to_be_checked: Colour, game: &Game) -> bool {
+    // Find the king and see if any opponent piece attacks it
     for _x in 0..8 {
         for _y in 0..8 {
-            if game.board[_x][_y].as_ref().is_none()
-                || game.board[_x][_y].as_ref().unwrap().1 == colour_to_be_checked
-            {
-                continue;
-            }
-
-            // Get all moves for the piece
-            let piece = game.board[_x][_y].as_ref().unwrap().to_owned();
-            let piece_moves = piece.0.get_available_moves((_x, _y), false, game);
-
-            // Check if move conquers the oponents king
-            for _move in piece_moves.iter() {
-                let target_piece = game.board[_move.0][_move.1].as_ref();
-                if target_piece.is_some()
-                    && target_piece.unwrap().1 == colour_to_be_checked
-                    && target_piece.unwrap().0 == Piece::King
-                {
-                    println!("CHECK: {} {}", _move.0, _move.1);
-                    return true;
-                }
+            if game.board[_x][_y] == Some((Piece::King, colour_to_be_checked)) {
+                return game.variant.checks_apply(game, (_x, _y))
+                    && is_attacked((_x, _y), colour_to_be_checked.opponent(), game);
             }
         }
     }
@@ -517,16 +662,19 @@ fn check_for_checkmate(colour_to_be_checked: Colour, game: &mut Game) -> bool {
 
             // Get all moves for the piece
             let piece = game.board[_x][_y].as_ref().unwrap().to_owned();
-            let piece_moves = piece.0.get_available_moves((_x, _y), true, game);
+            let piece_moves = piece
+                .0
+                .get_available_moves((_x, _y), MoveFilter::CounterCheck, game);
 
             // A single possible moves means that the colour is not in checkmate
-            if piece_moves.len() > 0 {
+            if !piece_moves.is_empty() {
                 return false;
             }
         }
     }
 
-    true
+    // A piece dropped in the way also stops the check
+    game.get_drop_moves(colour_to_be_checked, true).is_empty()
 }
 
 // The mode of movement, can only move to empty squares, squares with opponent pieces or both?
@@ -540,94 +688,165 @@ pub enum MovementMode {
 // Adds i32 and usize together in a way that prevents crashes, returns None if invalid
 fn add_i32_usize(value: usize, difference: i32) -> Option<usize> {
     if difference >= 0 {
-        return value.checked_add(difference as usize);
+        value.checked_add(difference as usize)
     } else {
-        return value.checked_sub(-difference as usize);
+        value.checked_sub(-difference as usize)
     }
 }
 
 static FILES: [&str; 8] = ["a", "b", "c", "d", "e", "f", "g", "h"];
 
+/// A game of chess, with everything needed to carry on playing or take moves back. With the
+/// "serde" feature it can be serialized, including a promotion that hasn't been chosen yet.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "saved::GameData")
+)]
 pub struct Game {
     state: GameState,
     board: [[Option<(Piece, Colour)>; 8]; 8],
     turn: Colour,
     move_made: bool,
     double_move_position: (usize, usize),
+    // The file of each rook that can still castle, indexed by colour and then KINGSIDE/QUEENSIDE
+    castling_rooks: [[Option<usize>; 2]; 2],
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    // How many times each colour has given check, for Three-check
+    checks: [u32; 2],
+    history: Vec<Snapshot>,
+    // Chess960 castling, where the king takes its own rook to castle
+    chess960: bool,
+    variant: Variant,
+    // Captured pieces that can be dropped, indexed by colour and then piece
+    pockets: [[u32; 6]; 2],
+    // Tiles with pieces that were pawns, they go back to being pawns when captured
+    promoted: [[bool; 8]; 8],
+}
+
+// Everything needed to take back a move
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Snapshot {
+    played_move: Move,
+    state: GameState,
+    board: [[Option<(Piece, Colour)>; 8]; 8],
+    turn: Colour,
+    move_made: bool,
+    double_move_position: (usize, usize),
+    castling_rooks: [[Option<usize>; 2]; 2],
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    checks: [u32; 2],
+    pockets: [[u32; 6]; 2],
+    promoted: [[bool; 8]; 8],
 }
 
 // Check if piece is correct colour for turn and not empty
 fn check_for_colour(position: Option<&(Piece, Colour)>, turn: Colour) -> bool {
-    if position.as_ref().is_none() {
-        return false;
+    match position {
+        Some(piece) => piece.1 == turn,
+        None => false,
     }
+}
+
+// Parse data
+// Parse a tile such as "e4", returns None if it's not on the board
+fn parse_square(input: &str) -> Option<(usize, usize)> {
+    let mut chars = input.chars();
+    let file_char = chars.next()?.to_string();
+    let file = FILES.iter().position(|&s| s == file_char)?;
+
+    // Offset by one as input is 1-8 whilst array is 0-7
+    let rank = (chars.next()?.to_digit(10)? as usize).checked_sub(1)?;
 
-    if position.as_ref().unwrap().1 != turn {
-        return false;
+    if rank >= 8 || chars.next().is_some() {
+        return None;
     }
 
-    return true;
+    Some((file, rank))
 }
 
-// Parse data
-fn parse_position(_position: String) -> (usize, usize) {
-    let file = FILES
-        .iter()
-        .position(|&s| s == _position.chars().nth(0).unwrap().to_string())
-        .unwrap();
+// Format a tile such as (4, 3) as "e4"
+fn format_square(position: (usize, usize)) -> String {
+    format!("{}{}", FILES[position.0], position.1 + 1)
+}
 
-    // Offset by one as input is 1-8 whilst array is 0-7
-    let rank = (_position.chars().nth(1).unwrap().to_digit(10).unwrap() - 1) as usize;
-    return (file, rank);
+// Column and row of a board shown with `orientation` at the bottom, to the tile there
+fn view_to_tile(orientation: Colour, (column, row): (usize, usize)) -> (usize, usize) {
+    match orientation {
+        Colour::White => (column, 7 - row),
+        Colour::Black => (7 - column, row),
+    }
 }
 
 fn parse_piece(input: &str) -> Option<Piece> {
     match input {
-        "Queen" => return Some(Piece::Queen),
-        "Bishop" => return Some(Piece::Bishop),
-        "Knight" => return Some(Piece::Knight),
-        "Rook" => return Some(Piece::Rook),
-        _ => return None,
+        "Queen" => Some(Piece::Queen),
+        "Bishop" => Some(Piece::Bishop),
+        "Knight" => Some(Piece::Knight),
+        "Rook" => Some(Piece::Rook),
+        _ => None,
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Game {
     fn change_turn(&mut self) {
+        self.turn = self.turn.opponent();
+
+        // A full move is completed once black has moved
         if self.turn == Colour::White {
-            self.turn = Colour::Black;
-        } else {
-            self.turn = Colour::White;
+            self.fullmove_number += 1;
         }
 
-        println!(
-            "TURN: {}",
-            if self.turn == Colour::White {
-                "White"
-            } else {
-                "Black"
-            }
-        );
-
         self.move_made = false;
     }
 
     /// Initialises a new board with pieces.
     pub fn new() -> Game {
-        let mut game = Game {
+        Game::new_variant(Variant::Standard)
+    }
+
+    /// Initialises a board with the starting position of a variant, played by its rules.
+    pub fn new_variant(variant: Variant) -> Game {
+        let mut game = Game::empty();
+        game.variant = variant;
+        variant.set_up(&mut game);
+
+        game
+    }
+
+    /// Get the variant whose rules the game is played by.
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    // A board without any pieces
+    fn empty() -> Game {
+        Game {
             state: GameState::InProgress,
             board: Default::default(),
             turn: Colour::White,
             move_made: false,
             double_move_position: (100, 100),
-        };
-
-        // Set default pieces
-        game.set_default_pieces(Colour::White, 0, 1);
-        game.set_default_pieces(Colour::Black, 7, 6);
-
-        game.print_board(None);
-
-        game
+            castling_rooks: [[None, None], [None, None]],
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            checks: [0; 2],
+            history: Vec::new(),
+            chess960: false,
+            variant: Variant::Standard,
+            pockets: [[0; 6]; 2],
+            promoted: [[false; 8]; 8],
+        }
     }
 
     fn set_default_pieces(&mut self, colour: Colour, main_row: usize, pawn_row: usize) {
@@ -662,66 +881,248 @@ impl Game {
             return None;
         }
 
-        let from = parse_position(_from);
-        let to = parse_position(_to);
+        let from = parse_square(&_from)?;
+        let to = parse_square(&_to)?;
 
         if !check_for_colour(self.board[from.0][from.1].as_ref(), self.turn) {
             return None;
         }
 
         let piece = self.board[from.0][from.1].as_ref().unwrap().0;
-        let available_moves: Vec<(usize, usize)> = piece.get_available_moves(from, true, self);
-
-        println!("AVAILABLE MOVES: {:?}", available_moves);
-        println!("PROPOSED MOVE: {:?} -> {:?}", from, to);
+        let available_moves: Vec<(usize, usize)> =
+            piece.get_available_moves(from, MoveFilter::CounterCheck, self);
 
         // Check if proposed move is valid
         if !available_moves.contains(&to) {
             return None;
         }
 
-        self.print_board(Some(available_moves));
+        // Make actual move, a pawn reaching the last row waits for set_promotion
+        let colour = self.turn;
+        self.push_move(Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        });
+
+        // Update game state and check for check(mate)
+        self.update_state(colour.opponent());
+
+        Some(self.state)
+    }
+
+    // Make a move that is known to be legal and update the game state for the next player
+    pub(crate) fn play_move(&mut self, _move: Move) -> GameState {
+        self.push_move(_move);
+        self.update_state(self.turn);
+        self.state
+    }
+
+    // Move a piece without checking if the move is legal. The previous position is saved so
+    // that the move can be taken back with pop_move.
+    pub(crate) fn push_move(&mut self, _move: Move) {
+        self.history.push(Snapshot {
+            played_move: _move,
+            state: self.state,
+            board: self.board,
+            turn: self.turn,
+            move_made: self.move_made,
+            double_move_position: self.double_move_position,
+            castling_rooks: self.castling_rooks,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            checks: self.checks,
+            pockets: self.pockets,
+            promoted: self.promoted,
+        });
+
+        if let Some(piece) = _move.drop {
+            self.push_drop(_move, piece);
+            return;
+        }
 
-        // Make actual move
+        let (from, to) = (_move.from, _move.to);
         let piece = self.board[from.0][from.1].as_ref().unwrap().to_owned();
+        let castling = self.get_castling(from, to);
+        let mut captured = if castling.is_some() {
+            None
+        } else {
+            self.board[to.0][to.1]
+        };
 
         // Remove pawn if move was a en passant move
-        if piece.0 == Piece::Pawn && from.0 != to.0 && self.board[to.0][to.1].is_none() {
-            self.board[to.0][from.1] = None;
+        if piece.0 == Piece::Pawn && from.0 != to.0 && captured.is_none() {
+            captured = self.board[to.0][from.1].take();
         }
 
-        self.board[to.0][to.1] = Some(piece);
-        self.board[from.0][from.1] = None;
+        if let Some(castling) = castling {
+            // Lift both pieces first as they may swap places
+            let row = from.1;
+            let rook = self.board[castling.rook_from][row].take();
+            self.board[from.0][row] = None;
+            self.board[castling.rook_to][row] = rook;
+            self.board[castling.king_to][row] = Some(piece);
+        } else {
+            self.board[to.0][to.1] = Some((_move.promotion.unwrap_or(piece.0), piece.1));
+            self.board[from.0][from.1] = None;
+        }
 
-        // Detect if move allows for potensial en passant (moved pawn two steps forward)
-        if piece.0 == Piece::Pawn && (to.1 as i32 - from.1 as i32).abs() == 2 {
+        // Castling is no longer possible once the king or rook has moved or the rook was taken
+        if piece.0 == Piece::King {
+            self.castling_rooks[piece.1 as usize] = [None, None];
+        }
+        self.remove_castling_rook(from);
+        self.remove_castling_rook(to);
+
+        // Detect if move allows for potensial en passant (moved pawn two steps forward). A Horde
+        // pawn stepping twice from the first row can't be taken en passant.
+        if piece.0 == Piece::Pawn
+            && (to.1 as i32 - from.1 as i32).abs() == 2
+            && (from.1 == 1 || from.1 == 6)
+        {
             self.double_move_position = to;
         } else {
             self.double_move_position = (100, 100);
         }
 
-        // Update game state and check for check(mate)
-        // All valid moves either doesn't cause check, check only the opponent of check both so a self check test is not required
-        let checked = check_for_checked(
-            if piece.1 == Colour::White {
-                Colour::Black
-            } else {
-                Colour::White
-            },
-            self,
-        );
+        if piece.0 == Piece::Pawn || captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
 
-        if checked {
-            let checkmated = check_for_checkmate(
-                if piece.1 == Colour::White {
-                    Colour::Black
-                } else {
-                    Colour::White
-                },
-                self,
-            );
+        let variant = self.variant;
+        variant.after_move(self, _move, captured);
+
+        // Change turn unless a pawn is waiting to be promoted
+        if self.board[to.0][to.1] == Some((Piece::Pawn, piece.1)) && (to.1 == 0 || to.1 == 7) {
+            self.move_made = true;
+        } else {
+            self.change_turn();
+        }
+    }
+
+    // Take back the last move made with push_move
+    pub(crate) fn pop_move(&mut self) -> Option<Move> {
+        let snapshot = self.history.pop()?;
+
+        self.state = snapshot.state;
+        self.board = snapshot.board;
+        self.turn = snapshot.turn;
+        self.move_made = snapshot.move_made;
+        self.double_move_position = snapshot.double_move_position;
+        self.castling_rooks = snapshot.castling_rooks;
+        self.halfmove_clock = snapshot.halfmove_clock;
+        self.fullmove_number = snapshot.fullmove_number;
+        self.checks = snapshot.checks;
+        self.pockets = snapshot.pockets;
+        self.promoted = snapshot.promoted;
+
+        Some(snapshot.played_move)
+    }
+
+    // Find out if a king move is castling. Chess960 castling is written as the king taking its
+    // own rook and normal castling as the king moving two steps.
+    fn get_castling(&self, from: (usize, usize), to: (usize, usize)) -> Option<Castling> {
+        let (piece, colour) = self.board[from.0][from.1]?;
+        if piece != Piece::King || from.1 != to.1 {
+            return None;
+        }
+
+        let side = if to.0 > from.0 { KINGSIDE } else { QUEENSIDE };
+        let rook_from = self.castling_rooks[colour as usize][side]?;
+
+        let takes_own_rook =
+            to.0 == rook_from && self.board[to.0][to.1] == Some((Piece::Rook, colour));
+        let two_steps = !self.chess960 && (to.0 as i32 - from.0 as i32).abs() == 2;
+        if !takes_own_rook && !two_steps {
+            return None;
+        }
+
+        let (king_to, rook_to) = if side == KINGSIDE { (6, 5) } else { (2, 3) };
+        Some(Castling {
+            side,
+            rook_from,
+            rook_to,
+            king_to,
+        })
+    }
+
+    // Forget the castling right of a rook standing on a tile
+    fn remove_castling_rook(&mut self, position: (usize, usize)) {
+        for (colour, row) in [(Colour::White, 0), (Colour::Black, 7)] {
+            if position.1 != row {
+                continue;
+            }
+
+            for side in [KINGSIDE, QUEENSIDE] {
+                if self.castling_rooks[colour as usize][side] == Some(position.0) {
+                    self.castling_rooks[colour as usize][side] = None;
+                }
+            }
+        }
+    }
+
+    // Test a move on the board and see if it leaves the moving colour's own king in check. With
+    // counter_check a move that checks both kings is allowed as the opponent has to answer the
+    // check first.
+    fn is_self_check(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        counter_check: bool,
+    ) -> bool {
+        let piece = self.board[from.0][from.1].as_ref().unwrap().to_owned();
+        let target_piece = self.board[to.0][to.1];
+
+        // Castling moves two pieces, so play it out
+        if self.get_castling(from, to).is_some() {
+            self.push_move(Move {
+                from,
+                to,
+                promotion: None,
+                drop: None,
+            });
+            let self_checked = check_for_checked(piece.1, self);
+            let checked_opponent =
+                counter_check && self_checked && check_for_checked(piece.1.opponent(), self);
+            self.pop_move();
+
+            return self_checked && !checked_opponent;
+        }
+
+        // En passant removes a pawn that isn't standing on the target tile
+        let en_passant = piece.0 == Piece::Pawn && from.0 != to.0 && target_piece.is_none();
+        let passed_pawn = if en_passant {
+            self.board[to.0][from.1].take()
+        } else {
+            None
+        };
 
-            if checkmated {
+        // Test move
+        self.board[to.0][to.1] = Some(piece);
+        self.board[from.0][from.1] = None;
+
+        let self_checked = check_for_checked(piece.1, self);
+        let checked_opponent =
+            counter_check && self_checked && check_for_checked(piece.1.opponent(), self);
+
+        // Revert move
+        self.board[from.0][from.1] = Some(piece);
+        self.board[to.0][to.1] = target_piece;
+
+        if en_passant {
+            self.board[to.0][from.1] = passed_pawn;
+        }
+
+        self_checked && !checked_opponent
+    }
+
+    // Check if the colour about to move is in check(mate)
+    fn update_state(&mut self, colour: Colour) {
+        if check_for_checked(colour, self) {
+            if check_for_checkmate(colour, self) {
                 self.state = GameState::Checkmate;
             } else {
                 self.state = GameState::Check;
@@ -730,31 +1131,141 @@ impl Game {
             self.state = GameState::InProgress;
         }
 
-        // Check if should be promoted
-        let mut should_promote: bool = false;
+        // Variants can end in other ways than checkmate, which ends the game at once. Stalemate
+        // is only an ending if it wins, otherwise the game stays in progress without moves.
+        // Moves are counted by the rules of make_move, like checkmate above.
+        if self.state != GameState::Checkmate && !self.move_made {
+            let variant = self.variant;
+            let ended = match variant.outcome(self) {
+                Some(_) => true,
+                None => {
+                    check_for_checkmate(colour, self)
+                        && matches!(variant.no_moves_outcome(self), Outcome::Win(..))
+                }
+            };
 
-        if piece.0 == Piece::Pawn {
-            if (piece.1 == Colour::White && to.1 == 7) || (piece.1 == Colour::Black && to.1 == 0) {
-                should_promote = true;
+            if ended {
+                self.state = GameState::GameOver;
             }
         }
+    }
 
-        // Change turn
-        if !should_promote {
-            self.change_turn();
+    /// Find out how the game has ended, or None if it hasn't. Draws by the 50 move rule and
+    /// repetition are left to the players to claim.
+    pub fn get_outcome(&mut self) -> Option<Outcome> {
+        if self.move_made {
+            return None;
+        }
+
+        let variant = self.variant;
+        if let Some(outcome) = variant.outcome(self) {
+            return Some(outcome);
         }
 
-        self.print_board(None);
+        if self.get_legal_moves().is_empty() {
+            return Some(variant.no_moves_outcome(self));
+        }
 
-        Some(self.state)
+        None
+    }
+
+    // All legal moves for the colour whose turn it is, with one move per possible promotion.
+    // Unlike make_move these never leave the own king in check, even when checking the opponent.
+    pub(crate) fn get_legal_moves(&mut self) -> Vec<Move> {
+        let mut moves: Vec<Move> = Vec::new();
+
+        if self.move_made {
+            return moves;
+        }
+
+        for _x in 0..8 {
+            for _y in 0..8 {
+                if !check_for_colour(self.board[_x][_y].as_ref(), self.turn) {
+                    continue;
+                }
+
+                let piece = self.board[_x][_y].as_ref().unwrap().0;
+
+                for to in piece.get_available_moves((_x, _y), MoveFilter::Legal, self) {
+                    if piece == Piece::Pawn && (to.1 == 0 || to.1 == 7) {
+                        for &promotion in self.variant.promotions() {
+                            moves.push(Move {
+                                from: (_x, _y),
+                                to,
+                                promotion: Some(promotion),
+                                drop: None,
+                            });
+                        }
+                    } else {
+                        moves.push(Move {
+                            from: (_x, _y),
+                            to,
+                            promotion: None,
+                            drop: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        moves.append(&mut self.get_drop_moves(self.turn, false));
+        moves
+    }
+
+    // The tiles the piece on `from` can reach with a legal move, or None if there is no piece
+    // of the colour to move there. Unlike get_possible_moves these are the moves that are
+    // accepted when played.
+    pub(crate) fn get_legal_targets(
+        &mut self,
+        from: (usize, usize),
+    ) -> Option<Vec<(usize, usize)>> {
+        if self.state == GameState::GameOver
+            || !check_for_colour(self.board[from.0][from.1].as_ref(), self.turn)
+        {
+            return None;
+        }
+
+        let mut targets: Vec<(usize, usize)> = Vec::new();
+        for _move in self.get_legal_moves() {
+            if _move.from == from && _move.drop.is_none() && !targets.contains(&_move.to) {
+                targets.push(_move.to);
+            }
+        }
+
+        Some(targets)
+    }
+
+    // Check if the current position has been seen before since the last capture or pawn move
+    pub(crate) fn is_repetition(&self) -> bool {
+        self.history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .any(|snapshot| {
+                snapshot.board == self.board
+                    && snapshot.turn == self.turn
+                    && snapshot.castling_rooks == self.castling_rooks
+                    && snapshot.pockets == self.pockets
+                    && snapshot.checks == self.checks
+            })
     }
 
     /// Set the piece type that a peasant becames following a promotion.
-    /// Possible values: "Queen", "Bishop", "Knight", "Rook"
-    pub fn set_promotion(&mut self, _piece: String) -> () {
-        let piece = parse_piece(&_piece);
+    /// Possible values: "Queen", "Bishop", "Knight", "Rook", and "King" in Antichess
+    pub fn set_promotion(&mut self, _piece: String) {
+        let piece = match _piece.as_str() {
+            "King" => Piece::King,
+            _ => match parse_piece(&_piece) {
+                Some(piece) => piece,
+                None => return,
+            },
+        };
+
+        if !self.variant.promotions().contains(&piece) {
+            return;
+        }
 
-        if piece.is_none() {
+        if !self.move_made {
             return;
         }
 
@@ -771,25 +1282,38 @@ impl Game {
         };
 
         if promoted {
+            if let Some(snapshot) = self.history.last_mut() {
+                snapshot.played_move.promotion = Some(piece);
+
+                // The move is only complete now, so let the variant look at it again
+                let played_move = snapshot.played_move;
+                let variant = self.variant;
+                variant.after_move(self, played_move, None);
+            }
+
             self.change_turn();
+            self.update_state(self.turn);
         }
     }
 
     // Find the correct piece to promote and promote it
-    fn promote_piece(&mut self, piece: Option<Piece>, row: usize, colour: Colour) -> bool {
+    fn promote_piece(&mut self, piece: Piece, row: usize, colour: Colour) -> bool {
         // Check top row (white) / bottom row (black)
         for _i in 0..8 {
-            let checked_piece = self.board[_i][row].as_ref();
-            if !checked_piece.is_none()
-                && checked_piece.unwrap().0 == Piece::Pawn
-                && checked_piece.unwrap().1 == colour
-            {
-                self.board[_i][row] = Some((piece.unwrap(), colour));
+            if self.board[_i][row] == Some((Piece::Pawn, colour)) {
+                self.board[_i][row] = Some((piece, colour));
                 return true;
             }
         }
 
-        return false;
+        false
+    }
+
+    /// Take back the last move and return the resulting state of the game,
+    /// or None if no moves have been made.
+    pub fn undo_move(&mut self) -> Option<GameState> {
+        self.pop_move()?;
+        Some(self.state)
     }
 
     /// Get the current game state.
@@ -803,73 +1327,90 @@ impl Game {
             return None;
         }
 
-        let position = parse_position(_position);
+        let position = parse_square(&_position)?;
         if !check_for_colour(self.board[position.0][position.1].as_ref(), self.turn) {
             return None;
         }
 
         let piece = self.board[position.0][position.1].as_ref().unwrap().0;
-        let available_moves = piece.get_available_moves(position, true, self);
+        let available_moves = piece.get_available_moves(position, MoveFilter::CounterCheck, self);
         let mut formatted_moves: Vec<String> = Default::default();
 
         // Parse moves to printed format
         for _move in available_moves.iter() {
-            formatted_moves.push(format!("{}{}", FILES[_move.0], _move.1 + 1));
+            formatted_moves.push(format_square(*_move));
         }
 
         Some(formatted_moves)
     }
 
-    // Prints out the entire board and a piece's avaiable moves if the available_moves variable is not none
-    fn print_board(&self, available_moves: Option<Vec<(usize, usize)>>) {
-        println!("");
-        println!(". a b c d e f g h");
+    // The board as text seen from the side of `orientation`, with available moves marked
+    // as "+" for movement and "X" for attacks
+    pub(crate) fn format_board(
+        &self,
+        orientation: Colour,
+        available_moves: &[(usize, usize)],
+    ) -> String {
+        let (files, ranks): (Vec<usize>, Vec<usize>) = match orientation {
+            Colour::White => ((0..8).collect(), (0..8).rev().collect()),
+            Colour::Black => ((0..8).rev().collect(), (0..8).collect()),
+        };
 
-        // Convert each piece to a unicode character and print it
-        for _y in 0..8 {
-            print!("{} ", _y + 1);
+        let mut text = String::from(".");
+        for &_x in files.iter() {
+            text.push(' ');
+            text.push_str(FILES[_x]);
+        }
+        text.push('\n');
 
-            for _x in 0..8 {
-                if !available_moves.is_none()
-                    && available_moves.as_ref().unwrap().contains(&(_x, _y))
-                {
+        // Convert each piece to a unicode character
+        for &_y in ranks.iter() {
+            text.push_str(&format!("{} ", _y + 1));
+
+            for &_x in files.iter() {
+                let tile = if available_moves.contains(&(_x, _y)) {
                     if self.board[_x][_y].is_none() {
-                        // Movement
-                        print!("+");
+                        '+'
                     } else {
-                        // Attack
-                        print!("X");
+                        'X'
                     }
-                } else if self.board[_x][_y].is_none() {
-                    print!("*");
                 } else {
-                    let piece = self.board[_x][_y].as_ref().unwrap();
-                    match piece.1 {
-                        Colour::White => match piece.0 {
-                            Piece::King => print!("♔"),
-                            Piece::Queen => print!("♕"),
-                            Piece::Rook => print!("♖"),
-                            Piece::Bishop => print!("♗"),
-                            Piece::Knight => print!("♘"),
-                            Piece::Pawn => print!("♙"),
-                        },
-                        Colour::Black => match piece.0 {
-                            Piece::King => print!("♚"),
-                            Piece::Queen => print!("♛"),
-                            Piece::Rook => print!("♜"),
-                            Piece::Bishop => print!("♝"),
-                            Piece::Knight => print!("♞"),
-                            Piece::Pawn => print!("♟"),
-                        },
+                    match self.board[_x][_y] {
+                        Some(piece) => piece_glyph(piece),
+                        None => '*',
                     }
-                }
+                };
 
-                print!(" ");
+                text.push(tile);
+                text.push(' ');
             }
 
-            // Jump to next line
-            println!("");
+            text.push('\n');
         }
+
+        text
+    }
+}
+
+// Unicode chess symbol of a piece
+fn piece_glyph(piece: (Piece, Colour)) -> char {
+    match piece.1 {
+        Colour::White => match piece.0 {
+            Piece::King => '♔',
+            Piece::Queen => '♕',
+            Piece::Rook => '♖',
+            Piece::Bishop => '♗',
+            Piece::Knight => '♘',
+            Piece::Pawn => '♙',
+        },
+        Colour::Black => match piece.0 {
+            Piece::King => '♚',
+            Piece::Queen => '♛',
+            Piece::Rook => '♜',
+            Piece::Bishop => '♝',
+            Piece::Knight => '♞',
+            Piece::Pawn => '♟',
+        },
     }
 }
 
@@ -1007,6 +1548,140 @@ mod tests {
         test_move("h5", "g6", &mut game);
     }
 
+    // Test castling and that it's not allowed through check
+    #[test]
+    fn test_castling() {
+        let mut game =
+            Game::from_fen("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1".to_string()).unwrap();
+
+        let king_moves = game.get_possible_moves("e1".to_string()).unwrap();
+        assert!(king_moves.contains(&"c1".to_string()));
+        assert!(!king_moves.contains(&"g1".to_string()));
+
+        test_move("e1", "c1", &mut game);
+        assert_eq!(game.get_fen(), "r3k2r/8/8/8/8/8/5r2/2KR3R b kq - 1 1");
+
+        test_move("e8", "g8", &mut game);
+        assert_eq!(game.get_fen(), "r4rk1/8/8/8/8/8/5r2/2KR3R w - - 2 2");
+    }
+
+    // Test taking back moves
+    #[test]
+    fn test_undo_move() {
+        let mut game = Game::new();
+        assert_eq!(game.undo_move(), None);
+
+        test_move("e2", "e4", &mut game);
+        test_move("d7", "d5", &mut game);
+        assert_eq!(game.undo_move(), Some(GameState::InProgress));
+        assert_eq!(
+            game.get_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        test_invalid_move("e4", "e5", &mut game);
+        test_move("d7", "d5", &mut game);
+    }
+
+    // Count the leaf nodes of the move tree to compare against known results
+    #[test]
+    fn test_move_generation() {
+        fn perft(game: &mut Game, depth: u32) -> u64 {
+            if depth == 0 {
+                return 1;
+            }
+
+            let mut nodes = 0;
+            for _move in game.get_legal_moves() {
+                game.push_move(_move);
+                nodes += perft(game, depth - 1);
+                game.pop_move();
+            }
+            nodes
+        }
+
+        assert_eq!(perft(&mut Game::new(), 3), 8902);
+
+        let mut kiwipete = Game::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".to_string(),
+        )
+        .unwrap();
+        assert_eq!(perft(&mut kiwipete, 2), 2039);
+        assert_eq!(perft(&mut kiwipete, 3), 97862);
+
+        // En passant that would expose the king, promotions and checks
+        for (fen, depth, nodes) in [
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                3,
+                9467,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                3,
+                62379,
+            ),
+        ] {
+            let mut game = Game::from_fen(fen.to_string()).unwrap();
+            assert_eq!(perft(&mut game, depth), nodes, "{}", fen);
+        }
+    }
+
+    // make_move lets a move check both kings, but the legal moves used by the engine don't
+    #[test]
+    fn test_counter_check() {
+        let mut game = Game::from_fen("4r3/8/8/8/k3R3/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        // The rook is pinned to the e-file, Rd4 would check both kings
+        assert!(game
+            .get_legal_moves()
+            .iter()
+            .filter(|_move| _move.from == (4, 3))
+            .all(|_move| _move.to.0 == 4));
+
+        assert_eq!(
+            game.make_move("e4".to_string(), "d4".to_string()),
+            Some(GameState::Check)
+        );
+    }
+
+    // Tiles that aren't on the board are rejected rather than panicking
+    #[test]
+    fn test_bad_squares() {
+        let mut game = Game::new();
+        test_invalid_move("z9", "e4", &mut game);
+        test_invalid_move("e2", "e44", &mut game);
+        test_invalid_move("", "", &mut game);
+        assert_eq!(game.get_possible_moves("x".to_string()), None);
+        assert_eq!(game.get_possible_moves("e0".to_string()), None);
+    }
+
+    // A saved game carries on exactly where it was, even waiting for a promotion
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut game = Game::from_fen("4k3/1P6/8/8/3p4/8/4P3/4K3 w - - 0 1".to_string()).unwrap();
+        test_move("e2", "e4", &mut game);
+        test_move("e8", "d8", &mut game);
+        test_move("b7", "b8", &mut game);
+
+        let json = serde_json::to_string(&game).unwrap();
+        let mut restored: Game = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_fen(), game.get_fen());
+        assert!(restored.move_made);
+
+        restored.set_promotion("Queen".to_string());
+        assert_eq!(restored.get_game_state(), GameState::Check);
+        assert_eq!(restored.get_fen(), "1Q1k4/8/8/8/3pP3/8/8/4K3 b - - 0 2");
+
+        // The en passant capture comes back with the history
+        restored.undo_move();
+        restored.undo_move();
+        assert_eq!(restored.double_move_position, (4, 3));
+        test_move("d4", "e3", &mut restored);
+    }
+
+    #[allow(clippy::bool_assert_comparison)]
     fn test_move(_from: &str, _to: &str, game: &mut Game) {
         // Test if move is valid
         assert_eq!(
@@ -1015,6 +1690,7 @@ mod tests {
         );
     }
 
+    #[allow(clippy::bool_assert_comparison)]
     fn test_invalid_move(_from: &str, _to: &str, game: &mut Game) {
         // Test if move is invalid
         assert_eq!(
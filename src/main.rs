@@ -0,0 +1,358 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "animation")]
+use melg_chess::animation::{self, AnimationOptions};
+use melg_chess::epd::{self, EpdResult};
+use melg_chess::play::{self, PlayOptions};
+use melg_chess::polyglot::{Book, BookBuilder};
+use melg_chess::search::SearchLimits;
+#[cfg(feature = "server")]
+use melg_chess::server;
+use melg_chess::syzygy::Tablebase;
+#[cfg(feature = "tui")]
+use melg_chess::tui;
+use melg_chess::variant::Variant;
+use melg_chess::xboard;
+use melg_chess::Colour;
+
+// Command line arguments split into positional arguments and "--name value" options
+struct Arguments {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Arguments {
+    fn parse() -> Arguments {
+        let mut arguments = Arguments {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args.next().unwrap_or_else(|| fail_usage());
+                    arguments.options.push((name.to_string(), value));
+                }
+                None => arguments.positional.push(arg),
+            }
+        }
+
+        arguments
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(option, _)| option == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // A numeric option, exits with the usage text if it's not a number
+    fn number_option<T: std::str::FromStr>(&self, name: &str) -> Option<T> {
+        self.option(name)
+            .map(|value| value.parse().unwrap_or_else(|_| fail_usage()))
+    }
+
+    // A time given in units of `unit` seconds, negative times count as 0. Exits with the
+    // usage text if it's not a number or too long for a Duration.
+    fn duration_option(&self, name: &str, unit: f64) -> Option<Duration> {
+        self.number_option::<f64>(name).map(|value| {
+            Duration::try_from_secs_f64(value.max(0.0) * unit).unwrap_or_else(|_| {
+                eprintln!(
+                    "melg-chess: --{} {} is out of range",
+                    name,
+                    self.option(name).unwrap_or_default()
+                );
+                fail_usage()
+            })
+        })
+    }
+
+    // "white" or "black", exits with the usage text if it's something else
+    fn colour_option(&self, name: &str) -> Option<Colour> {
+        self.option(name).map(|value| match value {
+            "white" => Colour::White,
+            "black" => Colour::Black,
+            _ => fail_usage(),
+        })
+    }
+}
+
+fn main() {
+    let arguments = Arguments::parse();
+
+    let result = match arguments.positional.first().map(|mode| mode.as_str()) {
+        Some("xboard") => run_xboard(&arguments),
+        Some("book") => build_book(&arguments),
+        Some("play") => run_play(&arguments),
+        Some("tui") => run_tui(&arguments),
+        Some("server") => run_server(&arguments),
+        Some("animate") => animate(&arguments),
+        Some("epd") => run_suite(&arguments),
+        _ => fail_usage(),
+    };
+
+    if let Err(error) = result {
+        eprintln!("melg-chess: {}", error);
+        process::exit(1);
+    }
+}
+
+fn run_xboard(arguments: &Arguments) -> io::Result<()> {
+    let book = match arguments.option("book") {
+        Some(path) => Some(Book::open(path)?),
+        None => None,
+    };
+
+    let tablebase = match arguments.option("syzygy") {
+        Some(path) => Some(Tablebase::open(path)?),
+        None => None,
+    };
+
+    xboard::run(
+        io::stdin().lock(),
+        io::stdout(),
+        book.as_ref(),
+        tablebase.as_ref(),
+    )
+}
+
+fn run_play(arguments: &Arguments) -> io::Result<()> {
+    play::run(io::stdin().lock(), io::stdout(), play_options(arguments))
+}
+
+#[cfg(feature = "tui")]
+fn run_tui(arguments: &Arguments) -> io::Result<()> {
+    let increment = arguments
+        .duration_option("increment", 1.0)
+        .unwrap_or_default();
+    let clock = arguments
+        .duration_option("clock", 60.0)
+        .map(|time| tui::Clock { time, increment });
+
+    tui::run(play_options(arguments), clock)
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_arguments: &Arguments) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this build doesn't have the \"tui\" feature",
+    ))
+}
+
+#[cfg(feature = "server")]
+fn run_server(arguments: &Arguments) -> io::Result<()> {
+    let address = arguments.option("address").unwrap_or("127.0.0.1:7878");
+    let listener = std::net::TcpListener::bind(address)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+
+    server::run(listener)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_server(_arguments: &Arguments) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this build doesn't have the \"server\" feature",
+    ))
+}
+
+#[cfg(feature = "animation")]
+fn animate(arguments: &Arguments) -> io::Result<()> {
+    if arguments.positional.len() != 3 {
+        fail_usage();
+    }
+
+    let text = String::from_utf8_lossy(&fs::read(&arguments.positional[1])?).into_owned();
+    let game = melg_chess::pgn::parse_pgn(&text)
+        .first()
+        .and_then(|pgn_game| pgn_game.to_game())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no valid game in the PGN"))?;
+
+    let mut options = AnimationOptions::default();
+    if let Some(orientation) = arguments.colour_option("orientation") {
+        options.orientation = orientation;
+    }
+    if let Some(delay) = arguments.duration_option("delay", 1.0) {
+        options.delay = delay;
+    }
+    if let Some(tile_size) = arguments.number_option("tile-size") {
+        options.tile_size = tile_size;
+    }
+
+    let path = &arguments.positional[2];
+    let output = io::BufWriter::new(fs::File::create(path)?);
+    if path.ends_with(".gif") {
+        animation::write_gif(&game, &options, output)
+    } else {
+        animation::write_apng(&game, &options, output)
+    }
+}
+
+#[cfg(not(feature = "animation"))]
+fn animate(_arguments: &Arguments) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "this build doesn't have the \"animation\" feature",
+    ))
+}
+
+// Options shared by the terminal modes
+fn play_options(arguments: &Arguments) -> PlayOptions {
+    let engine_colour = arguments.colour_option("engine");
+    let mut options = PlayOptions {
+        engine_colour,
+        // The player's own side is at the bottom
+        orientation: arguments
+            .colour_option("orientation")
+            .or_else(|| engine_colour.map(|colour| colour.opponent()))
+            .unwrap_or(Colour::White),
+        ..PlayOptions::default()
+    };
+
+    if let Some(time) = arguments.duration_option("time", 1.0) {
+        options.limits.time = Some(time);
+    }
+    if let Some(depth) = arguments.number_option("depth") {
+        options.limits.depth = Some(depth);
+    }
+    options.variant = match arguments.option("variant") {
+        Some(name) => Variant::from_name(name).unwrap_or_else(|| fail_usage()),
+        None => Variant::Standard,
+    };
+    options.chess960 = match arguments.option("chess960") {
+        Some("random") => Some(random_chess960()),
+        Some(_) => match arguments.number_option("chess960") {
+            Some(index) if index < 960 => Some(index),
+            _ => fail_usage(),
+        },
+        None => None,
+    };
+
+    options
+}
+
+// Any of the 960 starting positions, the clock is random enough for that
+fn random_chess960() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    (nanos % 960) as u16
+}
+
+fn build_book(arguments: &Arguments) -> io::Result<()> {
+    if arguments.positional.len() < 3 {
+        fail_usage();
+    }
+
+    let mut builder = BookBuilder::new();
+    if let Some(max_ply) = arguments.number_option("max-ply") {
+        builder = builder.max_ply(max_ply);
+    }
+    if let Some(min_games) = arguments.number_option("min-games") {
+        builder = builder.min_games(min_games);
+    }
+
+    let mut games = 0;
+    for path in arguments.positional[2..].iter() {
+        // Old PGN files are often Latin-1, so don't require valid UTF-8
+        let text = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+        games += builder.add_pgn(&text);
+    }
+
+    let book = builder.build();
+    book.save(&arguments.positional[1])?;
+
+    eprintln!(
+        "Read {} games, wrote {} book entries to {}",
+        games,
+        book.entries().len(),
+        arguments.positional[1]
+    );
+
+    Ok(())
+}
+
+fn run_suite(arguments: &Arguments) -> io::Result<()> {
+    if arguments.positional.len() != 2 {
+        fail_usage();
+    }
+
+    let text = fs::read_to_string(&arguments.positional[1])?;
+    let mut positions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match epd::parse_epd(line) {
+            Some(position) => positions.push(position),
+            None => eprintln!("Skipping line {}, it isn't valid EPD", number + 1),
+        }
+    }
+
+    // A second per position unless a depth or time is given
+    let mut limits = SearchLimits {
+        depth: arguments.number_option("depth"),
+        ..SearchLimits::default()
+    };
+    if let Some(time) = arguments.duration_option("time", 1.0) {
+        limits.time = Some(time);
+    } else if limits.depth.is_none() {
+        limits.time = Some(Duration::from_secs(1));
+    }
+
+    let report = epd::run_suite(&positions, limits, &mut |result: &EpdResult| {
+        println!(
+            "{}: {} {}",
+            result.id.as_deref().unwrap_or("?"),
+            result.best_move.as_deref().unwrap_or("(none)"),
+            if result.solved { "solved" } else { "failed" }
+        );
+    });
+
+    println!(
+        "Solved {} of {} positions, skipped {}",
+        report.solved(),
+        report.results.len(),
+        report.skipped
+    );
+
+    Ok(())
+}
+
+fn fail_usage() -> ! {
+    eprintln!("Usage: melg-chess <mode> [options]");
+    eprintln!();
+    eprintln!("Modes:");
+    eprintln!("  xboard [--book <file>] [--syzygy <directory>]");
+    eprintln!("      Talk to a GUI using the XBoard/CECP protocol, playing opening moves");
+    eprintln!("      from a Polyglot book and endgames from Syzygy tablebases if given");
+    eprintln!("  play [--engine <white|black>] [--orientation <white|black>] [--time <seconds>]");
+    eprintln!("       [--depth <n>] [--chess960 <0-959|random>] [--variant <name>]");
+    eprintln!("      Play in the terminal against another person, or against the engine if");
+    eprintln!("      it's given a colour");
+    eprintln!("  tui [same options as play] [--clock <minutes>] [--increment <seconds>]");
+    eprintln!("      Play in a full screen terminal interface with the mouse or keyboard");
+    eprintln!("      (needs the \"tui\" feature)");
+    eprintln!("  server [--address <host:port>]");
+    eprintln!("      Host games for clients sending JSON over TCP or WebSocket, on");
+    eprintln!("      127.0.0.1:7878 unless given (needs the \"server\" feature)");
+    eprintln!("  book <output.bin> <games.pgn>... [--max-ply <n>] [--min-games <n>]");
+    eprintln!("      Build a Polyglot opening book from PGN files");
+    eprintln!("  epd <suite.epd> [--time <seconds>] [--depth <n>]");
+    eprintln!("      Search every position of an EPD test suite, a second each unless");
+    eprintln!("      limited otherwise, and count the best moves found");
+    eprintln!("  animate <game.pgn> <output.gif|output.png> [--delay <seconds>]");
+    eprintln!("          [--orientation <white|black>] [--tile-size <pixels>]");
+    eprintln!("      Draw the first game of a PGN file as an animated GIF, or an animated");
+    eprintln!("      PNG for any other extension (needs the \"animation\" feature)");
+    process::exit(2);
+}
@@ -0,0 +1,382 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::variant::Variant;
+use crate::{Colour, Game, Move};
+
+static RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+// The tags every PGN game should have, in this order
+static SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+// Longest line written in the movetext
+const LINE_LENGTH: usize = 80;
+
+/// A game read from Portable Game Notation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgnGame {
+    /// Tag pairs such as ("White", "Carlsen, Magnus") in the order they were written.
+    pub tags: Vec<(String, String)>,
+    /// The moves of the main line in Standard Algebraic Notation, without variations or comments.
+    pub moves: Vec<String>,
+    /// "1-0", "0-1", "1/2-1/2" or "*" for an unknown result.
+    pub result: String,
+}
+
+impl PgnGame {
+    /// Get the value of a tag.
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the starting position, which is the FEN tag if the game has one. The game is
+    /// played by the rules of its Variant tag, where Chess960 means Chess960 castling.
+    pub fn get_start(&self) -> Option<Game> {
+        let variant_tag = self.get_tag("Variant").unwrap_or("Standard");
+        let variant = Variant::from_name(variant_tag).unwrap_or_default();
+
+        let mut game = match self.get_tag("FEN") {
+            Some(fen) => Game::from_variant_fen(variant, fen.to_string())?,
+            None => Game::new_variant(variant),
+        };
+
+        let variant_tag = variant_tag.to_lowercase().replace([' ', '-'], "");
+        if variant_tag == "chess960"
+            || variant_tag == "fischerandom"
+            || variant_tag == "fischerrandom"
+        {
+            game.chess960 = true;
+        }
+
+        Some(game)
+    }
+
+    /// Set the value of a tag, adding it if it doesn't exist.
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old_value)) => *old_value = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Record the moves played in a game. The tags are set to unknown values
+    /// that can be changed with `set_tag`.
+    pub fn from_game(game: &Game) -> PgnGame {
+        let mut start = game.clone();
+        let mut played: Vec<Move> = Vec::new();
+        while let Some(_move) = start.pop_move() {
+            played.push(_move);
+        }
+
+        let mut pgn_game = PgnGame {
+            tags: SEVEN_TAG_ROSTER
+                .iter()
+                .map(|&tag| (tag.to_string(), "?".to_string()))
+                .collect(),
+            moves: Vec::new(),
+            result: "*".to_string(),
+        };
+        pgn_game.set_tag("Date", "????.??.??");
+        pgn_game.set_tag("Result", "*");
+
+        if start.variant != Variant::Standard {
+            pgn_game.set_tag("Variant", start.variant.name());
+        } else if start.chess960 {
+            pgn_game.set_tag("Variant", "Chess960");
+        }
+
+        let fen = start.get_fen();
+        if fen != Game::new_variant(start.variant).get_fen() || start.chess960 {
+            pgn_game.set_tag("SetUp", "1");
+            pgn_game.set_tag("FEN", &fen);
+        }
+
+        for _move in played.into_iter().rev() {
+            pgn_game.moves.push(start.format_san(_move));
+            start.push_move(_move);
+        }
+
+        pgn_game
+    }
+
+    /// Write the game in Portable Game Notation.
+    pub fn to_pgn(&self) -> String {
+        let mut text = String::new();
+        for (name, value) in self.tags.iter() {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            text.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        text.push('\n');
+
+        // Games from a position with black to move start with "1..."
+        let start = self.get_start();
+        let (mut number, mut black) = match start {
+            Some(start) => (start.fullmove_number, start.turn == Colour::Black),
+            None => (1, false),
+        };
+
+        let mut tokens: Vec<String> = Vec::new();
+        for (_i, san) in self.moves.iter().enumerate() {
+            if !black {
+                tokens.push(format!("{}.", number));
+            } else if _i == 0 {
+                tokens.push(format!("{}...", number));
+            }
+
+            tokens.push(san.clone());
+
+            if black {
+                number += 1;
+            }
+            black = !black;
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        text.push_str(&line);
+        text.push('\n');
+
+        text
+    }
+
+    /// Play through all moves, returns None if a move is illegal.
+    pub fn to_game(&self) -> Option<Game> {
+        let mut game = self.get_start()?;
+
+        for san in self.moves.iter() {
+            let _move = game.parse_san(san)?;
+            game.play_move(_move);
+        }
+
+        Some(game)
+    }
+}
+
+/// Read all games in a PGN file. Variations, comments and annotations are skipped.
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games: Vec<PgnGame> = Vec::new();
+    let mut current = PgnGame::default();
+    let mut variation_depth: i32 = 0;
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(c) = chars.next() {
+        let at_line_start = line_start;
+        line_start = c == '\n';
+
+        match c {
+            // A tag after moves means the previous game had no result
+            '[' => {
+                if !current.moves.is_empty() {
+                    games.push(finish_game(current));
+                    current = PgnGame::default();
+                }
+
+                let mut tag = String::new();
+                let mut in_string = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if in_string => tag.extend(chars.next()),
+                        '"' => {
+                            in_string = !in_string;
+                            tag.push(c);
+                        }
+                        ']' if !in_string => break,
+                        _ => tag.push(c),
+                    }
+                }
+
+                if let Some(pair) = parse_tag(&tag) {
+                    current.tags.push(pair);
+                }
+            }
+            '{' => {
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            // Rest of line comments and escaped lines
+            ';' => skip_line(&mut chars, &mut line_start),
+            '%' if at_line_start => skip_line(&mut chars, &mut line_start),
+            '(' => variation_depth += 1,
+            ')' => variation_depth = (variation_depth - 1).max(0),
+            _ if c.is_whitespace() => {}
+            _ => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || "[]{}();".contains(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+
+                if variation_depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+
+                if RESULTS.contains(&token.as_str()) {
+                    current.result = token;
+                    games.push(finish_game(current));
+                    current = PgnGame::default();
+                    continue;
+                }
+
+                // Move numbers such as "12." or "12..." may be written right before the move
+                let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if !san.is_empty() {
+                    current.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    if !current.moves.is_empty() || !current.tags.is_empty() {
+        games.push(finish_game(current));
+    }
+
+    games
+}
+
+fn skip_line(chars: &mut Peekable<Chars>, line_start: &mut bool) {
+    for c in chars.by_ref() {
+        if c == '\n' {
+            *line_start = true;
+            break;
+        }
+    }
+}
+
+// Games without a result in the movetext take it from the Result tag
+fn finish_game(mut game: PgnGame) -> PgnGame {
+    if game.result.is_empty() {
+        game.result = game
+            .get_tag("Result")
+            .filter(|result| RESULTS.contains(result))
+            .unwrap_or("*")
+            .to_string();
+    }
+
+    game
+}
+
+// Name "Value"
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let (name, value) = tag.trim().split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    Some((name.to_string(), value.to_string()))
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_pgn, PgnGame};
+    use crate::Game;
+
+    #[test]
+    fn test_parse_pgn() {
+        let text = r#"[Event "Casual \"blitz\""]
+[White "Anna"]
+[Black "Bertil"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 {The most common move} Nc6 (2... d6 3. d4) 3. Bb5 $1 a6?!
+; Ruy Lopez
+4.Ba4 1-0
+
+[Event "Second"]
+[FEN "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]
+
+1. O-O Kd7 *
+[Event "Unfinished"]
+1. d4
+"#;
+
+        let games = parse_pgn(text);
+        assert_eq!(games.len(), 3);
+
+        assert_eq!(games[0].get_tag("Event"), Some("Casual \"blitz\""));
+        assert_eq!(games[0].get_tag("Black"), Some("Bertil"));
+        assert_eq!(
+            games[0].moves,
+            vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6?!", "Ba4"]
+        );
+        assert_eq!(games[0].result, "1-0");
+        assert!(games[0].to_game().is_some());
+
+        assert_eq!(games[1].moves, vec!["O-O", "Kd7"]);
+        assert_eq!(games[1].result, "*");
+        assert_eq!(
+            games[1].to_game().unwrap().get_fen(),
+            "8/3k4/8/8/8/8/8/5RK1 w - - 2 2"
+        );
+
+        assert_eq!(games[2].moves, vec!["d4"]);
+        assert_eq!(games[2].result, "*");
+    }
+
+    #[test]
+    fn test_write_pgn() {
+        let mut game = Game::new();
+        for san in ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"] {
+            let _move = game.parse_san(san).unwrap();
+            game.play_move(_move);
+        }
+
+        let mut pgn_game = PgnGame::from_game(&game);
+        pgn_game.set_tag("White", "Anna \"the Scholar\"");
+        pgn_game.set_tag("Result", "1-0");
+        pgn_game.result = "1-0".to_string();
+
+        let text = pgn_game.to_pgn();
+        assert!(text.starts_with("[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]"));
+        assert!(text.ends_with("\n\n1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n"));
+        assert_eq!(parse_pgn(&text), vec![pgn_game]);
+
+        // Black to move in the starting position
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4p3/4K3 b - - 0 40".to_string()).unwrap();
+        let _move = game.parse_san("Kd7").unwrap();
+        game.play_move(_move);
+
+        let text = PgnGame::from_game(&game).to_pgn();
+        assert!(text.contains("[FEN \"4k3/8/8/8/8/8/4p3/4K3 b - - 0 40\"]"));
+        assert!(text.ends_with("\n\n40... Kd7 *\n"));
+
+        // Chess960 castling survives a round trip
+        let mut game = Game::new_chess960(400).unwrap();
+        for (from, to) in [("f1", "g3"), ("f8", "g6"), ("g1", "h1")] {
+            game.make_move(from.to_string(), to.to_string());
+        }
+
+        let text = PgnGame::from_game(&game).to_pgn();
+        assert!(text.contains("[Variant \"Chess960\"]"));
+        assert!(text.contains("2. O-O *"));
+
+        let mut replayed = parse_pgn(&text)[0].get_start().unwrap();
+        for san in ["Ng3", "Ng6", "O-O"] {
+            let _move = replayed.parse_san(san).unwrap();
+            replayed.push_move(_move);
+        }
+        assert_eq!(replayed.get_fen(), game.get_fen());
+    }
+}
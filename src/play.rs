@@ -0,0 +1,531 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::pgn::PgnGame;
+use crate::search::{evaluate, search, SearchLimits};
+use crate::variant::Variant;
+use crate::xboard::game_result;
+use crate::{check_for_checked, format_square, parse_square, Colour, Game, Move, Piece};
+
+static HELP: &str = "Commands:
+  <move>          Play a move such as e2e4, e7e8q, Nf3 or O-O, or a drop such as N@f3
+  moves <square>  Show where the piece on a square can move
+  undo            Take back the last move
+  resign          Give up the game
+  draw            Offer a draw
+  accept          Accept a draw offer
+  flip            Turn the board around
+  board           Show the board
+  save <file>     Save the game as PGN
+  new             Start a new game
+  quit            Leave the game";
+
+/// Settings for a game played in the terminal.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlayOptions {
+    /// The colour the engine plays, None when two humans play against each other.
+    pub engine_colour: Option<Colour>,
+    /// The colour shown at the bottom of the board.
+    pub orientation: Colour,
+    /// How long the engine thinks about each move.
+    pub limits: SearchLimits,
+    /// The number of the Chess960 starting position, None for a normal game.
+    pub chess960: Option<u16>,
+    /// The rules the game is played by.
+    pub variant: Variant,
+}
+
+impl Default for PlayOptions {
+    fn default() -> PlayOptions {
+        PlayOptions {
+            engine_colour: None,
+            orientation: Colour::White,
+            limits: SearchLimits {
+                time: Some(Duration::from_secs(1)),
+                ..SearchLimits::default()
+            },
+            chess960: None,
+            variant: Variant::Standard,
+        }
+    }
+}
+
+impl PlayOptions {
+    // The position a new game starts from
+    pub(crate) fn start_position(&self) -> Game {
+        match self.chess960.and_then(Game::new_chess960) {
+            Some(game) => game,
+            None => Game::new_variant(self.variant),
+        }
+    }
+}
+
+// State of a game in the terminal
+struct Session {
+    game: Game,
+    options: PlayOptions,
+    orientation: Colour,
+    // Result with the reason, such as "0-1 {White resigns}", once the game has ended
+    result: Option<String>,
+    // The colour that offered a draw which hasn't been answered yet
+    draw_offer: Option<Colour>,
+}
+
+impl Session {
+    fn new(options: PlayOptions) -> Session {
+        Session {
+            game: options.start_position(),
+            options,
+            orientation: options.orientation,
+            result: None,
+            draw_offer: None,
+        }
+    }
+}
+
+/// Play a game in the terminal, reading commands from `input` until it ends or "quit"
+/// is given. Type "help" for the commands.
+pub fn run<R: BufRead, W: Write>(input: R, mut output: W, options: PlayOptions) -> io::Result<()> {
+    let mut session = Session::new(options);
+
+    writeln!(output, "Type \"help\" for a list of commands.")?;
+    start_game(&mut session, &mut output)?;
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let argument = words.next();
+
+        match command {
+            "" => {}
+            "quit" | "exit" => return Ok(()),
+            "help" => writeln!(output, "{}", HELP)?,
+            "board" => show_board(&session, &[], &mut output)?,
+            "flip" => {
+                session.orientation = session.orientation.opponent();
+                show_board(&session, &[], &mut output)?;
+            }
+            "moves" => show_moves(&mut session, argument, &mut output)?,
+            "undo" => undo(&mut session, &mut output)?,
+            "resign" => resign(&mut session, &mut output)?,
+            "draw" => offer_draw(&mut session, &mut output)?,
+            "accept" => accept_draw(&mut session, &mut output)?,
+            "save" => save(&session, argument, &mut output)?,
+            "new" => {
+                session = Session::new(session.options);
+                start_game(&mut session, &mut output)?;
+            }
+            _ => user_move(&mut session, command, &mut output)?,
+        }
+
+        prompt(&session, &mut output)?;
+    }
+
+    Ok(())
+}
+
+fn start_game<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    show_board(session, &[], output)?;
+    if session.options.engine_colour == Some(session.game.turn) {
+        engine_move(session, output)?;
+    }
+
+    prompt(session, output)
+}
+
+fn prompt<W: Write>(session: &Session, output: &mut W) -> io::Result<()> {
+    if session.result.is_some() {
+        write!(output, "> ")?;
+    } else {
+        write!(output, "{:?} to move: ", session.game.turn)?;
+    }
+
+    output.flush()
+}
+
+fn show_board<W: Write>(
+    session: &Session,
+    highlights: &[(usize, usize)],
+    output: &mut W,
+) -> io::Result<()> {
+    writeln!(output)?;
+    write!(
+        output,
+        "{}",
+        session.game.format_board(session.orientation, highlights)
+    )?;
+
+    if session.game.variant.has_pockets() {
+        for colour in [Colour::White, Colour::Black] {
+            let pocket: Vec<&str> = session
+                .game
+                .get_pocket(colour)
+                .into_iter()
+                .map(|piece| match piece {
+                    Piece::Queen => "Q",
+                    Piece::Rook => "R",
+                    Piece::Bishop => "B",
+                    Piece::Knight => "N",
+                    _ => "P",
+                })
+                .collect();
+            writeln!(output, "{:?} pocket: {}", colour, pocket.join(" "))?;
+        }
+    }
+
+    if let Some(result) = &session.result {
+        writeln!(output, "Game over: {}", result)
+    } else if check_for_checked(session.game.turn, &session.game) {
+        writeln!(output, "{:?} is in check", session.game.turn)
+    } else {
+        Ok(())
+    }
+}
+
+// Moves are written in coordinate notation or Standard Algebraic Notation
+pub(crate) fn find_move(game: &mut Game, text: &str) -> Option<Move> {
+    match Move::from_uci(text) {
+        // Pawns reaching the last rank become queens unless something else is asked for
+        Some(parsed) => game.get_legal_moves().into_iter().find(|_move| {
+            _move.from == parsed.from
+                && _move.to == parsed.to
+                && _move.drop == parsed.drop
+                && (_move.promotion == parsed.promotion
+                    || (parsed.promotion.is_none() && _move.promotion == Some(Piece::Queen)))
+        }),
+        None => game.parse_san(text),
+    }
+}
+
+fn user_move<W: Write>(session: &mut Session, text: &str, output: &mut W) -> io::Result<()> {
+    if session.result.is_some() {
+        return writeln!(
+            output,
+            "The game is over, type \"new\" to start another one"
+        );
+    }
+
+    match find_move(&mut session.game, text) {
+        Some(_move) => {
+            play(session, _move, output)?;
+            if session.result.is_none() && session.options.engine_colour == Some(session.game.turn)
+            {
+                engine_move(session, output)?;
+            }
+            Ok(())
+        }
+        None => writeln!(
+            output,
+            "Illegal move: {} (type \"help\" for a list of commands)",
+            text
+        ),
+    }
+}
+
+fn engine_move<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    let result = search(&session.game, session.options.limits);
+
+    match result.best_move {
+        Some(_move) => play(session, _move, output),
+        None => Ok(()),
+    }
+}
+
+// Make a move, show it and check if the game has ended
+fn play<W: Write>(session: &mut Session, _move: Move, output: &mut W) -> io::Result<()> {
+    let mover = session.game.turn;
+    let san = session.game.format_san(_move);
+    session.game.play_move(_move);
+
+    // Making a move declines the opponent's draw offer
+    if session.draw_offer == Some(mover.opponent()) {
+        session.draw_offer = None;
+    }
+
+    session.result = game_result(&mut session.game).or_else(|| {
+        if repetitions(&session.game) >= 2 {
+            Some("1/2-1/2 {Threefold repetition}".to_string())
+        } else {
+            None
+        }
+    });
+
+    writeln!(output)?;
+    writeln!(output, "{:?} plays {}", mover, san)?;
+    show_board(session, &[], output)
+}
+
+// How many times the current position has been seen before
+pub(crate) fn repetitions(game: &Game) -> usize {
+    game.history
+        .iter()
+        .rev()
+        .take(game.halfmove_clock as usize)
+        .filter(|snapshot| {
+            snapshot.board == game.board
+                && snapshot.turn == game.turn
+                && snapshot.castling_rooks == game.castling_rooks
+                && snapshot.pockets == game.pockets
+                && snapshot.checks == game.checks
+        })
+        .count()
+}
+
+fn show_moves<W: Write>(
+    session: &mut Session,
+    argument: Option<&str>,
+    output: &mut W,
+) -> io::Result<()> {
+    let (square, from) = match argument.and_then(|square| Some((square, parse_square(square)?))) {
+        Some(square) => square,
+        None => return writeln!(output, "Usage: moves <square>, such as \"moves e2\""),
+    };
+
+    // The same moves that are accepted when typed
+    let targets = match session.game.get_legal_targets(from) {
+        Some(targets) if session.result.is_none() => targets,
+        _ => return writeln!(output, "There is no piece that can move on {}", square),
+    };
+    show_board(session, &targets, output)?;
+
+    let moves: Vec<String> = targets.into_iter().map(format_square).collect();
+    if moves.is_empty() {
+        writeln!(output, "The piece on {} can't move", square)
+    } else {
+        writeln!(output, "{}: {}", square, moves.join(" "))
+    }
+}
+
+// Against the engine both its reply and the player's move are taken back
+fn undo<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    let mut undone = 0;
+    while session.game.undo_move().is_some() {
+        undone += 1;
+        if session.options.engine_colour != Some(session.game.turn) {
+            break;
+        }
+    }
+
+    if undone == 0 {
+        return writeln!(output, "There are no moves to take back");
+    }
+
+    session.result = None;
+    session.draw_offer = None;
+    show_board(session, &[], output)?;
+
+    // The engine played the first move of the game
+    if session.options.engine_colour == Some(session.game.turn) {
+        engine_move(session, output)?;
+    }
+
+    Ok(())
+}
+
+fn resign<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    if session.result.is_some() {
+        return writeln!(output, "The game is over");
+    }
+
+    let result = match session.game.turn {
+        Colour::White => "0-1",
+        Colour::Black => "1-0",
+    };
+    session.result = Some(format!("{} {{{:?} resigns}}", result, session.game.turn));
+    writeln!(output, "Game over: {}", session.result.as_ref().unwrap())
+}
+
+// The engine accepts a draw when it isn't better. Another player has to accept it.
+fn offer_draw<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    if session.result.is_some() {
+        return writeln!(output, "The game is over");
+    }
+
+    let turn = session.game.turn;
+    if session.options.engine_colour.is_some() {
+        if evaluate(&session.game) >= 0 {
+            session.result = Some("1/2-1/2 {Draw by agreement}".to_string());
+            writeln!(output, "The engine accepts the draw")?;
+            writeln!(output, "Game over: {}", session.result.as_ref().unwrap())
+        } else {
+            writeln!(output, "The engine declines the draw")
+        }
+    } else {
+        session.draw_offer = Some(turn);
+        writeln!(
+            output,
+            "{:?} offers a draw, {:?} can type \"accept\" instead of moving",
+            turn,
+            turn.opponent()
+        )
+    }
+}
+
+fn accept_draw<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    if session.result.is_some() || session.draw_offer != Some(session.game.turn.opponent()) {
+        return writeln!(output, "There is no draw offer to accept");
+    }
+
+    session.result = Some("1/2-1/2 {Draw by agreement}".to_string());
+    session.draw_offer = None;
+    writeln!(output, "Game over: {}", session.result.as_ref().unwrap())
+}
+
+fn save<W: Write>(session: &Session, argument: Option<&str>, output: &mut W) -> io::Result<()> {
+    let path = match argument {
+        Some(path) => path,
+        None => return writeln!(output, "Usage: save <file>"),
+    };
+
+    let player = |colour: Colour| {
+        if session.options.engine_colour == Some(colour) {
+            "melg-chess"
+        } else {
+            "Human"
+        }
+    };
+    let result = session
+        .result
+        .as_ref()
+        .and_then(|result| result.split(' ').next())
+        .unwrap_or("*");
+
+    let mut pgn_game = PgnGame::from_game(&session.game);
+    pgn_game.set_tag("Event", "Casual game");
+    pgn_game.set_tag("Date", &today());
+    pgn_game.set_tag("White", player(Colour::White));
+    pgn_game.set_tag("Black", player(Colour::Black));
+    pgn_game.set_tag("Result", result);
+    pgn_game.result = result.to_string();
+
+    match fs::write(path, pgn_game.to_pgn()) {
+        Ok(()) => writeln!(output, "Saved the game to {}", path),
+        Err(error) => writeln!(output, "Could not save the game to {}: {}", path, error),
+    }
+}
+
+// The current date as written in PGN, such as "2024.03.09"
+fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() / 86400) as i64;
+
+    // Convert days since 1970-01-01 to a civil date
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{find_move, run, show_moves, PlayOptions, Session};
+    use crate::pgn::parse_pgn;
+    use crate::search::SearchLimits;
+    use crate::{Colour, Game};
+    use std::fs;
+
+    fn run_commands(commands: &str, options: PlayOptions) -> String {
+        let mut output: Vec<u8> = Vec::new();
+        run(commands.as_bytes(), &mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_human_against_human() {
+        // Named after the process so that test runs at the same time don't share the file
+        let path =
+            std::env::temp_dir().join(format!("melg-chess-play-test-{}.pgn", std::process::id()));
+        let commands = format!(
+            "f3\ne7e5\nmoves g2\nmoves e7\ng4\nQh4\ne4\nsave {}\nquit\n",
+            path.display()
+        );
+        let output = run_commands(&commands, PlayOptions::default());
+
+        assert!(output.contains("White plays f3"));
+        assert!(output.contains("Black plays e5"));
+        assert!(output.contains("g2: g3 g4"));
+        assert!(output.contains("There is no piece that can move on e7"));
+        assert!(output.contains("Black plays Qh4#"));
+        assert!(output.contains("Game over: 0-1 {Black mates}"));
+        assert!(output.contains("The game is over"));
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let games = parse_pgn(&text);
+        assert_eq!(games[0].moves, vec!["f3", "e5", "g4", "Qh4#"]);
+        assert_eq!(games[0].result, "0-1");
+        assert_eq!(games[0].get_tag("White"), Some("Human"));
+    }
+
+    #[test]
+    fn test_moves_hint_matches_legal_moves() {
+        // Nc3 and Nf4 would check the black king but leave the white king to the rook, so
+        // neither is legal
+        let mut session = Session::new(PlayOptions::default());
+        session.game = Game::from_fen("4r3/8/8/3k4/8/8/4N3/4K3 w - - 0 1".to_string()).unwrap();
+        assert!(find_move(&mut session.game, "e2f4").is_none());
+
+        let mut output: Vec<u8> = Vec::new();
+        show_moves(&mut session, Some("e2"), &mut output).unwrap();
+        show_moves(&mut session, Some("e1"), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("The piece on e2 can't move"));
+        assert!(output.contains("e1: d1 d2 f2 f1"));
+    }
+
+    #[test]
+    fn test_undo_draw_and_resign() {
+        let output = run_commands(
+            "undo\ne4\nundo\nd4\ndraw\naccept\nd5\naccept\nresign\n",
+            PlayOptions::default(),
+        );
+
+        assert!(output.contains("There are no moves to take back"));
+        assert!(output.contains("Black offers a draw"));
+        assert!(output.contains("There is no draw offer to accept"));
+        assert!(output.contains("Game over: 1/2-1/2 {Draw by agreement}"));
+        assert!(!output.contains("resigns"));
+
+        let output = run_commands("e4\nresign\n", PlayOptions::default());
+        assert!(output.contains("Game over: 1-0 {Black resigns}"));
+    }
+
+    #[test]
+    fn test_human_against_engine() {
+        let options = PlayOptions {
+            engine_colour: Some(Colour::White),
+            orientation: Colour::Black,
+            limits: SearchLimits {
+                depth: Some(1),
+                ..SearchLimits::default()
+            },
+            ..PlayOptions::default()
+        };
+        let output = run_commands("e5\nundo\nflip\n", options);
+
+        // Undo takes back both the reply and the player's move, the board starts from black's side
+        assert_eq!(output.matches("White plays").count(), 2);
+        assert!(output.ends_with("Black to move: "));
+        assert!(output.contains(". h g f e d c b a"));
+        assert!(output.contains(". a b c d e f g h"));
+    }
+}
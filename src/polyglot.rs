@@ -0,0 +1,465 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::{Colour, Game, Move, Piece};
+
+// Every entry is a big endian key (8 bytes), move (2), weight (2) and learn value (4)
+const ENTRY_SIZE: usize = 16;
+
+/// A single entry of a Polyglot opening book.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookEntry {
+    /// Zobrist key of the position, see `Game::get_zobrist_key`.
+    pub key: u64,
+    /// The move encoded as to file, to row, from file, from row and promotion in 3 bits each.
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+/// A legal move found in the book for a position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BookMove {
+    pub chess_move: Move,
+    pub weight: u16,
+}
+
+/// A Polyglot (.bin) opening book.
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Read a book from a file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        Book::from_bytes(&fs::read(path)?)
+    }
+
+    /// Read a book from the contents of a .bin file.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Book> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "polyglot book size is not a multiple of 16 bytes",
+            ));
+        }
+
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks(ENTRY_SIZE)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap()),
+            })
+            .collect();
+
+        // Lookups depend on the entries being sorted by key
+        entries.sort_by_key(|entry| entry.key);
+
+        Ok(Book { entries })
+    }
+
+    /// Write the book in the .bin format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+
+        for entry in self.entries.iter() {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.raw_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+
+        bytes
+    }
+
+    /// Write the book to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// All entries of the book, sorted by key.
+    pub fn entries(&self) -> &[BookEntry] {
+        &self.entries
+    }
+
+    /// All legal moves the book has for the current position, with the highest weight first.
+    pub fn get_moves(&self, game: &mut Game) -> Vec<BookMove> {
+        let key = game.get_zobrist_key();
+        let first = self.entries.partition_point(|entry| entry.key < key);
+        let legal_moves = game.get_legal_moves();
+
+        let mut moves: Vec<BookMove> = self.entries[first..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| {
+                let chess_move = decode_move(game, entry.raw_move);
+                if legal_moves.contains(&chess_move) {
+                    Some(BookMove {
+                        chess_move,
+                        weight: entry.weight,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        moves.sort_by_key(|book_move| Reverse(book_move.weight));
+        moves
+    }
+
+    /// The book move with the highest weight.
+    pub fn best_move(&self, game: &mut Game) -> Option<Move> {
+        self.get_moves(game)
+            .first()
+            .filter(|book_move| book_move.weight > 0)
+            .map(|book_move| book_move.chess_move)
+    }
+
+    /// Pick a book move at random with a probability proportional to its weight.
+    /// The same seed always gives the same move.
+    pub fn weighted_move(&self, game: &mut Game, seed: u64) -> Option<Move> {
+        let moves = self.get_moves(game);
+        let total: u64 = moves.iter().map(|book_move| book_move.weight as u64).sum();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut choice = split_mix(seed) % total;
+        for book_move in moves {
+            if choice < book_move.weight as u64 {
+                return Some(book_move.chess_move);
+            }
+            choice -= book_move.weight as u64;
+        }
+
+        None
+    }
+}
+
+// A small random number generator, good enough to pick between book moves
+pub(crate) fn split_mix(seed: u64) -> u64 {
+    let mut value = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+// Polyglot writes castling as the king taking its own rook, which is how Chess960 games
+// already play it
+fn decode_move(game: &Game, raw_move: u16) -> Move {
+    let field = |shift: u16| ((raw_move >> shift) & 7) as usize;
+    let from = (field(6), field(9));
+    let mut to = (field(0), field(3));
+
+    let promotion = match field(12) {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None,
+    };
+
+    if let (Some((Piece::King, colour)), Some((Piece::Rook, rook_colour))) =
+        (game.board[from.0][from.1], game.board[to.0][to.1])
+    {
+        if colour == rook_colour && !game.chess960 {
+            to.0 = if to.0 > from.0 { 6 } else { 2 };
+        }
+    }
+
+    Move {
+        from,
+        to,
+        promotion,
+        drop: None,
+    }
+}
+
+// The reverse of decode_move
+fn encode_move(game: &Game, _move: Move) -> u16 {
+    let mut to = _move.to;
+
+    if let Some(castling) = game.get_castling(_move.from, _move.to) {
+        to.0 = castling.rook_from;
+    }
+
+    let promotion = match _move.promotion {
+        Some(Piece::Knight) => 1,
+        Some(Piece::Bishop) => 2,
+        Some(Piece::Rook) => 3,
+        Some(Piece::Queen) => 4,
+        _ => 0,
+    };
+
+    (to.0 | to.1 << 3 | _move.from.0 << 6 | _move.from.1 << 9 | promotion << 12) as u16
+}
+
+// How often a move was played and the points it scored, two for a win and one for a draw
+#[derive(Copy, Clone, Debug, Default)]
+struct MoveStatistics {
+    games: u32,
+    points: u32,
+}
+
+/// Builds a Polyglot book from a collection of games. A move's weight is the points it
+/// scored for the side that played it, two for every win and one for every draw.
+#[derive(Clone, Debug)]
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    statistics: HashMap<(u64, u16), MoveStatistics>,
+}
+
+impl Default for BookBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BookBuilder {
+    /// A builder that reads the first 30 half moves of every game and keeps moves played
+    /// in at least 3 games.
+    pub fn new() -> BookBuilder {
+        BookBuilder {
+            max_ply: 30,
+            min_games: 3,
+            statistics: HashMap::new(),
+        }
+    }
+
+    /// Only read this many half moves of every game.
+    pub fn max_ply(mut self, max_ply: usize) -> BookBuilder {
+        self.max_ply = max_ply;
+        self
+    }
+
+    /// Leave out moves played in fewer games than this.
+    pub fn min_games(mut self, min_games: u32) -> BookBuilder {
+        self.min_games = min_games;
+        self
+    }
+
+    /// Add the moves of a game, up to the first illegal move. Returns false if the game
+    /// was skipped because it has no result.
+    pub fn add_game(&mut self, pgn_game: &PgnGame) -> bool {
+        let winner = match pgn_game.result.as_str() {
+            "1-0" => Some(Colour::White),
+            "0-1" => Some(Colour::Black),
+            "1/2-1/2" => None,
+            _ => return false,
+        };
+
+        let mut game = match pgn_game.get_start() {
+            Some(game) => game,
+            None => return false,
+        };
+
+        for san in pgn_game.moves.iter().take(self.max_ply) {
+            let _move = match game.parse_san(san) {
+                Some(_move) => _move,
+                None => break,
+            };
+
+            let points = match winner {
+                Some(colour) if colour == game.turn => 2,
+                Some(_) => 0,
+                None => 1,
+            };
+
+            let key = (game.get_zobrist_key(), encode_move(&game, _move));
+            let statistics = self.statistics.entry(key).or_default();
+            statistics.games += 1;
+            statistics.points += points;
+
+            game.play_move(_move);
+        }
+
+        true
+    }
+
+    /// Add all games in a PGN file, returns the number of games that were used.
+    pub fn add_pgn(&mut self, text: &str) -> usize {
+        parse_pgn(text)
+            .iter()
+            .filter(|pgn_game| self.add_game(pgn_game))
+            .count()
+    }
+
+    /// Create the book. Weights are scaled down if they don't fit in 16 bits.
+    pub fn build(&self) -> Book {
+        let kept: Vec<(&(u64, u16), &MoveStatistics)> = self
+            .statistics
+            .iter()
+            .filter(|(_, statistics)| statistics.games >= self.min_games && statistics.points > 0)
+            .collect();
+
+        let max_points = kept
+            .iter()
+            .map(|(_, statistics)| statistics.points)
+            .max()
+            .unwrap_or(0);
+
+        let mut entries: Vec<BookEntry> = kept
+            .iter()
+            .map(|(&(key, raw_move), statistics)| {
+                let weight = if max_points > u16::MAX as u32 {
+                    (statistics.points as u64 * u16::MAX as u64 / max_points as u64).max(1)
+                } else {
+                    statistics.points as u64
+                };
+
+                BookEntry {
+                    key,
+                    raw_move,
+                    weight: weight as u16,
+                    learn: 0,
+                }
+            })
+            .collect();
+
+        entries.sort_by_key(|entry| (entry.key, Reverse(entry.weight), entry.raw_move));
+
+        Book { entries }
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Book, BookBuilder, BookMove};
+    use crate::{Game, Move};
+
+    fn entry(key: u64, raw_move: u16, weight: u16) -> Vec<u8> {
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&raw_move.to_be_bytes());
+        bytes.extend_from_slice(&weight.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes
+    }
+
+    // Encode a move the way Polyglot does
+    fn raw_move(from: (u16, u16), to: (u16, u16)) -> u16 {
+        to.0 | to.1 << 3 | from.0 << 6 | from.1 << 9
+    }
+
+    #[test]
+    fn test_book_lookup() {
+        let start = Game::new().get_zobrist_key();
+        let mut bytes = entry(start, raw_move((3, 1), (3, 3)), 10);
+        bytes.append(&mut entry(1, raw_move((4, 1), (4, 3)), 50));
+        bytes.append(&mut entry(start, raw_move((4, 1), (4, 3)), 30));
+        // Not a legal move in the start position
+        bytes.append(&mut entry(start, raw_move((4, 1), (4, 4)), 90));
+
+        let book = Book::from_bytes(&bytes).unwrap();
+        assert_eq!(book.entries().len(), 4);
+        assert_eq!(book.entries()[0].key, 1);
+
+        let mut game = Game::new();
+        let e4 = Move {
+            from: (4, 1),
+            to: (4, 3),
+            promotion: None,
+            drop: None,
+        };
+        let d4 = Move {
+            from: (3, 1),
+            to: (3, 3),
+            promotion: None,
+            drop: None,
+        };
+
+        assert_eq!(
+            book.get_moves(&mut game),
+            vec![
+                BookMove {
+                    chess_move: e4,
+                    weight: 30
+                },
+                BookMove {
+                    chess_move: d4,
+                    weight: 10
+                }
+            ]
+        );
+        assert_eq!(book.best_move(&mut game), Some(e4));
+
+        // Every seed picks one of the moves, and both moves get picked
+        let picks: Vec<Move> = (0..50)
+            .map(|seed| book.weighted_move(&mut game, seed).unwrap())
+            .collect();
+        assert!(picks.contains(&e4) && picks.contains(&d4));
+        assert_eq!(
+            book.weighted_move(&mut game, 7),
+            book.weighted_move(&mut game, 7)
+        );
+
+        game.make_move("g1".to_string(), "f3".to_string());
+        assert_eq!(book.best_move(&mut game), None);
+    }
+
+    #[test]
+    fn test_castling_and_bad_size() {
+        let mut game =
+            Game::from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1".to_string())
+                .unwrap();
+        let bytes = entry(game.get_zobrist_key(), raw_move((4, 0), (7, 0)), 1);
+        let book = Book::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            book.best_move(&mut game),
+            Some(Move {
+                from: (4, 0),
+                to: (6, 0),
+                promotion: None,
+                drop: None,
+            })
+        );
+
+        assert!(Book::from_bytes(&bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn test_build_book() {
+        let games = r#"
+1. e4 e5 2. Nf3 Nc6 1-0
+1. e4 c5 2. Nf3 d6 0-1
+1. e4 e5 2. Nc3 1/2-1/2
+1. d4 d5 1-0
+1. e4 e5 2. Nf3 Nf6 *
+"#;
+
+        let mut builder = BookBuilder::new().max_ply(2).min_games(2);
+        assert_eq!(builder.add_pgn(games), 4);
+        let book = builder.build();
+
+        // 1. e4 scored a win, a loss and a draw for white, 1... e5 a loss and a draw for black
+        let mut game = Game::new();
+        let moves = book.get_moves(&mut game);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].weight, 3);
+
+        game.make_move("e2".to_string(), "e4".to_string());
+        assert_eq!(book.get_moves(&mut game)[0].weight, 1);
+
+        // Nf3 was only played once within the first two half moves
+        game.make_move("e7".to_string(), "e5".to_string());
+        assert!(book.get_moves(&mut game).is_empty());
+
+        let copy = Book::from_bytes(&book.to_bytes()).unwrap();
+        assert_eq!(copy.entries(), book.entries());
+    }
+}
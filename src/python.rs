@@ -0,0 +1,296 @@
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::fen::piece_to_char;
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::play::find_move;
+use crate::search::{self, SearchLimits};
+use crate::variant::Variant;
+use crate::xboard::game_result;
+use crate::{parse_square, Colour, Game, GameState};
+
+/// A game for Python, with squares written as "e4" and moves in coordinate notation such as
+/// "e2e4" or "e7e8q". Bad input raises ValueError.
+#[pyclass(name = "Game", module = "melg_chess")]
+pub struct PyGame {
+    game: Game,
+}
+
+/// The best move found by a search and how good it is.
+#[pyclass(name = "SearchResult", module = "melg_chess", get_all)]
+pub struct PySearchResult {
+    best_move: Option<String>,
+    /// Centipawns from the point of view of the side to move
+    score: i32,
+    depth: u32,
+    nodes: u64,
+    principal_variation: Vec<String>,
+}
+
+#[pymethods]
+impl PyGame {
+    /// Start a game from a FEN, or from the starting position of the variant if none is given
+    #[new]
+    #[pyo3(signature = (fen = None, variant = None))]
+    fn new(fen: Option<String>, variant: Option<&str>) -> PyResult<PyGame> {
+        let variant = match variant {
+            Some(name) => Variant::from_name(name)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown variant {}", name)))?,
+            None => Variant::Standard,
+        };
+
+        let game = match fen {
+            Some(fen) => Game::from_variant_fen(variant, fen.clone())
+                .ok_or_else(|| PyValueError::new_err(format!("invalid FEN {}", fen)))?,
+            None => Game::new_variant(variant),
+        };
+        Ok(PyGame { game })
+    }
+
+    /// Load the first game of a PGN, with all of its moves played
+    #[staticmethod]
+    fn from_pgn(pgn: &str) -> PyResult<PyGame> {
+        match parse_pgn(pgn).first() {
+            Some(pgn_game) => pgn_to_game(pgn_game),
+            None => Err(PyValueError::new_err("no game in PGN")),
+        }
+    }
+
+    fn fen(&self) -> String {
+        self.game.get_fen()
+    }
+
+    fn pgn(&self) -> String {
+        PgnGame::from_game(&self.game).to_pgn()
+    }
+
+    /// The moves played so far in Standard Algebraic Notation
+    fn moves(&self) -> Vec<String> {
+        PgnGame::from_game(&self.game).moves
+    }
+
+    #[getter]
+    fn variant(&self) -> &'static str {
+        self.game.get_variant().name()
+    }
+
+    /// "white" or "black"
+    #[getter]
+    fn turn(&self) -> &'static str {
+        match self.game.turn {
+            Colour::White => "white",
+            Colour::Black => "black",
+        }
+    }
+
+    /// "in_progress", "check", "checkmate" or "game_over"
+    #[getter]
+    fn state(&self) -> &'static str {
+        match self.game.get_game_state() {
+            GameState::InProgress => "in_progress",
+            GameState::Check => "check",
+            GameState::Checkmate => "checkmate",
+            GameState::GameOver => "game_over",
+        }
+    }
+
+    /// The result such as "1-0 {White mates}", or None while the game goes on
+    fn result(&mut self) -> Option<String> {
+        game_result(&mut self.game)
+    }
+
+    fn zobrist_key(&self) -> u64 {
+        self.game.get_zobrist_key()
+    }
+
+    /// The piece on a square as a FEN letter such as "N" or "p"
+    fn piece_at(&self, square: &str) -> PyResult<Option<String>> {
+        let (x, y) = parse_square(square).ok_or_else(|| bad_square(square))?;
+        Ok(self.game.board[x][y].map(|piece| piece_to_char(piece).to_string()))
+    }
+
+    fn legal_moves(&mut self) -> Vec<String> {
+        self.game
+            .get_legal_moves()
+            .into_iter()
+            .map(|_move| _move.to_uci())
+            .collect()
+    }
+
+    /// The legal moves in Standard Algebraic Notation
+    fn legal_moves_san(&mut self) -> Vec<String> {
+        self.game
+            .get_legal_moves()
+            .into_iter()
+            .map(|_move| self.game.format_san(_move))
+            .collect()
+    }
+
+    /// The squares the piece on a square can move to
+    fn moves_from(&mut self, square: &str) -> PyResult<Vec<String>> {
+        self.game
+            .get_possible_moves(square.to_string())
+            .ok_or_else(|| bad_square(square))
+    }
+
+    /// Play a move in coordinate notation or Standard Algebraic Notation, returns the move in
+    /// Standard Algebraic Notation
+    fn make_move(&mut self, text: &str) -> PyResult<String> {
+        if self.result().is_some() {
+            return Err(PyValueError::new_err("the game is over"));
+        }
+
+        let _move = find_move(&mut self.game, text)
+            .ok_or_else(|| PyValueError::new_err(format!("illegal move {}", text)))?;
+        let san = self.game.format_san(_move);
+        self.game.play_move(_move);
+
+        Ok(san)
+    }
+
+    /// Take back the last move, returns False if there is none
+    fn undo(&mut self) -> bool {
+        self.game.undo_move().is_some()
+    }
+
+    /// Static evaluation in centipawns from the point of view of the side to move
+    fn evaluate(&self) -> i32 {
+        search::evaluate(&self.game)
+    }
+
+    /// Search for the best move, stopping at whichever limit is reached first. The time is in
+    /// seconds.
+    #[pyo3(signature = (depth = None, time = None, nodes = None))]
+    fn search(
+        &self,
+        py: Python<'_>,
+        depth: Option<u32>,
+        time: Option<f64>,
+        nodes: Option<u64>,
+    ) -> PyResult<PySearchResult> {
+        let limits = search_limits(depth, time, nodes)?;
+
+        // Let other Python threads run while searching
+        let game = self.game.clone();
+        let result = py.detach(move || search::search(&game, limits));
+        Ok(search_result(result))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Game('{}')", self.game.get_fen())
+    }
+}
+
+/// Read every game of a PGN archive, raising ValueError for a game whose moves can't be played.
+#[pyfunction]
+fn read_pgn(text: &str) -> PyResult<Vec<PyGame>> {
+    parse_pgn(text).iter().map(pgn_to_game).collect()
+}
+
+#[pymodule]
+fn melg_chess(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PySearchResult>()?;
+    module.add_function(wrap_pyfunction!(read_pgn, module)?)?;
+    Ok(())
+}
+
+fn pgn_to_game(pgn_game: &PgnGame) -> PyResult<PyGame> {
+    match pgn_game.to_game() {
+        Some(game) => Ok(PyGame { game }),
+        None => Err(PyValueError::new_err(format!(
+            "invalid game {} - {}",
+            pgn_game.get_tag("White").unwrap_or("?"),
+            pgn_game.get_tag("Black").unwrap_or("?")
+        ))),
+    }
+}
+
+fn search_result(result: search::SearchResult) -> PySearchResult {
+    PySearchResult {
+        best_move: result.best_move.map(|_move| _move.to_uci()),
+        score: result.score,
+        depth: result.depth,
+        nodes: result.nodes,
+        principal_variation: result
+            .principal_variation
+            .into_iter()
+            .map(|_move| _move.to_uci())
+            .collect(),
+    }
+}
+
+fn search_limits(
+    depth: Option<u32>,
+    time: Option<f64>,
+    nodes: Option<u64>,
+) -> PyResult<SearchLimits> {
+    let time = match time {
+        Some(seconds) => Some(
+            Duration::try_from_secs_f64(seconds)
+                .map_err(|_| PyValueError::new_err("invalid time"))?,
+        ),
+        None => None,
+    };
+
+    let limits = SearchLimits { depth, time, nodes };
+    if limits == SearchLimits::default() {
+        return Err(PyValueError::new_err(
+            "a search needs a depth, time or nodes",
+        ));
+    }
+    Ok(limits)
+}
+
+fn bad_square(square: &str) -> PyErr {
+    PyValueError::new_err(format!("invalid square {}", square))
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{read_pgn, search_limits, search_result, PyGame};
+    use crate::search;
+
+    #[test]
+    fn test_python_game() {
+        let mut game = PyGame::new(None, None).unwrap();
+        assert_eq!(game.legal_moves().len(), 20);
+        assert_eq!(game.make_move("e2e4").unwrap(), "e4");
+        assert_eq!(game.make_move("Nf6").unwrap(), "Nf6");
+        assert_eq!(game.moves(), vec!["e4", "Nf6"]);
+        assert_eq!(game.piece_at("f6").unwrap(), Some("n".to_string()));
+        assert_eq!(game.turn(), "white");
+
+        let games = read_pgn(&format!("{}\n\n{}", game.pgn(), game.pgn())).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].fen(), game.fen());
+
+        let game = PyGame::new(Some("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1".to_string()), None).unwrap();
+        let limits = search_limits(Some(2), None, None).unwrap();
+        let result = search_result(search::search(&game.game, limits));
+        assert_eq!(result.best_move, Some("a1a8".to_string()));
+    }
+
+    #[test]
+    fn test_python_bad_input() {
+        assert!(PyGame::new(Some("8/8/8".to_string()), None).is_err());
+        assert!(PyGame::new(None, Some("bughouse")).is_err());
+        assert!(PyGame::from_pgn("").is_err());
+        assert!(search_limits(None, None, None).is_err());
+        assert!(search_limits(None, Some(-1.0), None).is_err());
+        assert!(read_pgn("1. e4 e5 2. Ke3 *").is_err());
+
+        let mut game = PyGame::new(None, Some("atomic")).unwrap();
+        assert_eq!(game.variant(), "Atomic");
+        assert!(game.piece_at("z9").is_err());
+        assert!(game.moves_from("e").is_err());
+        assert!(game.make_move("e2e5").is_err());
+        assert!(game.make_move("").is_err());
+    }
+}
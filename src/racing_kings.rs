@@ -0,0 +1,112 @@
+use crate::{check_for_checked, Colour, Game, Move, MoveFilter, Piece};
+
+impl Game {
+    // No king may be in check in Racing Kings, so a move is illegal if it leaves either king
+    // in check, including when it checks both
+    pub(crate) fn is_legal_racing(&mut self, from: (usize, usize), to: (usize, usize)) -> bool {
+        self.push_move(Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        });
+        let legal =
+            !check_for_checked(Colour::White, self) && !check_for_checked(Colour::Black, self);
+        self.pop_move();
+
+        legal
+    }
+
+    pub(crate) fn king_on_last_row(&self, colour: Colour) -> bool {
+        (0..8).any(|_x| self.board[_x][7] == Some((Piece::King, colour)))
+    }
+
+    // If the colour's king has a legal move to the last row, which lets black draw a race
+    // that white has just won
+    pub(crate) fn can_reach_last_row(&mut self, colour: Colour) -> bool {
+        for _x in 0..8 {
+            for _y in 0..8 {
+                if self.board[_x][_y] == Some((Piece::King, colour)) {
+                    return Piece::King
+                        .get_available_moves((_x, _y), MoveFilter::CounterCheck, self)
+                        .iter()
+                        .any(|to| to.1 == 7);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::{Outcome, Variant};
+    use crate::{Colour, Game, GameState};
+
+    fn racing_kings(fen: &str) -> Game {
+        Game::from_variant_fen(Variant::RacingKings, fen.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_no_checks() {
+        let game = Game::new_variant(Variant::RacingKings);
+        assert_eq!(game.get_fen(), "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1");
+
+        // The rook can't give check, and the king can't walk into one
+        let mut game = racing_kings("8/8/8/8/8/8/1k6/6RK w - - 0 1");
+        assert_eq!(game.make_move("g1".to_string(), "g2".to_string()), None);
+        assert_eq!(game.make_move("g1".to_string(), "b1".to_string()), None);
+        game.make_move("g1".to_string(), "c1".to_string());
+        assert!(!game
+            .get_possible_moves("b2".to_string())
+            .unwrap()
+            .contains(&"c3".to_string()));
+    }
+
+    #[test]
+    fn test_race_to_last_row() {
+        // Black reaching the last row first wins
+        let mut game = racing_kings("8/1k6/8/8/8/8/8/7K b - - 0 1");
+        assert_eq!(
+            game.make_move("b7".to_string(), "b8".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::Black, "reaches the eighth rank"))
+        );
+
+        // White reaching it first wins at once if black can't follow
+        let mut game = racing_kings("8/6K1/1k6/8/8/8/8/8 w - - 0 1");
+        assert_eq!(
+            game.make_move("g7".to_string(), "g8".to_string()),
+            Some(GameState::GameOver)
+        );
+
+        // Otherwise black gets one move to draw
+        let mut game = racing_kings("8/1k4K1/8/8/8/8/8/8 w - - 0 1");
+        game.make_move("g7".to_string(), "g8".to_string());
+        assert_eq!(game.get_outcome(), None);
+        game.make_move("b7".to_string(), "b6".to_string());
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "reaches the eighth rank"))
+        );
+
+        let mut game = racing_kings("8/1k4K1/8/8/8/8/8/8 w - - 0 1");
+        game.make_move("g7".to_string(), "g8".to_string());
+        assert_eq!(
+            game.make_move("b7".to_string(), "b8".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Draw("Both kings reach the eighth rank"))
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use crate::{
+    check_for_checked, format_square, parse_square, Game, Move, Piece, KINGSIDE, QUEENSIDE,
+};
+
+fn piece_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::King => "K",
+        Piece::Queen => "Q",
+        Piece::Rook => "R",
+        Piece::Bishop => "B",
+        Piece::Knight => "N",
+        Piece::Pawn => "",
+    }
+}
+
+// Pawns need a letter when they are dropped
+fn drop_letter(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => "P",
+        _ => piece_letter(piece),
+    }
+}
+
+fn parse_piece_letter(letter: char) -> Option<Piece> {
+    match letter {
+        'K' => Some(Piece::King),
+        'Q' => Some(Piece::Queen),
+        'R' => Some(Piece::Rook),
+        'B' => Some(Piece::Bishop),
+        'N' => Some(Piece::Knight),
+        _ => None,
+    }
+}
+
+impl Game {
+    // Find the legal move described in Standard Algebraic Notation such as "Nbd7", "exd6",
+    // "e8=Q+" or "O-O", returns None if there is no such move or it's ambiguous
+    pub(crate) fn parse_san(&mut self, san: &str) -> Option<Move> {
+        // Check marks and annotations don't change the move
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.get_legal_moves();
+
+        let castling_side = match san {
+            "O-O" | "0-0" => Some(KINGSIDE),
+            "O-O-O" | "0-0-0" => Some(QUEENSIDE),
+            _ => None,
+        };
+
+        if let Some(side) = castling_side {
+            return legal_moves.into_iter().find(|_move| {
+                self.get_castling(_move.from, _move.to)
+                    .is_some_and(|castling| castling.side == side)
+            });
+        }
+
+        // Drops such as "N@f3", where pawns are written "P@e4" or "@e4"
+        if let Some((piece, target)) = san.split_once('@') {
+            let piece = match piece {
+                "" | "P" => Piece::Pawn,
+                _ => parse_piece_letter(piece.chars().next()?).filter(|_| piece.len() == 1)?,
+            };
+            let to = parse_square(target)?;
+
+            return legal_moves
+                .into_iter()
+                .find(|_move| _move.drop == Some(piece) && _move.to == to);
+        }
+
+        let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '=').collect();
+
+        // Promotion is the last letter, "e8=Q" and "e8Q" are both used
+        let promotion = match chars.last().copied().and_then(parse_piece_letter) {
+            Some(piece) => {
+                chars.pop();
+                Some(piece)
+            }
+            None => None,
+        };
+
+        let piece = match chars.first().copied().and_then(parse_piece_letter) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            }
+            None => Piece::Pawn,
+        };
+
+        if chars.len() < 2 {
+            return None;
+        }
+
+        let target: String = chars[chars.len() - 2..].iter().collect();
+        let to = parse_square(&target)?;
+
+        // Anything left between the piece and the target tells which piece moves
+        let mut from_file = None;
+        let mut from_rank = None;
+        for &c in chars[..chars.len() - 2].iter() {
+            match c {
+                'a'..='h' => from_file = Some(c as usize - 'a' as usize),
+                '1'..='8' => from_rank = Some(c as usize - '1' as usize),
+                _ => return None,
+            }
+        }
+
+        let mut candidates = legal_moves.into_iter().filter(|_move| {
+            _move.to == to
+                && _move.promotion == promotion
+                && self.board[_move.from.0][_move.from.1].map(|piece| piece.0) == Some(piece)
+                && from_file.is_none_or(|file| _move.from.0 == file)
+                && from_rank.is_none_or(|rank| _move.from.1 == rank)
+        });
+
+        let found = candidates.next()?;
+        if candidates.next().is_some() {
+            return None;
+        }
+
+        Some(found)
+    }
+
+    // Write a legal move in Standard Algebraic Notation, with "+" for check and "#" for mate
+    pub(crate) fn format_san(&mut self, _move: Move) -> String {
+        let piece = match (_move.drop, self.board[_move.from.0][_move.from.1]) {
+            (Some(piece), _) => piece,
+            (None, Some((piece, _))) => piece,
+            (None, None) => return String::new(),
+        };
+        let castling = self.get_castling(_move.from, _move.to);
+        let capture = castling.is_none()
+            && _move.drop.is_none()
+            && (self.board[_move.to.0][_move.to.1].is_some()
+                || (piece == Piece::Pawn && _move.from.0 != _move.to.0));
+
+        let mut san = if _move.drop.is_some() {
+            // Drops are written as "N@f3" and "P@e4"
+            format!("{}@{}", drop_letter(piece), format_square(_move.to))
+        } else if let Some(castling) = castling {
+            if castling.side == KINGSIDE {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            }
+        } else {
+            let mut san = piece_letter(piece).to_string();
+
+            if piece == Piece::Pawn {
+                if capture {
+                    san.push(format_square(_move.from).remove(0));
+                }
+            } else {
+                // Other pieces of the same kind that can reach the target
+                let others: Vec<Move> = self
+                    .get_legal_moves()
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == _move.to
+                            && other.from != _move.from
+                            && self.board[other.from.0][other.from.1].map(|piece| piece.0)
+                                == Some(piece)
+                    })
+                    .collect();
+
+                let square = format_square(_move.from);
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.from.0 != _move.from.0) {
+                        san.push_str(&square[..1]);
+                    } else if others.iter().all(|other| other.from.1 != _move.from.1) {
+                        san.push_str(&square[1..]);
+                    } else {
+                        san.push_str(&square);
+                    }
+                }
+            }
+
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&format_square(_move.to));
+
+            if let Some(promotion) = _move.promotion {
+                san.push('=');
+                san.push_str(piece_letter(promotion));
+            }
+
+            san
+        };
+
+        self.push_move(_move);
+        if check_for_checked(self.turn, self) {
+            san.push(if self.get_legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        self.pop_move();
+
+        san
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{Game, Move, Piece};
+
+    fn coordinates(from: (usize, usize), to: (usize, usize)) -> Option<Move> {
+        Some(Move {
+            from,
+            to,
+            promotion: None,
+            drop: None,
+        })
+    }
+
+    #[test]
+    fn test_parse_san() {
+        let mut game = Game::new();
+        assert_eq!(game.parse_san("e4"), coordinates((4, 1), (4, 3)));
+        assert_eq!(game.parse_san("Nf3"), coordinates((6, 0), (5, 2)));
+        assert_eq!(game.parse_san("Ng1f3!?"), coordinates((6, 0), (5, 2)));
+        assert_eq!(game.parse_san("e5"), None);
+        assert_eq!(game.parse_san("Qd4"), None);
+        assert_eq!(game.parse_san("xyz"), None);
+
+        // Two knights can reach d2
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/5N2/8/1N2K2R w K - 0 1".to_string()).unwrap();
+        assert_eq!(game.parse_san("Nd2"), None);
+        assert_eq!(game.parse_san("Nbd2"), coordinates((1, 0), (3, 1)));
+        assert_eq!(game.parse_san("Nfd2"), coordinates((5, 2), (3, 1)));
+        assert_eq!(game.parse_san("O-O"), coordinates((4, 0), (6, 0)));
+        assert_eq!(
+            game.parse_san("b8=N+"),
+            Some(Move {
+                from: (1, 6),
+                to: (1, 7),
+                promotion: Some(Piece::Knight),
+                drop: None,
+            })
+        );
+        assert_eq!(game.parse_san("b8"), None);
+    }
+
+    #[test]
+    fn test_format_san() {
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/5N2/8/1N2K2R w K - 0 1".to_string()).unwrap();
+        for san in ["Nbd2", "Nfd2", "O-O", "b8=N", "Rh8+", "Ke2"] {
+            let _move = game.parse_san(san).unwrap();
+            assert_eq!(game.format_san(_move), san);
+        }
+
+        // Pawn captures name the file, rooks on the same file use the rank
+        let mut game = Game::from_fen("4k3/8/8/R2p4/4P3/8/8/R3K3 w Q - 0 1".to_string()).unwrap();
+        for san in ["exd5", "R1a3", "R5a3", "O-O-O"] {
+            let _move = game.parse_san(san).unwrap();
+            assert_eq!(game.format_san(_move), san);
+        }
+
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1".to_string()).unwrap();
+        let _move = game.parse_san("Ra8").unwrap();
+        assert_eq!(game.format_san(_move), "Ra8#");
+    }
+}
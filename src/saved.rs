@@ -0,0 +1,175 @@
+use std::convert::TryFrom;
+
+use crate::variant::Variant;
+use crate::{Colour, Game, GameState, Move, Piece, Snapshot};
+
+type Board = [[Option<(Piece, Colour)>; 8]; 8];
+
+// A game as it's read by serde, it's checked before it becomes a Game so that a broken or
+// edited save can't make the moves index outside of the board later
+#[derive(serde::Deserialize)]
+pub(crate) struct GameData {
+    state: GameState,
+    board: Board,
+    turn: Colour,
+    move_made: bool,
+    double_move_position: (usize, usize),
+    castling_rooks: [[Option<usize>; 2]; 2],
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    checks: [u32; 2],
+    history: Vec<Snapshot>,
+    chess960: bool,
+    variant: Variant,
+    pockets: [[u32; 6]; 2],
+    promoted: [[bool; 8]; 8],
+}
+
+impl TryFrom<GameData> for Game {
+    type Error = &'static str;
+
+    fn try_from(data: GameData) -> Result<Game, &'static str> {
+        check_position(
+            data.variant,
+            &data.board,
+            data.move_made,
+            data.double_move_position,
+            &data.castling_rooks,
+            &data.pockets,
+        )?;
+
+        for snapshot in data.history.iter() {
+            check_position(
+                data.variant,
+                &snapshot.board,
+                snapshot.move_made,
+                snapshot.double_move_position,
+                &snapshot.castling_rooks,
+                &snapshot.pockets,
+            )?;
+            check_move(snapshot.played_move)?;
+        }
+
+        Ok(Game {
+            state: data.state,
+            board: data.board,
+            turn: data.turn,
+            move_made: data.move_made,
+            double_move_position: data.double_move_position,
+            castling_rooks: data.castling_rooks,
+            halfmove_clock: data.halfmove_clock,
+            fullmove_number: data.fullmove_number,
+            checks: data.checks,
+            history: data.history,
+            chess960: data.chess960,
+            variant: data.variant,
+            pockets: data.pockets,
+            promoted: data.promoted,
+        })
+    }
+}
+
+// The same rules as a position read from FEN, except that a pawn may wait on the last row
+// for its promotion
+fn check_position(
+    variant: Variant,
+    board: &Board,
+    move_made: bool,
+    double_move_position: (usize, usize),
+    castling_rooks: &[[Option<usize>; 2]; 2],
+    pockets: &[[u32; 6]; 2],
+) -> Result<(), &'static str> {
+    for colour in [Colour::White, Colour::Black] {
+        let kings = board
+            .iter()
+            .flatten()
+            .filter(|&&tile| tile == Some((Piece::King, colour)))
+            .count();
+
+        if variant.is_royal(colour) && kings != 1 {
+            return Err("each side needs exactly one king");
+        }
+        if pockets[colour as usize][Piece::King as usize] != 0 {
+            return Err("a king can't be in the pocket");
+        }
+        if castling_rooks[colour as usize]
+            .iter()
+            .flatten()
+            .any(|&file| file >= 8)
+        {
+            return Err("castling rook outside of the board");
+        }
+    }
+
+    for column in board.iter() {
+        for (_y, tile) in column.iter().enumerate() {
+            if let Some((Piece::Pawn, colour)) = *tile {
+                let promoting = move_made && (_y == 0 || _y == 7);
+                if !promoting && !variant.allows_pawn_on(colour, _y) {
+                    return Err("pawn on a row it can't stand on");
+                }
+            }
+        }
+    }
+
+    // Nothing can be taken en passant unless a pawn just stepped twice
+    if double_move_position != (100, 100)
+        && (double_move_position.0 >= 8
+            || double_move_position.1 >= 8
+            || !matches!(
+                board[double_move_position.0][double_move_position.1],
+                Some((Piece::Pawn, _))
+            ))
+    {
+        return Err("en passant pawn isn't on the board");
+    }
+
+    Ok(())
+}
+
+fn check_move(played_move: Move) -> Result<(), &'static str> {
+    let (from, to) = (played_move.from, played_move.to);
+    if from.0 >= 8 || from.1 >= 8 || to.0 >= 8 || to.1 >= 8 {
+        return Err("move outside of the board");
+    }
+
+    Ok(())
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::Game;
+
+    fn load(json: &serde_json::Value) -> Result<Game, serde_json::Error> {
+        serde_json::from_value(json.clone())
+    }
+
+    #[test]
+    fn test_invalid_saves() {
+        let mut game = Game::new();
+        game.make_move("e2".to_string(), "e4".to_string());
+        let json = serde_json::to_value(&game).unwrap();
+        assert!(load(&json).is_ok());
+
+        let broken = [
+            ("/castling_rooks/0/0", serde_json::json!(9)),
+            ("/double_move_position", serde_json::json!([8, 3])),
+            ("/double_move_position", serde_json::json!([0, 3])),
+            ("/board/4/0", serde_json::Value::Null),
+            ("/board/0/7", serde_json::json!(["Pawn", "White"])),
+            ("/pockets/1/0", serde_json::json!(1)),
+            ("/history/0/castling_rooks/1/1", serde_json::json!(100)),
+            ("/history/0/played_move/to", serde_json::json!([4, 8])),
+        ];
+
+        for (pointer, value) in broken.iter() {
+            let mut edited = json.clone();
+            *edited.pointer_mut(pointer).unwrap() = value.clone();
+            assert!(load(&edited).is_err(), "{} accepted", pointer);
+        }
+    }
+}
@@ -0,0 +1,568 @@
+use std::time::{Duration, Instant};
+
+use crate::crazyhouse::POCKET_PIECES;
+use crate::syzygy::Tablebase;
+use crate::variant::{Outcome, Variant};
+use crate::{Colour, Game, Move, Piece};
+
+/// Score of a side that delivers checkmate. A mate in n half moves scores `MATE_SCORE - n`.
+pub const MATE_SCORE: i32 = 100_000;
+
+// Deepest iteration when no other limit is given
+const MAX_DEPTH: u32 = 64;
+
+// Search ply at which quiescence search gives up
+const MAX_PLY: u32 = 96;
+
+/// How long a search may run. The search stops at whichever limit is reached first.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+/// The outcome of the deepest completed iteration of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// Centipawns from the point of view of the side to move.
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+    pub principal_variation: Vec<Move>,
+}
+
+/// Search for the best move for the side to move.
+pub fn search(game: &Game, limits: SearchLimits) -> SearchResult {
+    search_with_info(game, limits, &mut |_| {})
+}
+
+/// Search for the best move, calling `on_iteration` after every completed depth.
+pub fn search_with_info(
+    game: &Game,
+    limits: SearchLimits,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    run_search(game, limits, None, on_iteration)
+}
+
+/// Search for the best move, only considering the moves that are best according to the
+/// tablebase when the position is in it.
+pub fn search_with_tablebase(
+    game: &Game,
+    limits: SearchLimits,
+    tablebase: &Tablebase,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    run_search(game, limits, Some(tablebase), on_iteration)
+}
+
+fn run_search(
+    game: &Game,
+    limits: SearchLimits,
+    tablebase: Option<&Tablebase>,
+    on_iteration: &mut dyn FnMut(&SearchResult),
+) -> SearchResult {
+    let mut searcher = Searcher {
+        game: game.clone(),
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        stopped: false,
+    };
+
+    let mut root_moves = searcher.game.get_legal_moves();
+
+    // Keep the moves with the best outcome and distance to zeroing
+    if let Some(tablebase_moves) = tablebase.and_then(|tablebase| tablebase.probe_root(game)) {
+        if let Some(best) = tablebase_moves.first() {
+            root_moves = tablebase_moves
+                .iter()
+                .filter(|tablebase_move| tablebase_move.dtz == best.dtz)
+                .map(|tablebase_move| tablebase_move.chess_move)
+                .collect();
+        }
+    }
+    let mut result = SearchResult {
+        best_move: root_moves.first().copied(),
+        score: 0,
+        depth: 0,
+        nodes: 0,
+        time: Duration::default(),
+        principal_variation: Vec::new(),
+    };
+
+    if root_moves.is_empty() {
+        return result;
+    }
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH) {
+        // Search the best move of the previous iteration first
+        if let Some(best_move) = result.best_move {
+            let index = root_moves
+                .iter()
+                .position(|&_move| _move == best_move)
+                .unwrap();
+            root_moves[..=index].rotate_right(1);
+        }
+
+        let mut principal_variation = Vec::new();
+        let score = searcher.search_root(&root_moves, depth, &mut principal_variation);
+
+        if searcher.stopped {
+            break;
+        }
+
+        result = SearchResult {
+            best_move: principal_variation.first().copied(),
+            score,
+            depth,
+            nodes: searcher.nodes,
+            time: searcher.start.elapsed(),
+            principal_variation,
+        };
+        on_iteration(&result);
+
+        // Stop early if another iteration is unlikely to finish or a forced mate was found
+        let out_of_time = limits
+            .time
+            .is_some_and(|time| searcher.start.elapsed() * 2 > time);
+        if out_of_time || score.abs() >= MATE_SCORE - depth as i32 {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result.time = searcher.start.elapsed();
+    result
+}
+
+struct Searcher {
+    game: Game,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+}
+
+impl Searcher {
+    fn search_root(
+        &mut self,
+        root_moves: &[Move],
+        depth: u32,
+        principal_variation: &mut Vec<Move>,
+    ) -> i32 {
+        let mut alpha = -MATE_SCORE - 1;
+
+        for &_move in root_moves {
+            let mut child_variation = Vec::new();
+
+            self.game.push_move(_move);
+            let score = -self.negamax(depth - 1, 1, -MATE_SCORE - 1, -alpha, &mut child_variation);
+            self.game.pop_move();
+
+            if self.stopped {
+                break;
+            }
+
+            if score > alpha {
+                alpha = score;
+                principal_variation.clear();
+                principal_variation.push(_move);
+                principal_variation.append(&mut child_variation);
+            }
+        }
+
+        alpha
+    }
+
+    fn negamax(
+        &mut self,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        principal_variation: &mut Vec<Move>,
+    ) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        // Moves that check both kings let the king be taken
+        if self.game.variant.is_royal(self.game.turn) && !has_king(&self.game) {
+            return -MATE_SCORE + ply as i32;
+        }
+
+        if self.game.halfmove_clock >= 100 || self.game.is_repetition() {
+            return 0;
+        }
+
+        let variant = self.game.variant;
+        if let Some(outcome) = variant.outcome(&mut self.game) {
+            return outcome_score(&self.game, outcome, ply);
+        }
+
+        if depth == 0 {
+            return self.quiescence(ply, alpha, beta);
+        }
+
+        let mut moves = self.game.get_legal_moves();
+        if moves.is_empty() {
+            let outcome = variant.no_moves_outcome(&mut self.game);
+            return outcome_score(&self.game, outcome, ply);
+        }
+
+        self.order_moves(&mut moves);
+
+        for _move in moves {
+            let mut child_variation = Vec::new();
+
+            self.game.push_move(_move);
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut child_variation);
+            self.game.pop_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > alpha {
+                alpha = score;
+                principal_variation.clear();
+                principal_variation.push(_move);
+                principal_variation.append(&mut child_variation);
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        alpha
+    }
+
+    // Only look at captures so that the evaluation isn't done in the middle of an exchange
+    fn quiescence(&mut self, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if self.should_stop() {
+            return 0;
+        }
+
+        if self.game.variant.is_royal(self.game.turn) && !has_king(&self.game) {
+            return -MATE_SCORE + ply as i32;
+        }
+
+        let variant = self.game.variant;
+        if let Some(outcome) = variant.outcome(&mut self.game) {
+            return outcome_score(&self.game, outcome, ply);
+        }
+
+        let stand_pat = evaluate(&self.game);
+        if stand_pat >= beta || ply >= MAX_PLY {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = self
+            .game
+            .get_legal_moves()
+            .into_iter()
+            .filter(|&_move| is_capture(&self.game, _move) || _move.promotion == Some(Piece::Queen))
+            .collect();
+        self.order_moves(&mut moves);
+
+        for _move in moves {
+            self.game.push_move(_move);
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.game.pop_move();
+
+            if self.stopped {
+                return 0;
+            }
+
+            if score > alpha {
+                alpha = score;
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        alpha
+    }
+
+    fn should_stop(&mut self) -> bool {
+        self.nodes += 1;
+
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            self.stopped = true;
+        }
+
+        // Reading the clock is slow, so only do it every now and then
+        if self.nodes.is_multiple_of(1024)
+            && self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() >= time)
+        {
+            self.stopped = true;
+        }
+
+        self.stopped
+    }
+
+    // Most valuable victim, least valuable attacker first, then promotions
+    fn order_moves(&self, moves: &mut [Move]) {
+        moves.sort_by_cached_key(|&_move| {
+            let attacker = _move
+                .drop
+                .unwrap_or_else(|| self.game.board[_move.from.0][_move.from.1].unwrap().0);
+            let mut priority = 0;
+
+            if is_capture(&self.game, _move) {
+                let victim =
+                    self.game.board[_move.to.0][_move.to.1].map_or(Piece::Pawn, |piece| piece.0);
+                priority += 10 * piece_value(victim) - piece_value(attacker);
+            }
+
+            if let Some(promotion) = _move.promotion {
+                priority += piece_value(promotion);
+            }
+
+            -priority
+        });
+    }
+}
+
+fn has_king(game: &Game) -> bool {
+    game.board
+        .iter()
+        .flatten()
+        .any(|&tile| tile == Some((Piece::King, game.turn)))
+}
+
+// Score of a finished game for the side to move, quicker wins score higher
+fn outcome_score(game: &Game, outcome: Outcome, ply: u32) -> i32 {
+    match outcome {
+        Outcome::Win(winner, _) if winner == game.turn => MATE_SCORE - ply as i32,
+        Outcome::Win(..) => -MATE_SCORE + ply as i32,
+        Outcome::Draw(_) => 0,
+    }
+}
+
+// A Chess960 king taking its own rook is castling, not a capture
+fn is_capture(game: &Game, _move: Move) -> bool {
+    if _move.drop.is_some() {
+        return false;
+    }
+
+    (game.board[_move.to.0][_move.to.1].is_some()
+        && game.get_castling(_move.from, _move.to).is_none())
+        || (game.board[_move.from.0][_move.from.1].unwrap().0 == Piece::Pawn
+            && _move.from.0 != _move.to.0)
+}
+
+fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::King => 0,
+        Piece::Queen => 900,
+        Piece::Rook => 500,
+        Piece::Bishop => 330,
+        Piece::Knight => 320,
+        Piece::Pawn => 100,
+    }
+}
+
+/// Static evaluation of a position in centipawns from the point of view of the side to move.
+pub fn evaluate(game: &Game) -> i32 {
+    let mut score = 0;
+
+    for _x in 0..8 {
+        for _y in 0..8 {
+            if let Some((piece, colour)) = game.board[_x][_y] {
+                // Tables are written from white's side with the top row first
+                let row = if colour == Colour::White { 7 - _y } else { _y };
+                let table = match piece {
+                    Piece::King => &KING_TABLE,
+                    Piece::Queen => &QUEEN_TABLE,
+                    Piece::Rook => &ROOK_TABLE,
+                    Piece::Bishop => &BISHOP_TABLE,
+                    Piece::Knight => &KNIGHT_TABLE,
+                    Piece::Pawn => &PAWN_TABLE,
+                };
+
+                let value = piece_value(piece) + table[row][_x];
+                if colour == game.turn {
+                    score += value;
+                } else {
+                    score -= value;
+                }
+            }
+        }
+    }
+
+    // Pieces in a pocket can be dropped anywhere, so they count as much as those on the board
+    for colour in [Colour::White, Colour::Black] {
+        let value: i32 = POCKET_PIECES
+            .iter()
+            .map(|&piece| piece_value(piece) * game.pockets[colour as usize][piece as usize] as i32)
+            .sum();
+        if colour == game.turn {
+            score += value;
+        } else {
+            score -= value;
+        }
+    }
+
+    // Racing Kings is decided by how far up the board the kings have come
+    if game.variant == Variant::RacingKings {
+        for _x in 0..8 {
+            for _y in 0..8 {
+                if let Some((Piece::King, colour)) = game.board[_x][_y] {
+                    let value = 100 * _y as i32;
+                    if colour == game.turn {
+                        score += value;
+                    } else {
+                        score -= value;
+                    }
+                }
+            }
+        }
+    }
+
+    // Having fewer pieces is better in Antichess
+    if game.variant == Variant::Antichess {
+        return -score;
+    }
+
+    score
+}
+
+#[rustfmt::skip]
+static PAWN_TABLE: [[i32; 8]; 8] = [
+    [  0,  0,  0,  0,  0,  0,  0,  0],
+    [ 50, 50, 50, 50, 50, 50, 50, 50],
+    [ 10, 10, 20, 30, 30, 20, 10, 10],
+    [  5,  5, 10, 25, 25, 10,  5,  5],
+    [  0,  0,  0, 20, 20,  0,  0,  0],
+    [  5, -5,-10,  0,  0,-10, -5,  5],
+    [  5, 10, 10,-20,-20, 10, 10,  5],
+    [  0,  0,  0,  0,  0,  0,  0,  0],
+];
+
+#[rustfmt::skip]
+static KNIGHT_TABLE: [[i32; 8]; 8] = [
+    [-50,-40,-30,-30,-30,-30,-40,-50],
+    [-40,-20,  0,  0,  0,  0,-20,-40],
+    [-30,  0, 10, 15, 15, 10,  0,-30],
+    [-30,  5, 15, 20, 20, 15,  5,-30],
+    [-30,  0, 15, 20, 20, 15,  0,-30],
+    [-30,  5, 10, 15, 15, 10,  5,-30],
+    [-40,-20,  0,  5,  5,  0,-20,-40],
+    [-50,-40,-30,-30,-30,-30,-40,-50],
+];
+
+#[rustfmt::skip]
+static BISHOP_TABLE: [[i32; 8]; 8] = [
+    [-20,-10,-10,-10,-10,-10,-10,-20],
+    [-10,  0,  0,  0,  0,  0,  0,-10],
+    [-10,  0,  5, 10, 10,  5,  0,-10],
+    [-10,  5,  5, 10, 10,  5,  5,-10],
+    [-10,  0, 10, 10, 10, 10,  0,-10],
+    [-10, 10, 10, 10, 10, 10, 10,-10],
+    [-10,  5,  0,  0,  0,  0,  5,-10],
+    [-20,-10,-10,-10,-10,-10,-10,-20],
+];
+
+#[rustfmt::skip]
+static ROOK_TABLE: [[i32; 8]; 8] = [
+    [  0,  0,  0,  0,  0,  0,  0,  0],
+    [  5, 10, 10, 10, 10, 10, 10,  5],
+    [ -5,  0,  0,  0,  0,  0,  0, -5],
+    [ -5,  0,  0,  0,  0,  0,  0, -5],
+    [ -5,  0,  0,  0,  0,  0,  0, -5],
+    [ -5,  0,  0,  0,  0,  0,  0, -5],
+    [ -5,  0,  0,  0,  0,  0,  0, -5],
+    [  0,  0,  0,  5,  5,  0,  0,  0],
+];
+
+#[rustfmt::skip]
+static QUEEN_TABLE: [[i32; 8]; 8] = [
+    [-20,-10,-10, -5, -5,-10,-10,-20],
+    [-10,  0,  0,  0,  0,  0,  0,-10],
+    [-10,  0,  5,  5,  5,  5,  0,-10],
+    [ -5,  0,  5,  5,  5,  5,  0, -5],
+    [  0,  0,  5,  5,  5,  5,  0, -5],
+    [-10,  5,  5,  5,  5,  5,  0,-10],
+    [-10,  0,  5,  0,  0,  0,  0,-10],
+    [-20,-10,-10, -5, -5,-10,-10,-20],
+];
+
+#[rustfmt::skip]
+static KING_TABLE: [[i32; 8]; 8] = [
+    [-30,-40,-40,-50,-50,-40,-40,-30],
+    [-30,-40,-40,-50,-50,-40,-40,-30],
+    [-30,-40,-40,-50,-50,-40,-40,-30],
+    [-30,-40,-40,-50,-50,-40,-40,-30],
+    [-20,-30,-30,-40,-40,-30,-30,-20],
+    [-10,-20,-20,-20,-20,-20,-20,-10],
+    [ 20, 20,  0,  0,  0,  0, 20, 20],
+    [ 20, 30, 10,  0,  0, 10, 30, 20],
+];
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{search, SearchLimits, MATE_SCORE};
+    use crate::{Game, Move};
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        // Back rank mate with Ra8
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1".to_string()).unwrap();
+        let result = search(
+            &game,
+            SearchLimits {
+                depth: Some(3),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.best_move,
+            Some(Move {
+                from: (0, 0),
+                to: (0, 7),
+                promotion: None,
+                drop: None,
+            })
+        );
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_takes_hanging_queen() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1".to_string()).unwrap();
+        let result = search(
+            &game,
+            SearchLimits {
+                depth: Some(2),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.best_move,
+            Some(Move {
+                from: (3, 1),
+                to: (3, 4),
+                promotion: None,
+                drop: None,
+            })
+        );
+    }
+}
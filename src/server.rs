@@ -0,0 +1,719 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::play::repetitions;
+use crate::variant::Variant;
+use crate::xboard::game_result;
+use crate::{Colour, Game, Move};
+
+// How often connections look for messages to send and the clocks are checked
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+// Time left for both sides, the clock of the side to move runs from turn_start once both
+// players have joined
+struct GameClock {
+    remaining: [Duration; 2],
+    increment: Duration,
+    turn_start: Option<Instant>,
+}
+
+impl GameClock {
+    fn time_left(&self, colour: Colour, turn: Colour) -> Duration {
+        let remaining = self.remaining[colour as usize];
+        match self.turn_start {
+            Some(start) if colour == turn => remaining.saturating_sub(start.elapsed()),
+            _ => remaining,
+        }
+    }
+}
+
+// A player's place in a game, kept when the connection drops so they can come back with
+// their token
+struct Seat {
+    connection: Option<usize>,
+}
+
+struct HostedGame {
+    game: Game,
+    moves: Vec<String>,
+    seats: [Option<Seat>; 2],
+    spectators: Vec<usize>,
+    clock: Option<GameClock>,
+    result: Option<String>,
+}
+
+// Everything the connections share: the games, where to send messages for each connection
+// and which game and colour each token plays
+#[derive(Default)]
+struct Lobby {
+    games: HashMap<String, HostedGame>,
+    connections: HashMap<usize, Sender<String>>,
+    tokens: HashMap<String, (String, Colour)>,
+    next_game: u64,
+    next_connection: usize,
+}
+
+/// Host games for the clients connecting to the listener, until accepting a connection fails.
+/// Clients send JSON messages, one per line over plain TCP or one per text message over a
+/// WebSocket, and get JSON messages back.
+pub fn run(listener: TcpListener) -> io::Result<()> {
+    let lobby = Arc::new(Mutex::new(Lobby::default()));
+
+    let clock_lobby = Arc::clone(&lobby);
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        lock(&clock_lobby).check_clocks();
+    });
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let lobby = Arc::clone(&lobby);
+
+        // A client that goes away only ends its own connection
+        thread::spawn(move || {
+            let _ = serve(lobby, stream);
+        });
+    }
+
+    Ok(())
+}
+
+// The lobby even if a thread panicked while holding it, so one bad message can't stop the
+// clocks and every other connection
+fn lock(lobby: &Mutex<Lobby>) -> MutexGuard<'_, Lobby> {
+    lobby.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn serve(lobby: Arc<Mutex<Lobby>>, stream: TcpStream) -> io::Result<()> {
+    let mut transport = if is_websocket(&stream)? {
+        let socket = tungstenite::accept(stream)
+            .map_err(|error| io::Error::other(format!("handshake failed: {}", error)))?;
+        Transport::WebSocket(Box::new(socket))
+    } else {
+        Transport::Lines {
+            stream,
+            buffer: Vec::new(),
+        }
+    };
+    transport.stream().set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (sender, receiver) = mpsc::channel();
+    let connection = lock(&lobby).connect(sender);
+    let result = exchange(&lobby, connection, &mut transport, &receiver);
+    lock(&lobby).disconnect(connection);
+
+    result
+}
+
+// Pass messages both ways until the client goes away
+fn exchange(
+    lobby: &Mutex<Lobby>,
+    connection: usize,
+    transport: &mut Transport,
+    receiver: &Receiver<String>,
+) -> io::Result<()> {
+    loop {
+        while let Ok(message) = receiver.try_recv() {
+            transport.send(&message)?;
+        }
+
+        if let Some(text) = transport.receive()? {
+            if !text.trim().is_empty() {
+                lock(lobby).handle(connection, &text);
+            }
+        }
+    }
+}
+
+// WebSocket clients start with an HTTP request, plain TCP clients with a JSON message
+fn is_websocket(stream: &TcpStream) -> io::Result<bool> {
+    let mut start = [0; 4];
+
+    loop {
+        let read = stream.peek(&mut start)?;
+        if read == 0 || !b"GET ".starts_with(&start[..read]) {
+            return Ok(false);
+        }
+        if read == start.len() {
+            return Ok(true);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+enum Transport {
+    Lines { stream: TcpStream, buffer: Vec<u8> },
+    WebSocket(Box<WebSocket<TcpStream>>),
+}
+
+impl Transport {
+    fn stream(&mut self) -> &mut TcpStream {
+        match self {
+            Transport::Lines { stream, .. } => stream,
+            Transport::WebSocket(socket) => socket.get_mut(),
+        }
+    }
+
+    fn send(&mut self, text: &str) -> io::Result<()> {
+        match self {
+            Transport::Lines { stream, .. } => writeln!(stream, "{}", text),
+            Transport::WebSocket(socket) => socket
+                .send(Message::text(text))
+                .map_err(|error| io::Error::other(error.to_string())),
+        }
+    }
+
+    // The next message, or None if nothing has come in for a while
+    fn receive(&mut self) -> io::Result<Option<String>> {
+        match self {
+            Transport::Lines { stream, buffer } => loop {
+                if let Some(end) = buffer.iter().position(|&byte| byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    return Ok(Some(String::from_utf8_lossy(&line).into_owned()));
+                }
+
+                let mut chunk = [0; 1024];
+                match stream.read(&mut chunk) {
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                    Err(error) if is_timeout(&error) => return Ok(None),
+                    Err(error) => return Err(error),
+                }
+            },
+            Transport::WebSocket(socket) => match socket.read() {
+                Ok(Message::Text(text)) => Ok(Some(text.to_string())),
+                Ok(_) => Ok(None),
+                Err(tungstenite::Error::Io(error)) if is_timeout(&error) => Ok(None),
+                Err(error) => Err(io::Error::other(error.to_string())),
+            },
+        }
+    }
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+// A token of 128 bits straight from the operating system, so that no token can be worked out
+// from another one
+fn new_token() -> Result<String, &'static str> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|_| "no randomness for a token")?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// Negative times count as no time, times too large for a Duration as None
+fn seconds_to_duration(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds.max(0.0)).ok()
+}
+
+fn colour_name(colour: Colour) -> &'static str {
+    match colour {
+        Colour::White => "white",
+        Colour::Black => "black",
+    }
+}
+
+impl Lobby {
+    fn connect(&mut self, sender: Sender<String>) -> usize {
+        let connection = self.next_connection;
+        self.next_connection += 1;
+        self.connections.insert(connection, sender);
+        connection
+    }
+
+    // The seats stay taken so the players can reconnect
+    fn disconnect(&mut self, connection: usize) {
+        self.connections.remove(&connection);
+
+        for hosted in self.games.values_mut() {
+            hosted
+                .spectators
+                .retain(|&spectator| spectator != connection);
+            for seat in hosted.seats.iter_mut().flatten() {
+                if seat.connection == Some(connection) {
+                    seat.connection = None;
+                }
+            }
+        }
+    }
+
+    fn send(&self, connection: usize, message: &Value) {
+        if let Some(sender) = self.connections.get(&connection) {
+            let _ = sender.send(message.to_string());
+        }
+    }
+
+    fn send_error(&self, connection: usize, text: &str) {
+        self.send(connection, &json!({ "type": "error", "message": text }));
+    }
+
+    fn handle(&mut self, connection: usize, text: &str) {
+        let message: Value = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(_) => return self.send_error(connection, "invalid JSON"),
+        };
+        let field = |name: &str| message[name].as_str().map(String::from);
+
+        let result = match message["type"].as_str().unwrap_or("") {
+            "create" => self.create(connection, &message),
+            "join" => field("game")
+                .ok_or("missing game")
+                .and_then(|id| self.join(connection, &id)),
+            "watch" => field("game")
+                .ok_or("missing game")
+                .and_then(|id| self.watch(connection, &id)),
+            "reconnect" => field("token")
+                .ok_or("missing token")
+                .and_then(|token| self.reconnect(connection, &token)),
+            "move" => match (field("token"), field("move")) {
+                (Some(token), Some(text)) => self.play(&token, &text),
+                _ => Err("missing token or move"),
+            },
+            "resign" => field("token")
+                .ok_or("missing token")
+                .and_then(|token| self.resign(&token)),
+            "list" => {
+                self.list(connection);
+                Ok(())
+            }
+            _ => Err("unknown message type"),
+        };
+
+        if let Err(error) = result {
+            self.send_error(connection, error);
+        }
+    }
+
+    // Start a game with the creator on the chosen side, white if none is given
+    fn create(&mut self, connection: usize, message: &Value) -> Result<(), &'static str> {
+        let variant = match message["variant"].as_str() {
+            Some(name) => Variant::from_name(name).ok_or("unknown variant")?,
+            None => Variant::Standard,
+        };
+        let colour = match message["colour"].as_str() {
+            Some("black") => Colour::Black,
+            Some("white") | None => Colour::White,
+            Some(_) => return Err("colour must be white or black"),
+        };
+
+        // Time is given in seconds
+        let clock = match message["time"].as_f64() {
+            Some(seconds) => Some(GameClock {
+                remaining: [seconds_to_duration(seconds).ok_or("time out of range")?; 2],
+                increment: seconds_to_duration(message["increment"].as_f64().unwrap_or(0.0))
+                    .ok_or("increment out of range")?,
+                turn_start: None,
+            }),
+            None => None,
+        };
+
+        let token = new_token()?;
+        self.next_game += 1;
+        let id = self.next_game.to_string();
+        self.games.insert(
+            id.clone(),
+            HostedGame {
+                game: Game::new_variant(variant),
+                moves: Vec::new(),
+                seats: [None, None],
+                spectators: Vec::new(),
+                clock,
+                result: None,
+            },
+        );
+
+        self.seat(connection, &id, colour, token);
+        Ok(())
+    }
+
+    // Take the free seat of a game
+    fn join(&mut self, connection: usize, id: &str) -> Result<(), &'static str> {
+        let hosted = self.games.get(id).ok_or("unknown game")?;
+        let colour = [Colour::White, Colour::Black]
+            .iter()
+            .copied()
+            .find(|&colour| hosted.seats[colour as usize].is_none())
+            .ok_or("game is full")?;
+
+        let token = new_token()?;
+        self.seat(connection, id, colour, token);
+        Ok(())
+    }
+
+    fn seat(&mut self, connection: usize, id: &str, colour: Colour, token: String) {
+        let hosted = self.games.get_mut(id).unwrap();
+        hosted.seats[colour as usize] = Some(Seat {
+            connection: Some(connection),
+        });
+
+        // The clock starts once both players are there
+        let seated = hosted.seats.iter().all(|seat| seat.is_some());
+        if let Some(clock) = hosted.clock.as_mut() {
+            if seated && clock.turn_start.is_none() {
+                clock.turn_start = Some(Instant::now());
+            }
+        }
+
+        self.tokens.insert(token.clone(), (id.to_string(), colour));
+        self.send(
+            connection,
+            &json!({
+                "type": "joined",
+                "game": id,
+                "colour": colour_name(colour),
+                "token": token,
+            }),
+        );
+        self.broadcast(id);
+    }
+
+    fn watch(&mut self, connection: usize, id: &str) -> Result<(), &'static str> {
+        let hosted = self.games.get_mut(id).ok_or("unknown game")?;
+        hosted.spectators.push(connection);
+
+        let state = state_message(id, hosted);
+        self.send(connection, &state);
+        Ok(())
+    }
+
+    fn reconnect(&mut self, connection: usize, token: &str) -> Result<(), &'static str> {
+        let (id, colour) = self.tokens.get(token).cloned().ok_or("unknown token")?;
+        let hosted = self.games.get_mut(&id).unwrap();
+        if let Some(seat) = hosted.seats[colour as usize].as_mut() {
+            seat.connection = Some(connection);
+        }
+
+        let state = state_message(&id, hosted);
+        self.send(
+            connection,
+            &json!({
+                "type": "joined",
+                "game": id,
+                "colour": colour_name(colour),
+                "token": token,
+            }),
+        );
+        self.send(connection, &state);
+        Ok(())
+    }
+
+    // Play a move in coordinate notation such as "e2e4" or in SAN such as "Nf3"
+    fn play(&mut self, token: &str, text: &str) -> Result<(), &'static str> {
+        let (id, colour) = self.tokens.get(token).cloned().ok_or("unknown token")?;
+        let hosted = self.games.get_mut(&id).unwrap();
+
+        if hosted.result.is_some() {
+            return Err("the game is over");
+        }
+        if hosted.seats.iter().any(|seat| seat.is_none()) {
+            return Err("waiting for an opponent");
+        }
+        if hosted.game.turn != colour {
+            return Err("not your turn");
+        }
+
+        let legal_moves = hosted.game.get_legal_moves();
+        let _move = Move::from_uci(text)
+            .filter(|_move| legal_moves.contains(_move))
+            .or_else(|| hosted.game.parse_san(text))
+            .ok_or("illegal move")?;
+
+        if let Some(clock) = hosted.clock.as_mut() {
+            let left = clock.time_left(colour, colour);
+            clock.remaining[colour as usize] = left.saturating_add(clock.increment);
+            clock.turn_start = Some(Instant::now());
+        }
+
+        hosted.moves.push(hosted.game.format_san(_move));
+        hosted.game.play_move(_move);
+        hosted.result = game_result(&mut hosted.game).or_else(|| {
+            if repetitions(&hosted.game) >= 2 {
+                Some("1/2-1/2 {Threefold repetition}".to_string())
+            } else {
+                None
+            }
+        });
+
+        self.broadcast(&id);
+        Ok(())
+    }
+
+    fn resign(&mut self, token: &str) -> Result<(), &'static str> {
+        let (id, colour) = self.tokens.get(token).cloned().ok_or("unknown token")?;
+        let hosted = self.games.get_mut(&id).unwrap();
+
+        if hosted.result.is_some() {
+            return Err("the game is over");
+        }
+        hosted.result = Some(match colour {
+            Colour::White => "0-1 {White resigns}".to_string(),
+            Colour::Black => "1-0 {Black resigns}".to_string(),
+        });
+
+        self.broadcast(&id);
+        Ok(())
+    }
+
+    fn list(&self, connection: usize) {
+        let mut ids: Vec<&String> = self.games.keys().collect();
+        ids.sort_by_key(|id| id.parse::<u64>().unwrap_or(0));
+
+        let games: Vec<Value> = ids
+            .iter()
+            .map(|&id| {
+                let hosted = &self.games[id];
+                json!({
+                    "game": id,
+                    "variant": hosted.game.variant.name(),
+                    "players": hosted.seats.iter().flatten().count(),
+                    "result": hosted.result,
+                })
+            })
+            .collect();
+
+        self.send(connection, &json!({ "type": "games", "games": games }));
+    }
+
+    // End the games where the side to move has run out of time
+    fn check_clocks(&mut self) {
+        let mut flagged: Vec<String> = Vec::new();
+
+        for (id, hosted) in self.games.iter_mut() {
+            let turn = hosted.game.turn;
+            let out_of_time = match &hosted.clock {
+                Some(clock) => {
+                    clock.turn_start.is_some() && clock.time_left(turn, turn) == Duration::ZERO
+                }
+                None => false,
+            };
+
+            if hosted.result.is_none() && out_of_time {
+                hosted.result = Some(match turn {
+                    Colour::White => "0-1 {White loses on time}".to_string(),
+                    Colour::Black => "1-0 {Black loses on time}".to_string(),
+                });
+                flagged.push(id.clone());
+            }
+        }
+
+        for id in flagged {
+            self.broadcast(&id);
+        }
+    }
+
+    // Send the state of a game to its players and spectators
+    fn broadcast(&self, id: &str) {
+        let hosted = &self.games[id];
+        let state = state_message(id, hosted);
+
+        let players = hosted
+            .seats
+            .iter()
+            .flatten()
+            .filter_map(|seat| seat.connection);
+        for connection in players.chain(hosted.spectators.iter().copied()) {
+            self.send(connection, &state);
+        }
+    }
+}
+
+fn state_message(id: &str, hosted: &HostedGame) -> Value {
+    let turn = hosted.game.turn;
+    let clock = hosted.clock.as_ref().map(|clock| {
+        json!({
+            "white": clock.time_left(Colour::White, turn).as_millis() as u64,
+            "black": clock.time_left(Colour::Black, turn).as_millis() as u64,
+        })
+    });
+
+    json!({
+        "type": "state",
+        "game": id,
+        "variant": hosted.game.variant.name(),
+        "fen": hosted.game.get_fen(),
+        "moves": hosted.moves,
+        "turn": colour_name(turn),
+        "players": {
+            "white": hosted.seats[0].is_some(),
+            "black": hosted.seats[1].is_some(),
+        },
+        "clock": clock,
+        "result": hosted.result,
+    })
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+    use tungstenite::{Message, WebSocket};
+
+    use crate::polyglot::split_mix;
+
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || super::run(listener));
+        address
+    }
+
+    struct Client {
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+    }
+
+    impl Client {
+        fn connect(address: SocketAddr) -> Client {
+            let stream = TcpStream::connect(address).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            Client {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                stream,
+            }
+        }
+
+        fn send(&mut self, message: Value) {
+            writeln!(self.stream, "{}", message).unwrap();
+        }
+
+        fn receive(&mut self) -> Value {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_tcp_game() {
+        let address = start_server();
+
+        let mut alice = Client::connect(address);
+        alice.send(json!({ "type": "create" }));
+        let joined = alice.receive();
+        assert_eq!(joined["colour"], "white");
+        let game = joined["game"].as_str().unwrap().to_string();
+        let alice_token = joined["token"].clone();
+        assert_eq!(alice.receive()["players"]["black"], false);
+
+        let mut bob = Client::connect(address);
+        bob.send(json!({ "type": "join", "game": game }));
+        let joined = bob.receive();
+        assert_eq!(joined["colour"], "black");
+        let bob_token = joined["token"].clone();
+        assert_eq!(bob_token.as_str().unwrap().len(), 32);
+        assert_ne!(bob_token, alice_token);
+
+        // The tokens don't follow from each other like the numbers of a seeded generator
+        let alice_bits = u128::from_str_radix(alice_token.as_str().unwrap(), 16).unwrap();
+        let bob_bits = u128::from_str_radix(bob_token.as_str().unwrap(), 16).unwrap();
+        for half in [alice_bits as u64, (alice_bits >> 64) as u64] {
+            let next = split_mix(half);
+            assert_ne!(bob_bits as u64, next);
+            assert_ne!((bob_bits >> 64) as u64, next);
+        }
+        assert_eq!(bob.receive()["players"]["black"], true);
+        assert_eq!(alice.receive()["players"]["black"], true);
+
+        let mut carol = Client::connect(address);
+        carol.send(json!({ "type": "watch", "game": game }));
+        assert_eq!(carol.receive()["moves"], json!([]));
+
+        // Moves go to the players and the spectator
+        alice.send(json!({ "type": "move", "token": alice_token, "move": "e2e4" }));
+        for client in [&mut alice, &mut bob, &mut carol] {
+            let state = client.receive();
+            assert_eq!(state["moves"], json!(["e4"]));
+            assert_eq!(state["turn"], "black");
+        }
+
+        alice.send(json!({ "type": "move", "token": alice_token, "move": "d2d4" }));
+        assert_eq!(alice.receive()["message"], "not your turn");
+
+        // Bob loses the connection and comes back with the token
+        drop(bob);
+        let mut bob = Client::connect(address);
+        bob.send(json!({ "type": "reconnect", "token": bob_token }));
+        assert_eq!(bob.receive()["colour"], "black");
+        assert_eq!(bob.receive()["moves"], json!(["e4"]));
+
+        bob.send(json!({ "type": "move", "token": bob_token, "move": "e5" }));
+        assert_eq!(alice.receive()["moves"], json!(["e4", "e5"]));
+
+        alice.send(json!({ "type": "list" }));
+        let list = alice.receive();
+        assert_eq!(list["games"][0]["players"], 2);
+    }
+
+    #[test]
+    fn test_time_out_of_range() {
+        let address = start_server();
+
+        let mut alice = Client::connect(address);
+        alice.send(json!({ "type": "create", "time": 1e300 }));
+        assert_eq!(alice.receive()["message"], "time out of range");
+        alice.send(json!({ "type": "create", "time": 60, "increment": 1e300 }));
+        assert_eq!(alice.receive()["message"], "increment out of range");
+
+        // The server still serves this client and new ones
+        alice.send(json!({ "type": "list" }));
+        assert_eq!(alice.receive()["games"], json!([]));
+        let mut bob = Client::connect(address);
+        bob.send(json!({ "type": "create", "time": 60 }));
+        assert_eq!(bob.receive()["type"], "joined");
+        assert_eq!(bob.receive()["clock"]["white"], 60000);
+    }
+
+    fn read_json(socket: &mut WebSocket<TcpStream>) -> Value {
+        match socket.read().unwrap() {
+            Message::Text(text) => serde_json::from_str(text.as_str()).unwrap(),
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_websocket_and_clock() {
+        let address = start_server();
+
+        let stream = TcpStream::connect(address).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/", address), stream).unwrap();
+
+        socket
+            .send(Message::text(
+                json!({ "type": "create", "variant": "atomic", "time": 0.2 }).to_string(),
+            ))
+            .unwrap();
+        let game = read_json(&mut socket)["game"].clone();
+        let state = read_json(&mut socket);
+        assert_eq!(state["variant"], "Atomic");
+        assert_eq!(state["clock"]["white"], 200);
+
+        // White's time runs out once black has joined
+        let mut bob = Client::connect(address);
+        bob.send(json!({ "type": "join", "game": game }));
+        bob.receive();
+        bob.receive();
+        assert_eq!(bob.receive()["result"], "0-1 {White loses on time}");
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    format_square, parse_square, piece_glyph, view_to_tile, Colour, Game, GameState, Piece,
+};
+
+/// Colours of a board drawn as SVG, written as SVG colours such as "#f0d9b5" or "red".
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgTheme {
+    pub light_tile: String,
+    pub dark_tile: String,
+    pub last_move: String,
+    pub check: String,
+    pub white_piece: String,
+    pub black_piece: String,
+    pub arrow: String,
+    pub mark: String,
+}
+
+impl Default for SvgTheme {
+    fn default() -> SvgTheme {
+        SvgTheme {
+            light_tile: "#f0d9b5".to_string(),
+            dark_tile: "#b58863".to_string(),
+            last_move: "#cdd26a".to_string(),
+            check: "#e65046".to_string(),
+            white_piece: "#ffffff".to_string(),
+            black_piece: "#000000".to_string(),
+            arrow: "#15781b".to_string(),
+            mark: "#15781b".to_string(),
+        }
+    }
+}
+
+/// Settings for drawing a board as SVG. Squares are written as "e4", squares that aren't on the
+/// board are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgOptions {
+    /// Width and height of a tile in pixels.
+    pub tile_size: u32,
+    /// The colour shown at the bottom of the board.
+    pub orientation: Colour,
+    /// Letters and numbers of the files and ranks along the edges of the board.
+    pub coordinates: bool,
+    /// Highlight the tiles the last move was played from and to.
+    pub last_move: bool,
+    /// Highlight the king when it's in check.
+    pub check: bool,
+    /// Arrows from one square to another.
+    pub arrows: Vec<(String, String)>,
+    /// Squares marked with a circle.
+    pub marks: Vec<String>,
+    pub theme: SvgTheme,
+}
+
+impl Default for SvgOptions {
+    fn default() -> SvgOptions {
+        SvgOptions {
+            tile_size: 45,
+            orientation: Colour::White,
+            coordinates: true,
+            last_move: true,
+            check: true,
+            arrows: Vec::new(),
+            marks: Vec::new(),
+            theme: SvgTheme::default(),
+        }
+    }
+}
+
+/// Draw the current position of a game as an SVG image.
+pub fn render_svg(game: &Game, options: &SvgOptions) -> String {
+    let size = options.tile_size as f64;
+    let theme = &options.theme;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n",
+        size * 8.0
+    );
+
+    let last_move = match game.history.last() {
+        Some(snapshot) if options.last_move => Some(snapshot.played_move),
+        _ => None,
+    };
+    let checked_king = match game.state {
+        GameState::Check | GameState::Checkmate if options.check => Some((Piece::King, game.turn)),
+        _ => None,
+    };
+
+    for row in 0..8 {
+        for column in 0..8 {
+            let tile = view_to_tile(options.orientation, (column, row));
+            let piece = game.board[tile.0][tile.1];
+
+            let fill = if piece.is_some() && piece == checked_king {
+                &theme.check
+            } else if last_move.is_some_and(|_move| _move.from == tile || _move.to == tile) {
+                &theme.last_move
+            } else if (tile.0 + tile.1).is_multiple_of(2) {
+                &theme.dark_tile
+            } else {
+                &theme.light_tile
+            };
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                column as f64 * size,
+                row as f64 * size,
+                size,
+                size,
+                escape(fill)
+            );
+        }
+    }
+
+    if options.coordinates {
+        svg += &coordinates(options);
+    }
+
+    for mark in options.marks.iter() {
+        if let Some(tile) = parse_square(mark) {
+            let (x, y) = tile_centre(options, tile);
+            svg += &format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                x,
+                y,
+                size * 0.42,
+                escape(&theme.mark),
+                size * 0.08
+            );
+        }
+    }
+
+    for row in 0..8 {
+        for column in 0..8 {
+            let tile = view_to_tile(options.orientation, (column, row));
+            if let Some((piece, colour)) = game.board[tile.0][tile.1] {
+                // The filled glyphs are used for both colours, so the fill shows the colour
+                let (fill, stroke) = match colour {
+                    Colour::White => (&theme.white_piece, &theme.black_piece),
+                    Colour::Black => (&theme.black_piece, &theme.black_piece),
+                };
+                let (x, y) = tile_centre(options, tile);
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"DejaVu Sans, Segoe UI Symbol, sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\">{}</text>\n",
+                    x,
+                    y,
+                    size * 0.8,
+                    escape(fill),
+                    escape(stroke),
+                    size * 0.02,
+                    piece_glyph((piece, Colour::Black))
+                );
+            }
+        }
+    }
+
+    svg += &arrows(options);
+    svg += "</svg>\n";
+    svg
+}
+
+// File letters along the bottom and rank numbers along the left, in the colour of the other
+// tiles so they show on both
+fn coordinates(options: &SvgOptions) -> String {
+    let size = options.tile_size as f64;
+    let font_size = size * 0.25;
+    let mut svg = String::new();
+
+    for index in 0..8 {
+        let tile = view_to_tile(options.orientation, (index, 7));
+        let colour = tile_text_colour(options, tile);
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" text-anchor=\"end\" fill=\"{}\">{}</text>\n",
+            (index + 1) as f64 * size - font_size * 0.3,
+            8.0 * size - font_size * 0.3,
+            font_size,
+            escape(colour),
+            format_square(tile).remove(0)
+        );
+
+        let tile = view_to_tile(options.orientation, (0, index));
+        let colour = tile_text_colour(options, tile);
+        svg += &format!(
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" font-family=\"sans-serif\" fill=\"{}\">{}</text>\n",
+            font_size * 0.3,
+            index as f64 * size + font_size,
+            font_size,
+            escape(colour),
+            tile.1 + 1
+        );
+    }
+
+    svg
+}
+
+fn arrows(options: &SvgOptions) -> String {
+    let size = options.tile_size as f64;
+    let width = size * 0.15;
+    let mut svg = String::new();
+
+    for (from, to) in options.arrows.iter() {
+        let (from, to) = match (parse_square(from), parse_square(to)) {
+            (Some(from), Some(to)) if from != to => (from, to),
+            _ => continue,
+        };
+
+        if svg.is_empty() {
+            svg += &format!(
+                "<defs><marker id=\"arrowhead\" markerWidth=\"4\" markerHeight=\"4\" refX=\"2\" refY=\"2\" orient=\"auto\"><path d=\"M0,0 L4,2 L0,4 z\" fill=\"{}\"/></marker></defs>\n",
+                escape(&options.theme.arrow)
+            );
+        }
+
+        // The line stops short so that the tip of the head is in the centre of the tile
+        let (x1, y1) = tile_centre(options, from);
+        let (x2, y2) = tile_centre(options, to);
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        let shorten = 2.0 * width / length;
+        svg += &format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-opacity=\"0.8\" marker-end=\"url(#arrowhead)\"/>\n",
+            x1,
+            y1,
+            x2 - (x2 - x1) * shorten,
+            y2 - (y2 - y1) * shorten,
+            escape(&options.theme.arrow),
+            width
+        );
+    }
+
+    svg
+}
+
+fn tile_centre(options: &SvgOptions, tile: (usize, usize)) -> (f64, f64) {
+    // Turning the board around is its own inverse
+    let (column, row) = view_to_tile(options.orientation, tile);
+    let size = options.tile_size as f64;
+
+    ((column as f64 + 0.5) * size, (row as f64 + 0.5) * size)
+}
+
+fn tile_text_colour(options: &SvgOptions, tile: (usize, usize)) -> &str {
+    if (tile.0 + tile.1).is_multiple_of(2) {
+        &options.theme.light_tile
+    } else {
+        &options.theme.dark_tile
+    }
+}
+
+// Colours come from the caller, so they are escaped before going into an attribute
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{render_svg, SvgOptions};
+    use crate::{Colour, Game};
+
+    #[test]
+    fn test_render_svg() {
+        let mut game = Game::new();
+        let svg = render_svg(&game, &SvgOptions::default());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"360\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+        assert_eq!(svg.matches("♚").count(), 2);
+        assert_eq!(svg.matches("♟").count(), 16);
+        assert!(!svg.contains("#cdd26a"));
+
+        // White's rook is in the bottom left, or top right when black is at the bottom
+        assert!(svg.contains("<text x=\"22.5\" y=\"337.5\" font-size=\"36\" font-family=\"DejaVu Sans, Segoe UI Symbol, sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"#ffffff\""));
+        let flipped = SvgOptions {
+            orientation: Colour::Black,
+            coordinates: false,
+            ..SvgOptions::default()
+        };
+        let svg = render_svg(&game, &flipped);
+        assert!(svg.contains("<text x=\"337.5\" y=\"22.5\" font-size=\"36\" font-family=\"DejaVu Sans, Segoe UI Symbol, sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"#ffffff\""));
+        assert!(!svg.contains(">a</text>"));
+
+        game.make_move("e2".to_string(), "e4".to_string());
+        let svg = render_svg(&game, &SvgOptions::default());
+        assert_eq!(svg.matches("fill=\"#cdd26a\"").count(), 2);
+        assert!(svg.contains(">a</text>"));
+        assert!(svg.contains(">8</text>"));
+    }
+
+    #[test]
+    fn test_svg_annotations() {
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1".to_string()).unwrap();
+        game.make_move("a1".to_string(), "a8".to_string());
+
+        let mut options = SvgOptions {
+            arrows: vec![
+                ("a8".to_string(), "e8".to_string()),
+                ("a8".to_string(), "z9".to_string()),
+            ],
+            marks: vec!["g8".to_string(), "".to_string()],
+            ..SvgOptions::default()
+        };
+        options.theme.check = "red\"/><script/>".to_string();
+        let svg = render_svg(&game, &options);
+
+        // The mated king is highlighted, with the colour escaped
+        assert!(svg.contains("fill=\"red&quot;/&gt;&lt;script/&gt;\""));
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert!(svg.contains("<line x1=\"22.5\" y1=\"22.5\" x2=\"189\" y2=\"22.5\""));
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains("<circle cx=\"292.5\" cy=\"22.5\""));
+    }
+}
@@ -0,0 +1,1472 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::variant::Variant;
+use crate::{check_for_checked, Colour, Game, Move, Piece};
+
+/// The outcome of a position with perfect play, from the point of view of the side to move.
+/// A cursed win or blessed loss is a win or loss that the fifty move rule turns into a draw.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+
+    fn negate(self) -> Wdl {
+        Wdl::from_value(-(self as i32))
+    }
+}
+
+/// A tablebase move and what it leads to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TablebaseMove {
+    pub chess_move: Move,
+    /// The outcome for the side that made the move.
+    pub wdl: Wdl,
+    /// Half moves until the fifty move counter is reset by a capture or pawn move,
+    /// positive when winning and negative when losing.
+    pub dtz: i32,
+}
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+
+// Flags of a table's pairs data
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// The most pieces a Syzygy table can have
+const MAX_PIECES: usize = 7;
+
+#[derive(Copy, Clone, PartialEq)]
+enum TableType {
+    Wdl,
+    Dtz,
+}
+
+// What a probe found out besides the value
+#[derive(Copy, Clone, PartialEq)]
+enum ProbeState {
+    Ok,
+    // The DTZ table only stores the other side to move
+    ChangeStm,
+    // The best move resets the fifty move counter, so the DTZ table value can't be used
+    ZeroingBestMove,
+}
+
+/// Syzygy endgame tablebases (.rtbw and .rtbz files) in one or more local directories.
+/// Tables are read into memory the first time they are needed.
+pub struct Tablebase {
+    files: HashMap<String, PathBuf>,
+    tables: Mutex<HashMap<String, Option<Arc<Table>>>>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    /// Find all tablebase files in a directory.
+    pub fn open<P: AsRef<Path>>(directory: P) -> io::Result<Tablebase> {
+        let mut tablebase = Tablebase {
+            files: HashMap::new(),
+            tables: Mutex::new(HashMap::new()),
+            max_pieces: 0,
+        };
+        tablebase.add_directory(directory)?;
+        Ok(tablebase)
+    }
+
+    /// Also use the tablebase files in another directory.
+    pub fn add_directory<P: AsRef<Path>>(&mut self, directory: P) -> io::Result<()> {
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            let material = match name
+                .strip_suffix(".rtbw")
+                .or_else(|| name.strip_suffix(".rtbz"))
+            {
+                Some(material) => material,
+                None => continue,
+            };
+
+            if parse_material(material).is_some() {
+                let pieces = material.chars().filter(|&c| c != 'v').count();
+                self.max_pieces = self.max_pieces.max(pieces);
+                self.files.insert(name, path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The most pieces of any table found, positions with more pieces can't be probed.
+    pub fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    /// Look up if the side to move wins, draws or loses. Returns None if the
+    /// table is missing or the position has castling rights.
+    pub fn probe_wdl(&self, game: &Game) -> Option<Wdl> {
+        let mut position = self.probe_position(game)?;
+        self.search(&mut position, false).map(|(wdl, _)| wdl)
+    }
+
+    /// Look up the distance to zeroing the fifty move counter, in half moves. Positive
+    /// when the side to move wins and negative when it loses, 0 for draws.
+    pub fn probe_dtz(&self, game: &Game) -> Option<i32> {
+        let mut position = self.probe_position(game)?;
+        self.dtz(&mut position)
+    }
+
+    /// All legal moves with their outcome, best first. Wins are sorted by the shortest
+    /// distance to zeroing, losses by the longest.
+    pub fn probe_root(&self, game: &Game) -> Option<Vec<TablebaseMove>> {
+        let mut position = self.probe_position(game)?;
+        let mut moves: Vec<TablebaseMove> = Vec::new();
+
+        for _move in position.get_legal_moves() {
+            let zeroing = is_zeroing(&position, _move);
+            position.push_move(_move);
+
+            let dtz = if zeroing {
+                let (wdl, _) = self.search(&mut position, false)?;
+                dtz_before_zeroing(wdl.negate())
+            } else if position.halfmove_clock >= 100 || position.is_repetition() {
+                0
+            } else {
+                let dtz = -self.dtz(&mut position)?;
+                dtz + dtz.signum()
+            };
+
+            // A mating move always has a distance of 1
+            let mates = position.get_legal_moves().is_empty()
+                && check_for_checked(position.turn, &position);
+            let dtz = if mates { 1 } else { dtz };
+
+            position.pop_move();
+
+            moves.push(TablebaseMove {
+                chess_move: _move,
+                wdl: wdl_from_dtz(dtz),
+                dtz,
+            });
+        }
+
+        // Wins that can be converted before the fifty move rule first, then the rest
+        let halfmove_clock = game.halfmove_clock as i32;
+        moves.sort_by_key(|tablebase_move| {
+            let dtz = tablebase_move.dtz;
+            if dtz > 0 {
+                if dtz + halfmove_clock <= 100 {
+                    (-3, dtz)
+                } else {
+                    (-2, dtz)
+                }
+            } else if dtz == 0 {
+                (0, 0)
+            } else if -dtz + halfmove_clock <= 100 {
+                (2, dtz)
+            } else {
+                (1, dtz)
+            }
+        });
+
+        Some(moves)
+    }
+
+    /// The move that keeps the best outcome and makes the most progress towards it.
+    pub fn best_move(&self, game: &Game) -> Option<Move> {
+        self.probe_root(game)?
+            .first()
+            .map(|tablebase_move| tablebase_move.chess_move)
+    }
+
+    // Tables don't store castling rights or other variants' rules, and there must be a table
+    // for the material
+    fn probe_position(&self, game: &Game) -> Option<Game> {
+        let pieces = game
+            .board
+            .iter()
+            .flatten()
+            .filter(|tile| tile.is_some())
+            .count();
+
+        if game.move_made
+            || game.variant != Variant::Standard
+            || pieces > self.max_pieces.max(2)
+            || game
+                .castling_rooks
+                .iter()
+                .flatten()
+                .any(|rook| rook.is_some())
+        {
+            return None;
+        }
+
+        Some(game.clone())
+    }
+
+    // Find the table for a position, and if the colours have to be swapped to use it
+    fn table(&self, position: &Game, table_type: TableType) -> Option<(Arc<Table>, bool)> {
+        let white = material_name(position, Colour::White);
+        let black = material_name(position, Colour::Black);
+        let extension = match table_type {
+            TableType::Wdl => "rtbw",
+            TableType::Dtz => "rtbz",
+        };
+
+        for (name, flipped) in [
+            (format!("{}v{}", white, black), false),
+            (format!("{}v{}", black, white), true),
+        ] {
+            let file_name = format!("{}.{}", name, extension);
+            let path = match self.files.get(&file_name) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let mut tables = self.tables.lock().unwrap();
+            let table = tables
+                .entry(file_name)
+                .or_insert_with(|| {
+                    let data = fs::read(path).ok()?;
+                    Table::new(data, &name, table_type).map(Arc::new)
+                })
+                .clone();
+
+            return table.map(|table| (table, flipped));
+        }
+
+        None
+    }
+
+    // Captures (and pawn moves when looking for DTZ) are not always stored correctly in the
+    // tables, so they have to be searched
+    fn search(&self, position: &mut Game, check_zeroing_moves: bool) -> Option<(Wdl, ProbeState)> {
+        let moves = position.get_legal_moves();
+        let mut best = Wdl::Loss;
+        let mut move_count = 0;
+
+        for _move in moves.iter() {
+            let capture = is_capture(position, *_move);
+            if !(capture || check_zeroing_moves && is_pawn_move(position, *_move)) {
+                continue;
+            }
+
+            move_count += 1;
+
+            position.push_move(*_move);
+            let result = self.search(position, false);
+            position.pop_move();
+
+            let value = result?.0.negate();
+            if value > best {
+                best = value;
+
+                if value >= Wdl::Win {
+                    return Some((value, ProbeState::ZeroingBestMove));
+                }
+            }
+        }
+
+        // Without any other moves the table isn't needed, it may even be wrong
+        let no_more_moves = move_count > 0 && move_count == moves.len();
+
+        let value = if no_more_moves {
+            best
+        } else if moves.is_empty() {
+            if check_for_checked(position.turn, position) {
+                Wdl::Loss
+            } else {
+                Wdl::Draw
+            }
+        } else {
+            let (table, flipped) = self.table_or_draw(position, TableType::Wdl)?;
+            match table {
+                Some(table) => Wdl::from_value(table.probe(position, flipped, Wdl::Draw)?.0 - 2),
+                None => Wdl::Draw,
+            }
+        };
+
+        if best >= value {
+            let state = if best > Wdl::Draw || no_more_moves {
+                ProbeState::ZeroingBestMove
+            } else {
+                ProbeState::Ok
+            };
+            return Some((best, state));
+        }
+
+        Some((value, ProbeState::Ok))
+    }
+
+    // Two kings are always a draw and have no table
+    fn table_or_draw(
+        &self,
+        position: &Game,
+        table_type: TableType,
+    ) -> Option<(Option<Arc<Table>>, bool)> {
+        let pieces = position
+            .board
+            .iter()
+            .flatten()
+            .filter(|tile| tile.is_some())
+            .count();
+
+        if pieces == 2 {
+            return Some((None, false));
+        }
+
+        let (table, flipped) = self.table(position, table_type)?;
+        Some((Some(table), flipped))
+    }
+
+    fn dtz(&self, position: &mut Game) -> Option<i32> {
+        let (wdl, state) = self.search(position, true)?;
+
+        if wdl == Wdl::Draw {
+            return Some(0);
+        }
+
+        if state == ProbeState::ZeroingBestMove {
+            return Some(dtz_before_zeroing(wdl));
+        }
+
+        let (table, flipped) = self.table_or_draw(position, TableType::Dtz)?;
+        let (dtz, state) = match table {
+            Some(table) => table.probe(position, flipped, wdl)?,
+            None => return Some(0),
+        };
+
+        if state != ProbeState::ChangeStm {
+            let cursed = wdl == Wdl::BlessedLoss || wdl == Wdl::CursedWin;
+            return Some((dtz + if cursed { 100 } else { 0 }) * (wdl as i32).signum());
+        }
+
+        // The table only stores the other side to move, so look one move ahead
+        let mut min_dtz = i32::MAX;
+
+        for _move in position.get_legal_moves() {
+            let zeroing = is_zeroing(position, _move);
+            position.push_move(_move);
+
+            let result = if zeroing {
+                self.search(position, false)
+                    .map(|(wdl, _)| -dtz_before_zeroing(wdl))
+            } else {
+                self.dtz(position).map(|dtz| -dtz)
+            };
+
+            let mates =
+                position.get_legal_moves().is_empty() && check_for_checked(position.turn, position);
+            position.pop_move();
+
+            let mut dtz = result?;
+            if dtz == 1 && mates {
+                min_dtz = 1;
+            }
+
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == (wdl as i32).signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+}
+
+fn dtz_before_zeroing(wdl: Wdl) -> i32 {
+    match wdl {
+        Wdl::Win => 1,
+        Wdl::CursedWin => 101,
+        Wdl::Draw => 0,
+        Wdl::BlessedLoss => -101,
+        Wdl::Loss => -1,
+    }
+}
+
+fn wdl_from_dtz(dtz: i32) -> Wdl {
+    match dtz {
+        _ if dtz > 100 => Wdl::CursedWin,
+        _ if dtz > 0 => Wdl::Win,
+        0 => Wdl::Draw,
+        _ if dtz < -100 => Wdl::BlessedLoss,
+        _ => Wdl::Loss,
+    }
+}
+
+// A Chess960 king taking its own rook is castling, not a capture
+fn is_capture(game: &Game, _move: Move) -> bool {
+    (game.board[_move.to.0][_move.to.1].is_some()
+        && game.get_castling(_move.from, _move.to).is_none())
+        || (is_pawn_move(game, _move) && _move.from.0 != _move.to.0)
+}
+
+fn is_pawn_move(game: &Game, _move: Move) -> bool {
+    game.board[_move.from.0][_move.from.1].map(|piece| piece.0) == Some(Piece::Pawn)
+}
+
+fn is_zeroing(game: &Game, _move: Move) -> bool {
+    is_capture(game, _move) || is_pawn_move(game, _move)
+}
+
+// The pieces of one colour as written in table names, such as "KRP"
+fn material_name(game: &Game, colour: Colour) -> String {
+    let mut name = String::new();
+
+    for (piece, letter) in [
+        (Piece::King, 'K'),
+        (Piece::Queen, 'Q'),
+        (Piece::Rook, 'R'),
+        (Piece::Bishop, 'B'),
+        (Piece::Knight, 'N'),
+        (Piece::Pawn, 'P'),
+    ] {
+        for _ in game
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&tile| tile == Some((piece, colour)))
+        {
+            name.push(letter);
+        }
+    }
+
+    name
+}
+
+// Piece codes used by the tables, black pieces have 8 added
+fn piece_code(piece: (Piece, Colour)) -> u8 {
+    let code = match piece.0 {
+        Piece::Pawn => 1,
+        Piece::Knight => 2,
+        Piece::Bishop => 3,
+        Piece::Rook => 4,
+        Piece::Queen => 5,
+        Piece::King => 6,
+    };
+
+    match piece.1 {
+        Colour::White => code,
+        Colour::Black => code + 8,
+    }
+}
+
+// Split a table name like "KRPvKR" into the piece letters of both sides
+fn parse_material(name: &str) -> Option<(&str, &str)> {
+    let (first, second) = name.split_once('v')?;
+    let valid = |side: &str| {
+        side.starts_with('K')
+            && side[1..].chars().all(|c| "QRBNP".contains(c))
+            && side.len() < MAX_PIECES
+    };
+
+    if valid(first) && valid(second) && first.len() + second.len() <= MAX_PIECES {
+        Some((first, second))
+    } else {
+        None
+    }
+}
+
+// Indexing tables shared by all tablebase files
+struct Encoding {
+    binomial: [[u64; 64]; 6],
+    map_pawns: [usize; 64],
+    lead_pawn_index: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+    map_b1h1h7: [u64; 64],
+    map_a1d1d4: [u64; 64],
+    map_kk: [[u64; 64]; 10],
+}
+
+fn square_file(square: usize) -> usize {
+    square & 7
+}
+
+fn square_rank(square: usize) -> usize {
+    square >> 3
+}
+
+// Positive above the a1-h8 diagonal, negative below
+fn off_diagonal(square: usize) -> i32 {
+    square_rank(square) as i32 - square_file(square) as i32
+}
+
+fn flip_file(square: usize) -> usize {
+    square ^ 7
+}
+
+fn flip_rank(square: usize) -> usize {
+    square ^ 56
+}
+
+fn flip_diagonal(square: usize) -> usize {
+    ((square >> 3) | (square << 3)) & 63
+}
+
+fn encoding() -> &'static Encoding {
+    static ENCODING: OnceLock<Encoding> = OnceLock::new();
+    ENCODING.get_or_init(Encoding::new)
+}
+
+impl Encoding {
+    fn new() -> Encoding {
+        let mut encoding = Encoding {
+            binomial: [[0; 64]; 6],
+            map_pawns: [0; 64],
+            lead_pawn_index: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+        };
+
+        // Ways to choose k pieces out of n squares
+        encoding.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                encoding.binomial[k][n] = if k > 0 {
+                    encoding.binomial[k - 1][n - 1]
+                } else {
+                    0
+                } + if k < n {
+                    encoding.binomial[k][n - 1]
+                } else {
+                    0
+                };
+            }
+        }
+
+        // Squares below the a1-h8 diagonal
+        let mut code = 0;
+        for square in 0..64 {
+            if off_diagonal(square) < 0 {
+                encoding.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // The a1-d1-d4 triangle, with the diagonal last
+        let mut diagonal = Vec::new();
+        let mut code = 0;
+        for square in 0..28 {
+            if square_file(square) > 3 {
+                continue;
+            }
+
+            if off_diagonal(square) < 0 {
+                encoding.map_a1d1d4[square] = code;
+                code += 1;
+            } else if off_diagonal(square) == 0 {
+                diagonal.push(square);
+            }
+        }
+        for square in diagonal {
+            encoding.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // All legal placements of two kings with the first in the a1-d1-d4 triangle
+        let mut both_on_diagonal = Vec::new();
+        let mut code = 0;
+        for index in 0..10 {
+            for first in 0..28 {
+                if square_file(first) > 3 || off_diagonal(first) > 0 {
+                    continue;
+                }
+                if encoding.map_a1d1d4[first] != index as u64 || (index == 0 && first != 1) {
+                    continue;
+                }
+
+                for second in 0..64 {
+                    let file_distance =
+                        (square_file(first) as i32 - square_file(second) as i32).abs();
+                    let rank_distance =
+                        (square_rank(first) as i32 - square_rank(second) as i32).abs();
+
+                    // Kings can't touch, and with the first on the diagonal the second
+                    // can't be above it
+                    let touching = file_distance <= 1 && rank_distance <= 1;
+                    if touching || (off_diagonal(first) == 0 && off_diagonal(second) > 0) {
+                        continue;
+                    } else if off_diagonal(first) == 0 && off_diagonal(second) == 0 {
+                        both_on_diagonal.push((index, second));
+                    } else {
+                        encoding.map_kk[index][second] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (index, second) in both_on_diagonal {
+            encoding.map_kk[index][second] = code;
+            code += 1;
+        }
+
+        // Pawn squares a2-h7, numbered so that the leading pawn has the highest value
+        let mut available = 47;
+        for lead_pawns in 1..6 {
+            for file in 0..4 {
+                let mut index = 0;
+
+                for rank in 1..7 {
+                    let square = rank * 8 + file;
+
+                    if lead_pawns == 1 {
+                        encoding.map_pawns[square] = available;
+                        available -= 1;
+                        encoding.map_pawns[flip_file(square)] = available;
+                        available = available.saturating_sub(1);
+                    }
+
+                    encoding.lead_pawn_index[lead_pawns][square] = index;
+                    index += encoding.binomial[lead_pawns - 1][encoding.map_pawns[square]];
+                }
+
+                encoding.lead_pawns_size[lead_pawns][file] = index;
+            }
+        }
+
+        encoding
+    }
+}
+
+// Compressed values for one side to move and leading file of a table
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    max_symbol_length: u8,
+    min_symbol_length: u8,
+    block_size: usize,
+    span: usize,
+    blocks: usize,
+    block_length_size: usize,
+    sparse_index_size: usize,
+    // Offsets into the file
+    lowest_symbol: usize,
+    tree: usize,
+    block_length: usize,
+    sparse_index: usize,
+    data: usize,
+    base: Vec<u64>,
+    symbol_length: Vec<u8>,
+    pieces: [u8; MAX_PIECES],
+    group_index: [u64; MAX_PIECES + 1],
+    group_length: [usize; MAX_PIECES + 1],
+    map_index: [usize; 4],
+}
+
+struct Table {
+    data: Vec<u8>,
+    table_type: TableType,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+    // Pawns of the leading colour and of the other colour
+    pawn_count: [usize; 2],
+    // Indexed by side to move and then file of the leading pawn
+    pairs: Vec<Vec<PairsData>>,
+    map: usize,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64_be(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+impl Table {
+    fn new(data: Vec<u8>, name: &str, table_type: TableType) -> Option<Table> {
+        let magic = match table_type {
+            TableType::Wdl => WDL_MAGIC,
+            TableType::Dtz => DTZ_MAGIC,
+        };
+        if data.get(0..4)? != magic {
+            return None;
+        }
+
+        let (first, second) = parse_material(name)?;
+        let count = |side: &str, letter: char| side.chars().filter(|&c| c == letter).count();
+
+        let has_unique_pieces = "QRBNP"
+            .chars()
+            .any(|letter| count(first, letter) == 1 || count(second, letter) == 1);
+
+        // The leading colour is the one with fewer pawns, as long as it has any
+        let (first_pawns, second_pawns) = (count(first, 'P'), count(second, 'P'));
+        let first_leads = second_pawns == 0 || (first_pawns > 0 && second_pawns >= first_pawns);
+        let pawn_count = if first_leads {
+            [first_pawns, second_pawns]
+        } else {
+            [second_pawns, first_pawns]
+        };
+
+        let mut table = Table {
+            data,
+            table_type,
+            symmetric: first == second,
+            has_pawns: first_pawns + second_pawns > 0,
+            has_unique_pieces,
+            piece_count: first.len() + second.len(),
+            pawn_count,
+            pairs: Vec::new(),
+            map: 0,
+        };
+
+        table.read_header()?;
+        Some(table)
+    }
+
+    fn read_header(&mut self) -> Option<()> {
+        let data = &self.data;
+        let mut offset = 4;
+
+        let split = data.get(offset)? & 1 != 0;
+        offset += 1;
+
+        let sides = if self.table_type == TableType::Wdl && split {
+            2
+        } else {
+            1
+        };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let pawns_on_both_sides = self.has_pawns && self.pawn_count[1] > 0;
+
+        self.pairs = vec![vec![PairsData::default(); files]; sides];
+
+        for file in 0..files {
+            let order = [
+                [
+                    data.get(offset)? & 0xf,
+                    if pawns_on_both_sides {
+                        data.get(offset + 1)? & 0xf
+                    } else {
+                        0xf
+                    },
+                ],
+                [
+                    data.get(offset)? >> 4,
+                    if pawns_on_both_sides {
+                        data.get(offset + 1)? >> 4
+                    } else {
+                        0xf
+                    },
+                ],
+            ];
+            offset += 1 + pawns_on_both_sides as usize;
+
+            for _k in 0..self.piece_count {
+                let byte = *data.get(offset)?;
+                for side in 0..sides {
+                    self.pairs[side][file].pieces[_k] =
+                        if side == 0 { byte & 0xf } else { byte >> 4 };
+                }
+                offset += 1;
+            }
+
+            for (side, order) in order.iter().enumerate().take(sides) {
+                let mut pairs = self.pairs[side][file].clone();
+                self.set_groups(&mut pairs, *order, file);
+                self.pairs[side][file] = pairs;
+            }
+        }
+
+        offset += offset & 1;
+
+        for file in 0..files {
+            for side in 0..sides {
+                let mut pairs = self.pairs[side][file].clone();
+                offset = set_sizes(&mut pairs, &self.data, offset)?;
+                self.pairs[side][file] = pairs;
+            }
+        }
+
+        if self.table_type == TableType::Dtz {
+            self.map = offset;
+
+            for file in 0..files {
+                let flags = self.pairs[0][file].flags;
+                if flags & FLAG_MAPPED == 0 {
+                    continue;
+                }
+
+                if flags & FLAG_WIDE != 0 {
+                    offset += offset & 1;
+                    for _i in 0..4 {
+                        self.pairs[0][file].map_index[_i] = (offset - self.map) / 2 + 1;
+                        offset += 2 * read_u16(&self.data, offset)? as usize + 2;
+                    }
+                } else {
+                    for _i in 0..4 {
+                        self.pairs[0][file].map_index[_i] = offset - self.map + 1;
+                        offset += *self.data.get(offset)? as usize + 1;
+                    }
+                }
+            }
+
+            offset += offset & 1;
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.sparse_index = offset;
+                offset += pairs.sparse_index_size * 6;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                let pairs = &mut self.pairs[side][file];
+                pairs.block_length = offset;
+                offset += pairs.block_length_size * 2;
+            }
+        }
+
+        for file in 0..files {
+            for side in 0..sides {
+                offset = (offset + 0x3f) & !0x3f;
+                let pairs = &mut self.pairs[side][file];
+                pairs.data = offset;
+                offset += pairs.blocks * pairs.block_size;
+            }
+        }
+
+        if offset > self.data.len() {
+            return None;
+        }
+
+        Some(())
+    }
+
+    // Pieces that are encoded together: the leading pieces or pawns, then one group for
+    // every kind of piece
+    fn set_groups(&self, pairs: &mut PairsData, order: [u8; 2], file: usize) {
+        let encoding = encoding();
+        let mut first_length: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+
+        let mut n = 0;
+        pairs.group_length[n] = 1;
+
+        for _i in 1..self.piece_count {
+            first_length -= 1;
+            if first_length > 0 || pairs.pieces[_i] == pairs.pieces[_i - 1] {
+                pairs.group_length[n] += 1;
+            } else {
+                n += 1;
+                pairs.group_length[n] = 1;
+            }
+        }
+
+        n += 1;
+        pairs.group_length[n] = 0;
+
+        let pawns_on_both_sides = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64
+            - pairs.group_length[0]
+            - if pawns_on_both_sides {
+                pairs.group_length[1]
+            } else {
+                0
+            };
+        let mut index: u64 = 1;
+
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                pairs.group_index[0] = index;
+                index *= if self.has_pawns {
+                    encoding.lead_pawns_size[pairs.group_length[0]][file]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                pairs.group_index[1] = index;
+                index *= encoding.binomial[pairs.group_length[1]][48 - pairs.group_length[0]];
+            } else {
+                pairs.group_index[next] = index;
+                index *= encoding.binomial[pairs.group_length[next]][free_squares];
+                free_squares -= pairs.group_length[next];
+                next += 1;
+            }
+
+            k += 1;
+        }
+
+        pairs.group_index[n] = index;
+    }
+
+    // Find the value stored for a position. Colours are swapped when the table has black
+    // as the stronger side, or the sides are equal and black is to move.
+    fn probe(&self, position: &Game, flipped: bool, wdl: Wdl) -> Option<(i32, ProbeState)> {
+        let encoding = encoding();
+        let flip = flipped || (self.symmetric && position.turn == Colour::Black);
+        let flip_colour = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip != (position.turn == Colour::Black)) as usize;
+
+        let mut squares: Vec<usize> = Vec::new();
+        let mut pieces: Vec<u8> = Vec::new();
+        let mut lead_pawn_count = 0;
+        let mut file = 0;
+
+        // Pieces by square, a1 to h8
+        let mut occupied: Vec<(usize, u8)> = Vec::new();
+        for square in 0..64 {
+            if let Some(piece) = position.board[square_file(square)][square_rank(square)] {
+                occupied.push((square, piece_code(piece)));
+            }
+        }
+
+        if self.has_pawns {
+            // The leading pawns are listed first in every table of the file
+            let lead_pawn = self.pairs[0][0].pieces[0] ^ flip_colour;
+
+            for &(square, code) in occupied.iter() {
+                if code == lead_pawn {
+                    squares.push(square ^ flip_squares);
+                    pieces.push(code ^ flip_colour);
+                }
+            }
+            lead_pawn_count = squares.len();
+
+            let leading = (0..lead_pawn_count)
+                .max_by_key(|&_i| (encoding.map_pawns[squares[_i]], std::cmp::Reverse(_i)))?;
+            squares.swap(0, leading);
+
+            file = square_file(squares[0]).min(7 - square_file(squares[0]));
+        }
+
+        let pairs = &self.pairs[stm % self.pairs.len()][file];
+
+        if self.table_type == TableType::Dtz
+            && (pairs.flags & FLAG_STM) as usize != stm
+            && (self.has_pawns || !self.symmetric)
+        {
+            return Some((0, ProbeState::ChangeStm));
+        }
+
+        for &(square, code) in occupied.iter() {
+            if self.has_pawns && code == self.pairs[0][0].pieces[0] ^ flip_colour {
+                continue;
+            }
+            squares.push(square ^ flip_squares);
+            pieces.push(code ^ flip_colour);
+        }
+
+        if squares.len() != self.piece_count {
+            return None;
+        }
+
+        // Put the pieces in the order the table uses
+        let size = squares.len();
+        for _i in lead_pawn_count..size.saturating_sub(1) {
+            for _j in (_i + 1)..size {
+                if pairs.pieces[_i] == pieces[_j] {
+                    pieces.swap(_i, _j);
+                    squares.swap(_i, _j);
+                    break;
+                }
+            }
+        }
+
+        // Mirror so that the leading piece is on files a-d
+        if square_file(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square = flip_file(*square);
+            }
+        }
+
+        let mut index: u64;
+
+        if self.has_pawns {
+            index = encoding.lead_pawn_index[lead_pawn_count][squares[0]];
+
+            squares[1..lead_pawn_count].sort_by_key(|&square| encoding.map_pawns[square]);
+
+            for (_i, &square) in squares.iter().enumerate().take(lead_pawn_count).skip(1) {
+                index += encoding.binomial[_i][encoding.map_pawns[square]];
+            }
+        } else {
+            // Mirror so that the leading piece is on ranks 1-4
+            if square_rank(squares[0]) > 3 {
+                for square in squares.iter_mut() {
+                    *square = flip_rank(*square);
+                }
+            }
+
+            // Mirror along the diagonal so that the first piece off it is below it
+            for _i in 0..pairs.group_length[0] {
+                if off_diagonal(squares[_i]) == 0 {
+                    continue;
+                }
+
+                if off_diagonal(squares[_i]) > 0 {
+                    for square in squares[_i..].iter_mut() {
+                        *square = flip_diagonal(*square);
+                    }
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let rank = |square: usize| square_rank(square) as u64;
+
+                index = if off_diagonal(squares[0]) != 0 {
+                    (encoding.map_a1d1d4[squares[0]] * 63 + (squares[1] as u64 - adjust1)) * 62
+                        + squares[2] as u64
+                        - adjust2
+                } else if off_diagonal(squares[1]) != 0 {
+                    (6 * 63 + rank(squares[0]) * 28 + encoding.map_b1h1h7[squares[1]]) * 62
+                        + squares[2] as u64
+                        - adjust2
+                } else if off_diagonal(squares[2]) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(squares[0]) * 7 * 28
+                        + (rank(squares[1]) - adjust1) * 28
+                        + encoding.map_b1h1h7[squares[2]]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(squares[0]) * 7 * 6
+                        + (rank(squares[1]) - adjust1) * 6
+                        + (rank(squares[2]) - adjust2)
+                };
+            } else {
+                index = encoding.map_kk[encoding.map_a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+
+        // The remaining groups, each sorted by square
+        index *= pairs.group_index[0];
+        let mut group_start = pairs.group_length[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+
+        while pairs.group_length[next] > 0 {
+            let group_end = group_start + pairs.group_length[next];
+            squares[group_start..group_end].sort_unstable();
+
+            let mut n: u64 = 0;
+            for _i in group_start..group_end {
+                let adjust = squares[..group_start]
+                    .iter()
+                    .filter(|&&square| squares[_i] > square)
+                    .count();
+                let pawn_offset = if remaining_pawns { 8 } else { 0 };
+                n += encoding.binomial[_i - group_start + 1][squares[_i] - adjust - pawn_offset];
+            }
+
+            remaining_pawns = false;
+            index += n * pairs.group_index[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = self.decompress(pairs, index)? as i32;
+
+        Some((self.map_score(file, value, wdl)?, ProbeState::Ok))
+    }
+
+    // DTZ values are stored in order of how common they are
+    fn map_score(&self, file: usize, value: i32, wdl: Wdl) -> Option<i32> {
+        if self.table_type == TableType::Wdl {
+            return Some(value);
+        }
+
+        let pairs = &self.pairs[0][file];
+        let mut value = value;
+
+        if pairs.flags & FLAG_MAPPED != 0 {
+            let map = match wdl {
+                Wdl::Loss => 1,
+                Wdl::BlessedLoss => 3,
+                Wdl::Draw => 0,
+                Wdl::CursedWin => 2,
+                Wdl::Win => 0,
+            };
+            let position = pairs.map_index[map] + value as usize;
+
+            value = if pairs.flags & FLAG_WIDE != 0 {
+                read_u16(&self.data, self.map + 2 * position)? as i32
+            } else {
+                *self.data.get(self.map + position)? as i32
+            };
+        }
+
+        // Values are stored in full moves unless the table says otherwise
+        let in_moves = match wdl {
+            Wdl::Win => pairs.flags & FLAG_WIN_PLIES == 0,
+            Wdl::Loss => pairs.flags & FLAG_LOSS_PLIES == 0,
+            Wdl::CursedWin | Wdl::BlessedLoss => true,
+            Wdl::Draw => false,
+        };
+
+        if in_moves {
+            value *= 2;
+        }
+
+        Some(value + 1)
+    }
+
+    // Canonical Huffman decoding of the block that holds the index, then expanding the
+    // recursive pairing of symbols until the value is found
+    fn decompress(&self, pairs: &PairsData, index: u64) -> Option<u32> {
+        let data = &self.data;
+
+        if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+            return Some(pairs.min_symbol_length as u32);
+        }
+
+        let span = pairs.span as u64;
+        let k = (index / span) as usize;
+
+        let mut block = read_u32(data, pairs.sparse_index + 6 * k)? as usize;
+        let mut offset = read_u16(data, pairs.sparse_index + 6 * k + 4)? as i64;
+
+        offset += (index % span) as i64 - (span / 2) as i64;
+
+        let block_length = |block: usize| {
+            read_u16(data, pairs.block_length + 2 * block).map(|length| length as i64)
+        };
+
+        while offset < 0 {
+            block = block.checked_sub(1)?;
+            offset += block_length(block)? + 1;
+        }
+
+        while offset > block_length(block)? {
+            offset -= block_length(block)? + 1;
+            block += 1;
+        }
+
+        let mut pointer = pairs.data + block * pairs.block_size;
+        let mut buffer = read_u64_be(data, pointer)?;
+        pointer += 8;
+        let mut buffer_size = 64;
+        let min_length = pairs.min_symbol_length as usize;
+        let lowest = |length: usize| read_u16(data, pairs.lowest_symbol + 2 * length);
+
+        let mut symbol;
+        loop {
+            let mut length = 0;
+            while buffer < *pairs.base.get(length)? {
+                length += 1;
+            }
+
+            symbol = ((buffer - pairs.base[length]) >> (64 - length - min_length)) as usize;
+            symbol += lowest(length)? as usize;
+
+            let symbol_length = *pairs.symbol_length.get(symbol)? as i64;
+            if offset < symbol_length + 1 {
+                break;
+            }
+
+            offset -= symbol_length + 1;
+            length += min_length;
+            buffer = buffer.checked_shl(length as u32).unwrap_or(0);
+            buffer_size -= length as i32;
+
+            if buffer_size <= 32 {
+                buffer_size += 32;
+                buffer |= (read_u32_be(data, pointer)? as u64) << (64 - buffer_size);
+                pointer += 4;
+            }
+        }
+
+        while pairs.symbol_length[symbol] > 0 {
+            let left = tree_left(data, pairs.tree, symbol)?;
+
+            if offset < pairs.symbol_length[left] as i64 + 1 {
+                symbol = left;
+            } else {
+                offset -= pairs.symbol_length[left] as i64 + 1;
+                symbol = tree_right(data, pairs.tree, symbol)?;
+            }
+        }
+
+        tree_left(data, pairs.tree, symbol).map(|value| value as u32)
+    }
+}
+
+// Every symbol of the pairing tree is 3 bytes: 12 bits left child and 12 bits right child
+fn tree_left(data: &[u8], tree: usize, symbol: usize) -> Option<usize> {
+    let bytes = data.get(tree + 3 * symbol..tree + 3 * symbol + 3)?;
+    Some((((bytes[1] & 0xf) as usize) << 8) | bytes[0] as usize)
+}
+
+fn tree_right(data: &[u8], tree: usize, symbol: usize) -> Option<usize> {
+    let bytes = data.get(tree + 3 * symbol..tree + 3 * symbol + 3)?;
+    Some(((bytes[2] as usize) << 4) | (bytes[1] >> 4) as usize)
+}
+
+fn set_sizes(pairs: &mut PairsData, data: &[u8], mut offset: usize) -> Option<usize> {
+    pairs.flags = *data.get(offset)?;
+    offset += 1;
+
+    if pairs.flags & FLAG_SINGLE_VALUE != 0 {
+        pairs.min_symbol_length = *data.get(offset)?;
+        return Some(offset + 1);
+    }
+
+    let groups = pairs.group_length.iter().position(|&length| length == 0)?;
+    let table_size = pairs.group_index[groups];
+
+    pairs.block_size = 1 << data.get(offset)?;
+    pairs.span = 1 << data.get(offset + 1)?;
+    pairs.sparse_index_size = table_size.div_ceil(pairs.span as u64) as usize;
+    let padding = *data.get(offset + 2)? as usize;
+    pairs.blocks = read_u32(data, offset + 3)? as usize;
+    pairs.block_length_size = pairs.blocks + padding;
+    pairs.max_symbol_length = *data.get(offset + 7)?;
+    pairs.min_symbol_length = *data.get(offset + 8)?;
+    offset += 9;
+    pairs.lowest_symbol = offset;
+
+    let lengths =
+        (pairs.max_symbol_length as usize + 1).checked_sub(pairs.min_symbol_length as usize)?;
+    pairs.base = vec![0; lengths];
+
+    for _i in (0..lengths.saturating_sub(1)).rev() {
+        let lowest = read_u16(data, offset + 2 * _i)? as u64;
+        let next_lowest = read_u16(data, offset + 2 * (_i + 1))? as u64;
+        pairs.base[_i] = (pairs.base[_i + 1] + lowest).wrapping_sub(next_lowest) / 2;
+    }
+
+    for _i in 0..lengths {
+        let shift = 64 - _i - pairs.min_symbol_length as usize;
+        pairs.base[_i] = pairs.base[_i].checked_shl(shift as u32).unwrap_or(0);
+    }
+
+    offset += lengths * 2;
+    let symbols = read_u16(data, offset)? as usize;
+    offset += 2;
+    pairs.tree = offset;
+
+    pairs.symbol_length = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for symbol in 0..symbols {
+        if !visited[symbol] {
+            pairs.symbol_length[symbol] = symbol_length(pairs, data, symbol, &mut visited)?;
+        }
+    }
+
+    Some(offset + symbols * 3 + (symbols & 1))
+}
+
+// How many values a symbol expands to, minus one
+fn symbol_length(
+    pairs: &mut PairsData,
+    data: &[u8],
+    symbol: usize,
+    visited: &mut Vec<bool>,
+) -> Option<u8> {
+    visited[symbol] = true;
+
+    let right = tree_right(data, pairs.tree, symbol)?;
+    if right == 0xfff {
+        return Some(0);
+    }
+
+    let left = tree_left(data, pairs.tree, symbol)?;
+
+    for child in [left, right] {
+        if !*visited.get(child)? {
+            pairs.symbol_length[child] = symbol_length(pairs, data, child, visited)?;
+        }
+    }
+
+    Some(
+        pairs.symbol_length[left]
+            .wrapping_add(pairs.symbol_length[right])
+            .wrapping_add(1),
+    )
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{encoding, material_name, parse_material, Tablebase, Wdl};
+    use crate::{Colour, Game};
+    use std::fs;
+
+    // Known sizes of the index spaces from the Syzygy generator
+    #[test]
+    fn test_encoding_tables() {
+        let encoding = encoding();
+
+        assert_eq!(encoding.binomial[2][62], 62 * 61 / 2);
+        assert_eq!(encoding.map_kk.iter().flatten().max(), Some(&461));
+        assert_eq!(encoding.map_a1d1d4[1], 0);
+        assert_eq!(encoding.map_a1d1d4[27], 9);
+        assert_eq!(encoding.map_pawns[8], 47);
+        assert_eq!(encoding.map_pawns[15], 46);
+        assert_eq!(encoding.lead_pawns_size[1], [6, 6, 6, 6]);
+    }
+
+    #[test]
+    fn test_material() {
+        let game = Game::from_fen("8/8/4k3/8/2P5/3R4/8/4K3 w - - 0 1".to_string()).unwrap();
+        assert_eq!(material_name(&game, Colour::White), "KRP");
+        assert_eq!(material_name(&game, Colour::Black), "K");
+
+        assert_eq!(parse_material("KRPvKR"), Some(("KRP", "KR")));
+        assert_eq!(parse_material("KRvR"), None);
+        assert_eq!(parse_material("KQQQQvKQQ"), None);
+    }
+
+    // KQvK tables made by tests/syzygy/generate.py
+    #[test]
+    fn test_kqvk() {
+        let tablebase =
+            Tablebase::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy")).unwrap();
+        assert_eq!(tablebase.max_pieces(), 3);
+        let position = |fen: &str| Game::from_fen(fen.to_string()).unwrap();
+
+        // White always wins with the move
+        let game = position("8/8/8/4k3/8/8/8/KQ6 w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Some(Wdl::Win));
+        let game = position("8/8/8/4k3/8/8/8/KQ6 b - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Some(Wdl::Loss));
+
+        // Mate in one
+        let game = position("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1");
+        assert_eq!(tablebase.probe_dtz(&game), Some(1));
+        assert_eq!(tablebase.best_move(&game).unwrap().to_uci(), "c1c8");
+
+        // Kb8 is forced, then Qc5 Ka8 and mate
+        let game = position("k7/8/1K6/8/8/8/8/2Q5 b - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Some(Wdl::Loss));
+        assert_eq!(tablebase.probe_dtz(&game), Some(-4));
+
+        // The same with the colours swapped
+        let game = position("2q5/8/8/8/8/1k6/8/K7 w - - 0 1");
+        assert_eq!(tablebase.probe_dtz(&game), Some(-4));
+
+        // Taking the queen draws
+        let game = position("8/8/8/8/8/2k5/1Q6/7K b - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&game), Some(Wdl::Draw));
+        assert_eq!(tablebase.probe_dtz(&game), Some(0));
+        assert_eq!(tablebase.best_move(&game).unwrap().to_uci(), "c3b2");
+    }
+
+    #[test]
+    fn test_kpvk() {
+        // A table split by the file of the pawn, with the pieces in a different order for
+        // each side to move and symbols that stand for runs of values
+        let tablebase =
+            Tablebase::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/syzygy")).unwrap();
+        let wdl = |fen: &str| tablebase.probe_wdl(&Game::from_fen(fen.to_string()).unwrap());
+
+        // With the king in front of its pawn on the sixth rank white wins whoever moves
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(Wdl::Loss));
+
+        // Black is stalemated, but with white to move the king steps aside and the pawn
+        // promotes
+        assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("8/8/8/8/8/4k3/4p3/4K3 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("8/8/8/8/8/4k3/4p3/4K3 b - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("1k6/1P6/1K6/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("3k4/3P4/3K4/8/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("6k1/6P1/6K1/8/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+
+        // A rook pawn can't win once the king is in the corner
+        assert_eq!(wdl("k7/8/K7/P7/8/8/8/8 w - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("k7/8/K7/P7/8/8/8/8 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("7k/8/8/8/8/8/7P/7K b - - 0 1"), Some(Wdl::Draw));
+
+        // The king is too far away to catch the pawn
+        assert_eq!(wdl("7k/8/8/8/8/8/P7/K7 w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(wdl("8/p7/8/8/8/8/8/k6K b - - 0 1"), Some(Wdl::Win));
+
+        // Taking the pawn draws, promoting with mate wins
+        assert_eq!(wdl("8/8/8/8/8/8/3kP3/7K b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(wdl("k7/2P5/1K6/8/8/8/8/8 w - - 0 1"), Some(Wdl::Win));
+    }
+
+    #[test]
+    fn test_missing_tables() {
+        let directory = std::env::temp_dir().join("melg-chess-syzygy-test");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("readme.txt"), "not a table").unwrap();
+
+        let tablebase = Tablebase::open(&directory).unwrap();
+        assert_eq!(tablebase.max_pieces(), 0);
+
+        // Two kings never need a table
+        let kings = Game::from_fen("8/8/4k3/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        assert_eq!(tablebase.probe_dtz(&kings), Some(0));
+
+        let game = Game::from_fen("8/8/4k3/8/8/3R4/8/4K3 w - - 0 1".to_string()).unwrap();
+        assert_eq!(tablebase.probe_wdl(&game), None);
+        assert_eq!(tablebase.best_move(&game), None);
+        assert_eq!(tablebase.probe_wdl(&Game::new()), None);
+
+        assert!(Tablebase::open(directory.join("missing")).is_err());
+    }
+}
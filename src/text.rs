@@ -0,0 +1,155 @@
+use crate::fen::piece_to_char;
+use crate::{format_square, parse_square, piece_glyph, view_to_tile, Colour, Game};
+
+// Tile colours as ANSI true colour backgrounds, the same as the terminal interface
+const LIGHT_TILE: &str = "\x1b[48;2;240;217;181m";
+const DARK_TILE: &str = "\x1b[48;2;181;136;99m";
+const HIGHLIGHT_TILE: &str = "\x1b[48;2;205;210;106m";
+const BLACK_TEXT: &str = "\x1b[30m";
+const RESET: &str = "\x1b[0m";
+
+/// Settings for drawing a board as text. Squares are written as "e4".
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextOptions {
+    /// The colour shown at the bottom of the board.
+    pub orientation: Colour,
+    /// Chess symbols such as ♞, or FEN letters such as "n" when false.
+    pub unicode: bool,
+    /// Colour the tiles with ANSI escape codes.
+    pub colours: bool,
+    /// Letters and numbers of the files and ranks along the edges of the board.
+    pub coordinates: bool,
+    /// Squares to highlight, in brackets without colours.
+    pub highlights: Vec<String>,
+}
+
+impl Default for TextOptions {
+    fn default() -> TextOptions {
+        TextOptions {
+            orientation: Colour::White,
+            unicode: true,
+            colours: false,
+            coordinates: true,
+            highlights: Vec::new(),
+        }
+    }
+}
+
+/// Draw the current position of a game as text, one line per rank.
+pub fn render_text(game: &Game, options: &TextOptions) -> String {
+    let highlights: Vec<(usize, usize)> = options
+        .highlights
+        .iter()
+        .filter_map(|square| parse_square(square))
+        .collect();
+    let mut text = String::new();
+
+    for row in 0..8 {
+        if options.coordinates {
+            text += &format!("{} ", view_to_tile(options.orientation, (0, row)).1 + 1);
+        }
+
+        for column in 0..8 {
+            let tile = view_to_tile(options.orientation, (column, row));
+            let symbol = match game.board[tile.0][tile.1] {
+                Some(piece) if options.unicode => piece_glyph(piece),
+                Some(piece) => piece_to_char(piece),
+                None if options.colours => ' ',
+                None => '.',
+            };
+            let highlighted = highlights.contains(&tile);
+
+            if options.colours {
+                let background = if highlighted {
+                    HIGHLIGHT_TILE
+                } else if (tile.0 + tile.1).is_multiple_of(2) {
+                    DARK_TILE
+                } else {
+                    LIGHT_TILE
+                };
+                text += &format!("{}{} {} ", background, BLACK_TEXT, symbol);
+            } else if highlighted {
+                text += &format!("[{}]", symbol);
+            } else {
+                text += &format!(" {} ", symbol);
+            }
+        }
+
+        if options.colours {
+            text += RESET;
+        }
+        text += "\n";
+    }
+
+    if options.coordinates {
+        text += "  ";
+        for column in 0..8 {
+            let file = view_to_tile(options.orientation, (column, 0)).0;
+            text += &format!(" {} ", format_square((file, 0)).remove(0));
+        }
+        text += "\n";
+    }
+
+    text
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{render_text, TextOptions};
+    use crate::{Colour, Game};
+
+    #[test]
+    fn test_render_text() {
+        let mut game = Game::new();
+        game.make_move("e2".to_string(), "e4".to_string());
+
+        let options = TextOptions {
+            unicode: false,
+            highlights: vec!["e2".to_string(), "e4".to_string(), "z9".to_string()],
+            ..TextOptions::default()
+        };
+        assert_eq!(
+            render_text(&game, &options),
+            "8  r  n  b  q  k  b  n  r \n\
+             7  p  p  p  p  p  p  p  p \n\
+             6  .  .  .  .  .  .  .  . \n\
+             5  .  .  .  .  .  .  .  . \n\
+             4  .  .  .  . [P] .  .  . \n\
+             3  .  .  .  .  .  .  .  . \n\
+             2  P  P  P  P [.] P  P  P \n\
+             1  R  N  B  Q  K  B  N  R \n   \
+             a  b  c  d  e  f  g  h \n"
+        );
+
+        // Black at the bottom, without coordinates
+        let options = TextOptions {
+            orientation: Colour::Black,
+            coordinates: false,
+            ..TextOptions::default()
+        };
+        let text = render_text(&game, &options);
+        assert!(text.starts_with(" ♖  ♘  ♗  ♔  ♕  ♗  ♘  ♖ \n"));
+        assert!(text.ends_with(" ♜  ♞  ♝  ♚  ♛  ♝  ♞  ♜ \n"));
+        assert_eq!(text.lines().count(), 8);
+    }
+
+    #[test]
+    fn test_render_text_colours() {
+        let options = TextOptions {
+            colours: true,
+            coordinates: false,
+            highlights: vec!["a8".to_string()],
+            ..TextOptions::default()
+        };
+        let text = render_text(&Game::new(), &options);
+        let first_line = text.lines().next().unwrap();
+
+        assert!(first_line.starts_with("\x1b[48;2;205;210;106m\x1b[30m ♜ "));
+        assert!(first_line.contains("\x1b[48;2;181;136;99m\x1b[30m ♞ "));
+        assert!(first_line.ends_with("\x1b[0m"));
+    }
+}
@@ -0,0 +1,736 @@
+use std::io::{self, stdout};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    MouseButton, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::play::{repetitions, PlayOptions};
+use crate::search::{search, SearchLimits, SearchResult, MATE_SCORE};
+use crate::xboard::game_result;
+use crate::{check_for_checked, format_square, piece_glyph, Colour, Game, Move, Piece};
+
+// Width of a tile in characters, the board is drawn with one line per rank
+const TILE_WIDTH: u16 = 3;
+
+const LIGHT_TILE: Color = Color::Rgb(240, 217, 181);
+const DARK_TILE: Color = Color::Rgb(181, 136, 99);
+const SELECTED_TILE: Color = Color::Rgb(246, 246, 105);
+const TARGET_TILE: Color = Color::Rgb(130, 151, 105);
+const LAST_MOVE_TILE: Color = Color::Rgb(205, 210, 106);
+const CHECK_TILE: Color = Color::Rgb(230, 80, 70);
+
+// How long the evaluation pane thinks about a position
+const ANALYSIS_LIMITS: SearchLimits = SearchLimits {
+    depth: Some(6),
+    time: Some(Duration::from_millis(500)),
+    nodes: None,
+};
+
+static HELP: &str = "Click or use arrows and enter to move  u: undo  f: flip  n: new game  q: quit";
+
+/// Time each player has for the whole game, with an increment added after every move.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Clock {
+    pub time: Duration,
+    pub increment: Duration,
+}
+
+// State of the full screen game
+struct App {
+    game: Game,
+    options: PlayOptions,
+    clock: Option<Clock>,
+    orientation: Colour,
+    // Standard Algebraic Notation of the moves played
+    moves: Vec<String>,
+    selected: Option<(usize, usize)>,
+    targets: Vec<(usize, usize)>,
+    cursor: (usize, usize),
+    // A pawn move to the last rank waiting for the piece to be chosen
+    promotion: Option<Move>,
+    result: Option<String>,
+    // Time left, or time used without a clock, indexed by colour
+    times: [Duration; 2],
+    // The times before each move, restored when it is taken back
+    earlier_times: Vec<[Duration; 2]>,
+    turn_start: Instant,
+    // Score in centipawns for white and the depth it was found at
+    evaluation: Option<(i32, u32)>,
+    engine_search: Option<(Colour, Receiver<SearchResult>)>,
+    analysis: Option<(Colour, Receiver<SearchResult>)>,
+    // Where the top left tile was drawn, for mapping mouse clicks to tiles
+    board_origin: (u16, u16),
+}
+
+impl App {
+    fn new(options: PlayOptions, clock: Option<Clock>) -> App {
+        let start_time = clock.map_or(Duration::default(), |clock| clock.time);
+
+        let mut app = App {
+            game: options.start_position(),
+            options,
+            clock,
+            orientation: options.orientation,
+            moves: Vec::new(),
+            selected: None,
+            targets: Vec::new(),
+            cursor: (4, 1),
+            promotion: None,
+            result: None,
+            times: [start_time; 2],
+            earlier_times: Vec::new(),
+            turn_start: Instant::now(),
+            evaluation: None,
+            engine_search: None,
+            analysis: None,
+            board_origin: (0, 0),
+        };
+
+        app.position_changed();
+        app
+    }
+
+    fn engine_to_move(&self) -> bool {
+        self.result.is_none() && self.options.engine_colour == Some(self.game.turn)
+    }
+
+    // Start the engine or the evaluation for the new position. Results of searches
+    // of earlier positions are dropped.
+    fn position_changed(&mut self) {
+        self.selected = None;
+        self.targets.clear();
+        self.promotion = None;
+        self.engine_search = None;
+        self.analysis = None;
+
+        if self.engine_to_move() {
+            let receiver = start_search(&self.game, self.options.limits);
+            self.engine_search = Some((self.game.turn, receiver));
+        } else if self.result.is_none() {
+            let receiver = start_search(&self.game, ANALYSIS_LIMITS);
+            self.analysis = Some((self.game.turn, receiver));
+        }
+    }
+
+    // Time of a side including the running time of the side to move
+    fn time(&self, colour: Colour) -> Duration {
+        let time = self.times[colour as usize];
+        if colour != self.game.turn || self.result.is_some() {
+            return time;
+        }
+
+        let elapsed = self.turn_start.elapsed();
+        match self.clock {
+            Some(_) => time.saturating_sub(elapsed),
+            None => time + elapsed,
+        }
+    }
+
+    fn play(&mut self, _move: Move) {
+        let mover = self.game.turn;
+
+        let time = self.time(mover);
+        self.earlier_times.push(self.times);
+        self.times[mover as usize] = time + self.clock.map_or(Duration::default(), |c| c.increment);
+        self.turn_start = Instant::now();
+
+        self.moves.push(self.game.format_san(_move));
+        self.game.play_move(_move);
+        self.result = game_result(&mut self.game).or_else(|| {
+            if repetitions(&self.game) >= 2 {
+                Some("1/2-1/2 {Threefold repetition}".to_string())
+            } else {
+                None
+            }
+        });
+
+        self.position_changed();
+    }
+
+    // Against the engine both its reply and the player's move are taken back
+    fn undo(&mut self) {
+        while self.game.undo_move().is_some() {
+            self.moves.pop();
+            if let Some(times) = self.earlier_times.pop() {
+                self.times = times;
+            }
+            if self.options.engine_colour != Some(self.game.turn) {
+                break;
+            }
+        }
+
+        self.result = None;
+        self.turn_start = Instant::now();
+        self.position_changed();
+    }
+
+    // Check on the searches running in the background and the clocks
+    fn update(&mut self) {
+        if let Some((colour, receiver)) = &self.engine_search {
+            if let Ok(result) = receiver.try_recv() {
+                self.evaluation = Some((white_score(result.score, *colour), result.depth));
+                match result.best_move {
+                    Some(_move) => self.play(_move),
+                    None => self.engine_search = None,
+                }
+            }
+        }
+
+        if let Some((colour, receiver)) = &self.analysis {
+            if let Ok(result) = receiver.try_recv() {
+                self.evaluation = Some((white_score(result.score, *colour), result.depth));
+                self.analysis = None;
+            }
+        }
+
+        let turn = self.game.turn;
+        if self.clock.is_some() && self.result.is_none() && self.time(turn).is_zero() {
+            self.times[turn as usize] = Duration::default();
+            self.result = Some(match turn {
+                Colour::White => "0-1 {White loses on time}".to_string(),
+                Colour::Black => "1-0 {Black loses on time}".to_string(),
+            });
+            self.position_changed();
+        }
+    }
+
+    // Select a piece of the player to move, or move the selected piece to the tile
+    fn select(&mut self, tile: (usize, usize)) {
+        self.cursor = tile;
+
+        if self.result.is_some() || self.engine_to_move() || self.promotion.is_some() {
+            return;
+        }
+
+        if let Some(from) = self.selected {
+            if self.targets.contains(&tile) {
+                let moves: Vec<Move> = self
+                    .game
+                    .get_legal_moves()
+                    .into_iter()
+                    .filter(|_move| _move.from == from && _move.to == tile)
+                    .collect();
+
+                match moves.as_slice() {
+                    [_move] => self.play(*_move),
+                    [_move, ..] => {
+                        self.promotion = Some(Move {
+                            promotion: None,
+                            ..*_move
+                        })
+                    }
+                    [] => {}
+                }
+                return;
+            }
+        }
+
+        // The legal moves of the piece are highlighted, the same moves a click plays
+        match self.game.get_legal_targets(tile) {
+            Some(targets) if self.selected != Some(tile) => {
+                self.selected = Some(tile);
+                self.targets = targets;
+            }
+            _ => {
+                self.selected = None;
+                self.targets.clear();
+            }
+        }
+    }
+
+    // The tile at a position on the screen
+    fn tile_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        let (x, y) = self.board_origin;
+        if column < x || row < y || column >= x + 8 * TILE_WIDTH || row >= y + 8 {
+            return None;
+        }
+
+        Some(self.view_to_tile((((column - x) / TILE_WIDTH) as usize, (row - y) as usize)))
+    }
+
+    // Column and row of the board as shown, to the tile there
+    fn view_to_tile(&self, (column, row): (usize, usize)) -> (usize, usize) {
+        match self.orientation {
+            Colour::White => (column, 7 - row),
+            Colour::Black => (7 - column, row),
+        }
+    }
+
+    // Returns false when the user wants to quit
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if let Some(_move) = self.promotion {
+            let piece = match key.code {
+                KeyCode::Char('q') => Some(Piece::Queen),
+                KeyCode::Char('r') => Some(Piece::Rook),
+                KeyCode::Char('b') => Some(Piece::Bishop),
+                KeyCode::Char('n') => Some(Piece::Knight),
+                KeyCode::Char('k') if self.game.variant.promotions().contains(&Piece::King) => {
+                    Some(Piece::King)
+                }
+                _ => None,
+            };
+
+            self.promotion = None;
+            if let Some(piece) = piece {
+                self.play(Move {
+                    promotion: Some(piece),
+                    .._move
+                });
+            }
+            return true;
+        }
+
+        // Arrow keys move on the board as it is shown
+        let (file, rank) = self.cursor;
+        let (right, up) = match self.orientation {
+            Colour::White => (1, 1),
+            Colour::Black => (-1, -1),
+        };
+        let step = |value: usize, delta: i32| (value as i32 + delta).clamp(0, 7) as usize;
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('f') => self.orientation = self.orientation.opponent(),
+            KeyCode::Char('n') => *self = App::new(self.options, self.clock),
+            KeyCode::Left => self.cursor = (step(file, -right), rank),
+            KeyCode::Right => self.cursor = (step(file, right), rank),
+            KeyCode::Up => self.cursor = (file, step(rank, up)),
+            KeyCode::Down => self.cursor = (file, step(rank, -up)),
+            KeyCode::Enter | KeyCode::Char(' ') => self.select(self.cursor),
+            _ => {}
+        }
+
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main_area, status_area] =
+            Layout::vertical([Constraint::Min(12), Constraint::Length(1)]).areas(frame.area());
+        let [board_area, side_area] =
+            Layout::horizontal([Constraint::Length(8 * TILE_WIDTH + 5), Constraint::Min(24)])
+                .areas(main_area);
+        let [clock_area, evaluation_area, captured_area, moves_area] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(3),
+            Constraint::Length(4),
+            Constraint::Min(3),
+        ])
+        .areas(side_area);
+
+        self.draw_board(frame, board_area);
+
+        let clocks: Vec<Line> = [Colour::White, Colour::Black]
+            .iter()
+            .map(|&colour| {
+                // A clock rounds up so that it shows 00:00 only when the time is out
+                let time = match self.clock {
+                    Some(_) => self.time(colour) + Duration::from_millis(999),
+                    None => self.time(colour),
+                };
+                let text = format!("{:?}: {}", colour, format_time(time));
+                if colour == self.game.turn && self.result.is_none() {
+                    Line::styled(text, Style::new().add_modifier(Modifier::BOLD))
+                } else {
+                    Line::raw(text)
+                }
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(clocks).block(Block::bordered().title(" Clocks ")),
+            clock_area,
+        );
+
+        let evaluation = match self.evaluation {
+            Some((score, depth)) => format!("{} (depth {})", format_score(score), depth),
+            None => "...".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(evaluation).block(Block::bordered().title(" Evaluation ")),
+            evaluation_area,
+        );
+
+        let captured: Vec<Line> = [Colour::White, Colour::Black]
+            .iter()
+            .map(|&colour| {
+                let pieces: String = captured_pieces(&self.game, colour.opponent())
+                    .into_iter()
+                    .map(|piece| piece_glyph((piece, colour.opponent())))
+                    .collect();
+                let advantage =
+                    material(&self.game, colour) - material(&self.game, colour.opponent());
+                if advantage > 0 {
+                    Line::raw(format!("{:?}: {} +{}", colour, pieces, advantage))
+                } else {
+                    Line::raw(format!("{:?}: {}", colour, pieces))
+                }
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(captured).block(Block::bordered().title(" Captured ")),
+            captured_area,
+        );
+
+        // Numbered moves, scrolled so that the latest ones are shown
+        let mut lines: Vec<Line> = self
+            .moves
+            .chunks(2)
+            .enumerate()
+            .map(|(_i, pair)| {
+                Line::raw(format!(
+                    "{:>3}. {:<8} {}",
+                    _i + 1,
+                    pair[0],
+                    pair.get(1).map_or("", |san| san)
+                ))
+            })
+            .collect();
+        if let Some(result) = &self.result {
+            lines.push(Line::styled(
+                result.clone(),
+                Style::new().add_modifier(Modifier::BOLD),
+            ));
+        }
+        let scroll = (lines.len() as u16).saturating_sub(moves_area.height.saturating_sub(2));
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(Block::bordered().title(" Moves "))
+                .scroll((scroll, 0)),
+            moves_area,
+        );
+
+        let status = if self.promotion.is_some() {
+            if self.game.variant.promotions().contains(&Piece::King) {
+                "Promote to (q)ueen, (r)ook, (b)ishop, k(n)ight or (k)ing".to_string()
+            } else {
+                "Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight".to_string()
+            }
+        } else if let Some(result) = &self.result {
+            format!("Game over: {}. n: new game  u: undo  q: quit", result)
+        } else if self.engine_to_move() {
+            "The engine is thinking...".to_string()
+        } else {
+            HELP.to_string()
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn draw_board(&mut self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(" melg-chess ");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        // Rank numbers take the first two columns
+        self.board_origin = (inner.x + 2, inner.y);
+
+        let last_move = self
+            .game
+            .history
+            .last()
+            .map(|snapshot| snapshot.played_move);
+        let checked_king = if check_for_checked(self.game.turn, &self.game) {
+            Some((Piece::King, self.game.turn))
+        } else {
+            None
+        };
+
+        let mut lines: Vec<Line> = Vec::new();
+        for row in 0..8 {
+            let rank = self.view_to_tile((0, row)).1;
+            let mut spans = vec![Span::raw(format!("{} ", rank + 1))];
+
+            for column in 0..8 {
+                let tile = self.view_to_tile((column, row));
+                let piece = self.game.board[tile.0][tile.1];
+
+                let background = if self.selected == Some(tile) {
+                    SELECTED_TILE
+                } else if self.targets.contains(&tile) {
+                    TARGET_TILE
+                } else if piece.is_some() && piece == checked_king {
+                    CHECK_TILE
+                } else if last_move.is_some_and(|_move| _move.from == tile || _move.to == tile) {
+                    LAST_MOVE_TILE
+                } else if (tile.0 + tile.1).is_multiple_of(2) {
+                    DARK_TILE
+                } else {
+                    LIGHT_TILE
+                };
+
+                let mut style = Style::new().fg(Color::Black).bg(background);
+                if self.cursor == tile {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+
+                let text = match piece {
+                    Some(piece) => format!(" {} ", piece_glyph(piece)),
+                    None => "   ".to_string(),
+                };
+                spans.push(Span::styled(text, style));
+            }
+
+            lines.push(Line::from(spans));
+        }
+
+        let files: String = (0..8)
+            .map(|column| {
+                let file = self.view_to_tile((column, 0)).0;
+                format!(" {} ", format_square((file, 0)).remove(0))
+            })
+            .collect();
+        lines.push(Line::raw(format!("  {}", files)));
+
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+}
+
+/// Play in a full screen terminal interface with the mouse or the keyboard. The clock
+/// is optional, without it the time used by each player is shown.
+pub fn run(options: PlayOptions, clock: Option<Clock>) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    execute!(stdout(), EnableMouseCapture)?;
+
+    let mut app = App::new(options, clock);
+    let result = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| app.draw(frame))?;
+
+            if event::poll(Duration::from_millis(100))? {
+                let running = match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                    Event::Mouse(mouse)
+                        if mouse.kind == MouseEventKind::Down(MouseButton::Left) =>
+                    {
+                        if let Some(tile) = app.tile_at(mouse.column, mouse.row) {
+                            app.select(tile);
+                        }
+                        true
+                    }
+                    _ => true,
+                };
+
+                if !running {
+                    return Ok(());
+                }
+            }
+
+            app.update();
+        }
+    })();
+
+    execute!(stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    result
+}
+
+fn start_search(game: &Game, limits: SearchLimits) -> Receiver<SearchResult> {
+    let (sender, receiver) = mpsc::channel();
+    let game = game.clone();
+
+    // Nobody is listening anymore if the position changed before the search finished
+    thread::spawn(move || {
+        let _ = sender.send(search(&game, limits));
+    });
+
+    receiver
+}
+
+fn white_score(score: i32, turn: Colour) -> i32 {
+    match turn {
+        Colour::White => score,
+        Colour::Black => -score,
+    }
+}
+
+// Pawns with two decimals, or moves until mate such as "#3" and "#-2"
+fn format_score(score: i32) -> String {
+    if score.abs() >= MATE_SCORE - 1000 {
+        let moves = (MATE_SCORE - score.abs() + 1) / 2;
+        if score > 0 {
+            format!("#{}", moves)
+        } else {
+            format!("#-{}", moves)
+        }
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// Pieces of a colour missing from the board compared to the start, most valuable first
+fn captured_pieces(game: &Game, colour: Colour) -> Vec<Piece> {
+    let mut captured = Vec::new();
+
+    for (piece, count) in [
+        (Piece::Queen, 1),
+        (Piece::Rook, 2),
+        (Piece::Bishop, 2),
+        (Piece::Knight, 2),
+        (Piece::Pawn, 8),
+    ] {
+        let on_board = game
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&tile| tile == Some((piece, colour)))
+            .count();
+
+        for _ in on_board..count {
+            captured.push(piece);
+        }
+    }
+
+    captured
+}
+
+// Material of a colour counted in pawns
+fn material(game: &Game, colour: Colour) -> i32 {
+    game.board
+        .iter()
+        .flatten()
+        .filter_map(|&tile| tile.filter(|piece| piece.1 == colour))
+        .map(|piece| match piece.0 {
+            Piece::Queen => 9,
+            Piece::Rook => 5,
+            Piece::Bishop | Piece::Knight => 3,
+            Piece::Pawn => 1,
+            Piece::King => 0,
+        })
+        .sum()
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{captured_pieces, format_score, App, Clock};
+    use crate::play::PlayOptions;
+    use crate::{Colour, Game, Piece};
+    use ratatui::backend::TestBackend;
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+    use ratatui::Terminal;
+    use std::time::Duration;
+
+    fn draw(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                text.push_str(buffer[(x, y)].symbol());
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn test_click_to_move() {
+        let clock = Clock {
+            time: Duration::from_secs(300),
+            increment: Duration::from_secs(2),
+        };
+        let mut app = App::new(PlayOptions::default(), Some(clock));
+        let screen = draw(&mut app);
+        assert!(screen.contains("White: 05:00"));
+        assert!(screen.contains("♜"));
+
+        // The e2 pawn is on the fifth column and seventh row of the board
+        let (x, y) = app.board_origin;
+        let e2 = app.tile_at(x + 4 * 3 + 1, y + 6).unwrap();
+        assert_eq!(e2, (4, 1));
+
+        app.select(e2);
+        assert_eq!(app.targets, vec![(4, 2), (4, 3)]);
+        app.select((4, 3));
+        assert_eq!(app.moves, vec!["e4"]);
+        assert!(app.times[0] >= Duration::from_secs(301));
+        assert!(draw(&mut app).contains("1. e4"));
+
+        // Flipped, the same tile is at the mirrored place
+        app.handle_key(KeyEvent::from(KeyCode::Char('f')));
+        assert_eq!(app.tile_at(x + 3 * 3, y + 1), Some((4, 1)));
+
+        // Taking the move back also gives back the time it took and the increment
+        app.handle_key(KeyEvent::from(KeyCode::Char('u')));
+        assert!(app.moves.is_empty());
+        assert_eq!(app.times, [Duration::from_secs(300); 2]);
+        assert!(!app.handle_key(KeyEvent::from(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn test_promotion_choice() {
+        let mut app = App::new(PlayOptions::default(), None);
+        app.game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+
+        app.select((1, 6));
+        app.select((1, 7));
+        assert!(app.promotion.is_some());
+        assert!(draw(&mut app).contains("Promote to"));
+
+        app.handle_key(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(app.moves, vec!["b8=N"]);
+        assert_eq!(app.game.board[1][7], Some((Piece::Knight, Colour::White)));
+    }
+
+    #[test]
+    fn test_highlights_are_legal_moves() {
+        // The pinned knight could check the black king, but that isn't a legal move
+        let mut app = App::new(PlayOptions::default(), None);
+        app.game = Game::from_fen("4r3/8/8/3k4/8/8/4N3/4K3 w - - 0 1".to_string()).unwrap();
+
+        app.select((4, 1));
+        assert_eq!(app.selected, Some((4, 1)));
+        assert!(app.targets.is_empty());
+        app.select((4, 0));
+        assert_eq!(app.targets, vec![(3, 0), (3, 1), (5, 1), (5, 0)]);
+        app.select((5, 1));
+        assert_eq!(app.moves, vec!["Kf2"]);
+    }
+
+    #[test]
+    fn test_captured_and_score() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/PPPPPPP1/RN2K2R w - - 0 1".to_string()).unwrap();
+        assert_eq!(
+            captured_pieces(&game, Colour::White),
+            vec![
+                Piece::Queen,
+                Piece::Bishop,
+                Piece::Bishop,
+                Piece::Knight,
+                Piece::Pawn
+            ]
+        );
+
+        assert_eq!(format_score(35), "+0.35");
+        assert_eq!(format_score(-120), "-1.20");
+        assert_eq!(format_score(crate::search::MATE_SCORE - 5), "#3");
+    }
+}
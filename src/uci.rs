@@ -0,0 +1,154 @@
+use crate::{format_square, parse_square, Game, GameState, Move, Piece};
+
+impl Move {
+    /// Parse a move in UCI notation such as "e2e4", "e7e8q" or "e1g1" for castling, or a drop
+    /// such as "N@f3". Returns None if it can't be parsed, it isn't checked to be legal.
+    pub fn from_uci(text: &str) -> Option<Move> {
+        if !text.is_ascii() || text.len() < 4 || text.len() > 5 {
+            return None;
+        }
+
+        if text.len() == 4 && &text[1..2] == "@" {
+            let piece = match &text[0..1] {
+                "Q" => Piece::Queen,
+                "R" => Piece::Rook,
+                "B" => Piece::Bishop,
+                "N" => Piece::Knight,
+                "P" => Piece::Pawn,
+                _ => return None,
+            };
+            let to = parse_square(&text[2..4])?;
+
+            return Some(Move {
+                from: to,
+                to,
+                promotion: None,
+                drop: Some(piece),
+            });
+        }
+
+        let promotion = match text.get(4..) {
+            Some("q") => Some(Piece::Queen),
+            Some("r") => Some(Piece::Rook),
+            Some("b") => Some(Piece::Bishop),
+            Some("n") => Some(Piece::Knight),
+            Some("k") => Some(Piece::King),
+            Some("") => None,
+            _ => return None,
+        };
+
+        Some(Move {
+            from: parse_square(&text[0..2])?,
+            to: parse_square(&text[2..4])?,
+            promotion,
+            drop: None,
+        })
+    }
+
+    /// Write the move in UCI notation, such as "e2e4", "e7e8q" or "N@f3".
+    pub fn to_uci(&self) -> String {
+        if let Some(piece) = self.drop {
+            let letter = match piece {
+                Piece::Queen => "Q",
+                Piece::Rook => "R",
+                Piece::Bishop => "B",
+                Piece::Knight => "N",
+                _ => "P",
+            };
+            return format!("{}@{}", letter, format_square(self.to));
+        }
+
+        let promotion = match self.promotion {
+            Some(Piece::Queen) => "q",
+            Some(Piece::Rook) => "r",
+            Some(Piece::Bishop) => "b",
+            Some(Piece::Knight) => "n",
+            Some(Piece::King) => "k",
+            _ => "",
+        };
+
+        format!(
+            "{}{}{}",
+            format_square(self.from),
+            format_square(self.to),
+            promotion
+        )
+    }
+}
+
+impl Game {
+    /// If the move written in UCI notation such as "e2e4", "e7e8q" or "e1g1" is legal, play it
+    /// and return the resulting state of the game. A promotion needs the piece to be given.
+    pub fn make_uci_move(&mut self, _move: String) -> Option<GameState> {
+        if self.state == GameState::Checkmate || self.state == GameState::GameOver {
+            return None;
+        }
+
+        let _move = Move::from_uci(&_move)?;
+        if !self.get_legal_moves().contains(&_move) {
+            return None;
+        }
+
+        Some(self.play_move(_move))
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::variant::Variant;
+    use crate::{Game, GameState, Move, Piece};
+
+    #[test]
+    fn test_uci_notation() {
+        let promotion = Move {
+            from: (4, 6),
+            to: (4, 7),
+            promotion: Some(Piece::Queen),
+            drop: None,
+        };
+        assert_eq!(Move::from_uci("e7e8q"), Some(promotion));
+        assert_eq!(promotion.to_uci(), "e7e8q");
+
+        let drop = Move::from_uci("N@f3").unwrap();
+        assert_eq!(drop.drop, Some(Piece::Knight));
+        assert_eq!(drop.to_uci(), "N@f3");
+
+        for text in ["", "e2", "e2e9", "e7e8x", "e2e4e5", "K@e4", "é2e4"] {
+            assert_eq!(Move::from_uci(text), None);
+        }
+    }
+
+    #[test]
+    fn test_make_uci_move() {
+        let mut game = Game::from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1".to_string()).unwrap();
+        assert_eq!(game.make_uci_move("e1e3".to_string()), None);
+        assert_eq!(game.make_uci_move("b7b8".to_string()), None);
+        assert_eq!(game.make_uci_move("h1h2q".to_string()), None);
+        assert_eq!(
+            game.make_uci_move("e1g1".to_string()),
+            Some(GameState::InProgress)
+        );
+        assert_eq!(game.get_fen(), "4k3/1P6/8/8/8/8/8/5RK1 b - - 1 1");
+
+        game.make_uci_move("e8d7".to_string());
+        assert_eq!(
+            game.make_uci_move("b7b8n".to_string()),
+            Some(GameState::Check)
+        );
+        assert_eq!(game.get_fen(), "1N6/3k4/8/8/8/8/8/5RK1 b - - 0 2");
+
+        let mut game = Game::from_variant_fen(
+            Variant::Crazyhouse,
+            "4k3/8/8/8/8/8/8/4K3[N] w - - 0 1".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            game.make_uci_move("N@d6".to_string()),
+            Some(GameState::Check)
+        );
+    }
+}
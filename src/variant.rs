@@ -0,0 +1,426 @@
+use crate::{check_for_checked, Colour, Game, Move, Piece};
+
+/// The rules a game is played by. Each variant decides the starting position, when the
+/// game ends, which moves are legal and what happens after a move.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Captured pieces change sides and can be dropped back on the board instead of moving.
+    Crazyhouse,
+    /// Giving check for the third time wins.
+    ThreeCheck,
+    /// Moving the king to one of the four centre tiles wins.
+    KingOfTheHill,
+    /// Captures explode, removing all pieces around them apart from pawns.
+    Atomic,
+    /// Captures are compulsory, the king can be taken and losing all pieces or being
+    /// stalemated wins.
+    Antichess,
+    /// White has 36 pawns and no king, and wins by mating while black wins by taking them all.
+    Horde,
+    /// Both sides race their king to the last row without giving check.
+    RacingKings,
+}
+
+/// How a game ended.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The colour won, with what it did such as "mates".
+    Win(Colour, &'static str),
+    /// The game is drawn, with the reason such as "Stalemate".
+    Draw(&'static str),
+}
+
+impl Variant {
+    /// All variants, for listing them to players.
+    pub const ALL: [Variant; 8] = [
+        Variant::Standard,
+        Variant::Crazyhouse,
+        Variant::ThreeCheck,
+        Variant::KingOfTheHill,
+        Variant::Atomic,
+        Variant::Antichess,
+        Variant::Horde,
+        Variant::RacingKings,
+    ];
+
+    /// The name used in PGN Variant tags.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::Crazyhouse => "Crazyhouse",
+            Variant::ThreeCheck => "Three-check",
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::Atomic => "Atomic",
+            Variant::Antichess => "Antichess",
+            Variant::Horde => "Horde",
+            Variant::RacingKings => "Racing Kings",
+        }
+    }
+
+    /// Find a variant by its name, ignoring case, spaces and dashes.
+    pub fn from_name(name: &str) -> Option<Variant> {
+        let simplify = |name: &str| name.to_lowercase().replace([' ', '-', '_'], "");
+        let name = simplify(name);
+
+        Variant::ALL.iter().copied().find(|variant| {
+            simplify(variant.name()) == name || variant.aliases().contains(&name.as_str())
+        })
+    }
+
+    // The name XBoard uses for the variant
+    pub(crate) fn xboard_name(&self) -> &'static str {
+        self.aliases()[0]
+    }
+
+    // Other names that programs use for the variant, the first one being XBoard's
+    fn aliases(&self) -> &'static [&'static str] {
+        match self {
+            Variant::Standard => &["normal", "chess"],
+            Variant::Crazyhouse => &["crazyhouse", "zh"],
+            Variant::ThreeCheck => &["3check", "threecheck"],
+            Variant::KingOfTheHill => &["kingofthehill", "koth"],
+            Variant::Atomic => &["atomic"],
+            Variant::Antichess => &["giveaway", "antichess", "suicide", "losingchess"],
+            Variant::Horde => &["horde"],
+            Variant::RacingKings => &["racingkings", "racing"],
+        }
+    }
+
+    // If captured pieces are kept in pockets, which FEN then lists after the board
+    pub(crate) fn has_pockets(&self) -> bool {
+        *self == Variant::Crazyhouse
+    }
+
+    // If the colour's king has to be kept out of check, and so must be on the board
+    pub(crate) fn is_royal(&self, colour: Colour) -> bool {
+        match self {
+            Variant::Antichess => false,
+            Variant::Horde => colour == Colour::Black,
+            _ => true,
+        }
+    }
+
+    // If a pawn can stand on the row. Pawns are promoted on the last row and never start on
+    // the first, apart from white's in Horde.
+    pub(crate) fn allows_pawn_on(&self, colour: Colour, row: usize) -> bool {
+        match colour {
+            Colour::White => row != 7 && (row != 0 || *self == Variant::Horde),
+            Colour::Black => row != 0 && row != 7,
+        }
+    }
+
+    // The pieces a pawn on the last row can become
+    pub(crate) fn promotions(&self) -> &'static [Piece] {
+        match self {
+            Variant::Antichess => &[
+                Piece::Queen,
+                Piece::Rook,
+                Piece::Bishop,
+                Piece::Knight,
+                Piece::King,
+            ],
+            _ => &[Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight],
+        }
+    }
+
+    // Place the pieces and castling rights of the starting position on an empty board
+    pub(crate) fn set_up(&self, game: &mut Game) {
+        match self {
+            Variant::Standard
+            | Variant::Crazyhouse
+            | Variant::ThreeCheck
+            | Variant::KingOfTheHill
+            | Variant::Atomic => {
+                game.set_default_pieces(Colour::White, 0, 1);
+                game.set_default_pieces(Colour::Black, 7, 6);
+                game.castling_rooks = [[Some(7), Some(0)], [Some(7), Some(0)]];
+            }
+            Variant::Antichess => {
+                game.set_default_pieces(Colour::White, 0, 1);
+                game.set_default_pieces(Colour::Black, 7, 6);
+            }
+            Variant::Horde => {
+                game.set_default_pieces(Colour::Black, 7, 6);
+                game.castling_rooks = [[None, None], [Some(7), Some(0)]];
+
+                for _x in 0..8 {
+                    for _y in 0..4 {
+                        game.board[_x][_y] = Some((Piece::Pawn, Colour::White));
+                    }
+                }
+                for &_x in [1, 2, 5, 6].iter() {
+                    game.board[_x][4] = Some((Piece::Pawn, Colour::White));
+                }
+            }
+            Variant::RacingKings => {
+                // Black's pieces fill the left half of the first two rows, white's mirror them
+                for (_y, row) in RACING_ROWS.iter().enumerate() {
+                    for (_x, &piece) in row.iter().enumerate() {
+                        game.board[_x][_y] = Some((piece, Colour::Black));
+                        game.board[7 - _x][_y] = Some((piece, Colour::White));
+                    }
+                }
+            }
+        }
+    }
+
+    // Check if a move that the pieces can make is legal, which normally means that it doesn't
+    // leave the moving colour's own king in check, unless counter_check allows it to check both
+    pub(crate) fn allows_move(
+        &self,
+        game: &mut Game,
+        from: (usize, usize),
+        to: (usize, usize),
+        counter_check: bool,
+    ) -> bool {
+        match self {
+            Variant::Standard
+            | Variant::Crazyhouse
+            | Variant::ThreeCheck
+            | Variant::KingOfTheHill
+            | Variant::Horde => !game.is_self_check(from, to, counter_check),
+            Variant::Atomic => game.is_legal_atomic(from, to),
+            Variant::Antichess => game.is_legal_antichess(from, to),
+            Variant::RacingKings => game.is_legal_racing(from, to),
+        }
+    }
+
+    // Check if the king on a tile can be in check at all
+    pub(crate) fn checks_apply(&self, game: &Game, king: (usize, usize)) -> bool {
+        match self {
+            Variant::Atomic => !game.kings_touch(king),
+            Variant::Antichess => false,
+            _ => true,
+        }
+    }
+
+    // Update the board or state that only the variant keeps after a move has been made, before
+    // the turn changes. It's called again without a capture once a waiting pawn is promoted.
+    pub(crate) fn after_move(
+        &self,
+        game: &mut Game,
+        played_move: Move,
+        captured: Option<(Piece, Colour)>,
+    ) {
+        match self {
+            Variant::Standard
+            | Variant::KingOfTheHill
+            | Variant::Antichess
+            | Variant::Horde
+            | Variant::RacingKings => {}
+            Variant::Crazyhouse => game.update_pockets(played_move, captured),
+            Variant::ThreeCheck => {
+                // Wait for a pawn on the last row to be promoted before looking for check
+                let to = played_move.to;
+                let waiting = game.board[to.0][to.1] == Some((Piece::Pawn, game.turn))
+                    && (to.1 == 0 || to.1 == 7);
+
+                if !waiting && check_for_checked(game.turn.opponent(), game) {
+                    game.checks[game.turn as usize] += 1;
+                }
+            }
+            Variant::Atomic => {
+                if captured.is_some() {
+                    game.explode(played_move.to);
+                }
+            }
+        }
+    }
+
+    // Find out if the game has ended by a rule of the variant before the colour about to move
+    // has made a move, the search calls this in every position so it should be quick
+    pub(crate) fn outcome(&self, game: &mut Game) -> Option<Outcome> {
+        let colours = [game.turn, game.turn.opponent()];
+
+        match self {
+            Variant::Standard | Variant::Crazyhouse => None,
+            Variant::ThreeCheck => colours
+                .iter()
+                .copied()
+                .find(|&colour| game.checks[colour as usize] >= 3)
+                .map(|colour| Outcome::Win(colour, "gives three checks")),
+            Variant::KingOfTheHill => colours
+                .iter()
+                .copied()
+                .find(|&colour| {
+                    HILL.iter()
+                        .any(|&(_x, _y)| game.board[_x][_y] == Some((Piece::King, colour)))
+                })
+                .map(|colour| Outcome::Win(colour, "reaches the centre")),
+            Variant::Atomic => colours
+                .iter()
+                .copied()
+                .find(|&colour| !game.has_king(colour))
+                .map(|colour| Outcome::Win(colour.opponent(), "explodes the king")),
+            Variant::Antichess => colours
+                .iter()
+                .copied()
+                .find(|&colour| !game.has_pieces(colour))
+                .map(|colour| Outcome::Win(colour, "loses all pieces")),
+            Variant::Horde => {
+                if game.has_pieces(Colour::White) {
+                    None
+                } else {
+                    Some(Outcome::Win(Colour::Black, "destroys the horde"))
+                }
+            }
+            Variant::RacingKings => {
+                let white = game.king_on_last_row(Colour::White);
+                let black = game.king_on_last_row(Colour::Black);
+
+                // Black moves second, so it gets one move to draw after white gets there
+                match (white, black) {
+                    (true, true) => Some(Outcome::Draw("Both kings reach the eighth rank")),
+                    (false, true) => Some(Outcome::Win(Colour::Black, "reaches the eighth rank")),
+                    (true, false)
+                        if game.turn == Colour::White
+                            || !game.can_reach_last_row(Colour::Black) =>
+                    {
+                        Some(Outcome::Win(Colour::White, "reaches the eighth rank"))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    // How the game ends when the colour about to move has no legal moves
+    pub(crate) fn no_moves_outcome(&self, game: &mut Game) -> Outcome {
+        match self {
+            Variant::Standard
+            | Variant::Crazyhouse
+            | Variant::ThreeCheck
+            | Variant::KingOfTheHill
+            | Variant::Atomic
+            | Variant::Horde
+            | Variant::RacingKings => checkmate_or_stalemate(game),
+            Variant::Antichess => Outcome::Win(game.turn, "is stalemated"),
+        }
+    }
+}
+
+// The centre tiles that win King of the Hill
+const HILL: [(usize, usize); 4] = [(3, 3), (4, 3), (3, 4), (4, 4)];
+
+// The first two rows of Racing Kings from a to d, white has the same pieces from h to e
+const RACING_ROWS: [[Piece; 4]; 2] = [
+    [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight],
+    [Piece::King, Piece::Rook, Piece::Bishop, Piece::Knight],
+];
+
+// The normal way a game ends without legal moves
+fn checkmate_or_stalemate(game: &mut Game) -> Outcome {
+    if check_for_checked(game.turn, game) {
+        Outcome::Win(game.turn.opponent(), "mates")
+    } else {
+        Outcome::Draw("Stalemate")
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, Variant};
+    use crate::{Colour, Game, GameState};
+
+    #[test]
+    fn test_variant_names() {
+        assert_eq!(Variant::from_name("standard"), Some(Variant::Standard));
+        assert_eq!(Variant::from_name("normal"), Some(Variant::Standard));
+        assert_eq!(Variant::from_name("Crazy Chess"), None);
+
+        for &variant in Variant::ALL.iter() {
+            assert_eq!(Variant::from_name(variant.name()), Some(variant));
+        }
+    }
+
+    #[test]
+    fn test_standard_outcome() {
+        let mut game = Game::new_variant(Variant::Standard);
+        assert_eq!(game.get_variant(), Variant::Standard);
+        assert_eq!(game.get_outcome(), None);
+
+        for (from, to) in [("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")] {
+            game.make_move(from.to_string(), to.to_string());
+        }
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::Black, "mates"))
+        );
+
+        let mut game = Game::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1".to_string()).unwrap();
+        assert_eq!(game.get_outcome(), Some(Outcome::Draw("Stalemate")));
+    }
+
+    #[test]
+    fn test_three_check() {
+        let fen = "4k3/8/8/8/8/8/8/4K2R w K - 1+3 0 1";
+        let mut game = Game::from_variant_fen(Variant::ThreeCheck, fen.to_string()).unwrap();
+        assert_eq!(game.get_fen(), fen);
+
+        // Checks given can also be written after the clocks
+        let given = "4k3/8/8/8/8/8/8/4K2R w K - 0 1 +2+0";
+        let given = Game::from_variant_fen(Variant::ThreeCheck, given.to_string()).unwrap();
+        assert_eq!(given.get_fen(), fen);
+
+        assert_eq!(
+            game.make_move("h1".to_string(), "h8".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "gives three checks"))
+        );
+        assert_eq!(game.get_fen(), "4k2R/8/8/8/8/8/8/4K3 b - - 0+3 1 1");
+
+        game.undo_move();
+        assert_eq!(game.get_outcome(), None);
+    }
+
+    #[test]
+    fn test_king_of_the_hill() {
+        let fen = "8/8/8/8/8/4K3/8/k7 w - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::KingOfTheHill, fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("e3".to_string(), "e4".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::White, "reaches the centre"))
+        );
+        assert_eq!(game.make_move("a1".to_string(), "a2".to_string()), None);
+    }
+
+    #[test]
+    fn test_horde() {
+        assert_eq!(
+            Game::new_variant(Variant::Horde).get_fen(),
+            "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1"
+        );
+
+        // Pawns on the first row can step twice, but not be taken en passant
+        let fen = "4k3/8/8/8/8/8/8/P7 w - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Horde, fen.to_string()).unwrap();
+        game.make_move("a1".to_string(), "a3".to_string());
+        assert_eq!(game.get_fen(), "4k3/8/8/8/8/P7/8/8 b - - 0 1");
+
+        // Taking the last white piece wins for black
+        let fen = "k7/8/8/8/8/8/r7/P7 b - - 0 1";
+        let mut game = Game::from_variant_fen(Variant::Horde, fen.to_string()).unwrap();
+        assert_eq!(
+            game.make_move("a2".to_string(), "a1".to_string()),
+            Some(GameState::GameOver)
+        );
+        assert_eq!(
+            game.get_outcome(),
+            Some(Outcome::Win(Colour::Black, "destroys the horde"))
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use wasm_bindgen::prelude::*;
+
+use crate::fen::piece_to_char;
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::play::find_move;
+use crate::variant::Variant;
+use crate::xboard::game_result;
+use crate::{parse_square, Colour, Game, GameState};
+
+/// A game for JavaScript, with squares written as "e4" and moves in coordinate notation such as
+/// "e2e4" or "e7e8q". Bad input gives an error or an empty result instead of a panic.
+#[wasm_bindgen(js_name = Game)]
+pub struct WasmGame {
+    game: Game,
+}
+
+#[wasm_bindgen(js_class = Game)]
+impl WasmGame {
+    /// Start a game from the starting position of a variant, standard chess if none is given
+    #[wasm_bindgen(constructor)]
+    pub fn new(variant: Option<String>) -> Result<WasmGame, String> {
+        Ok(WasmGame {
+            game: Game::new_variant(parse_variant(variant)?),
+        })
+    }
+
+    /// Start a game from a FEN
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: String, variant: Option<String>) -> Result<WasmGame, String> {
+        let game = Game::from_variant_fen(parse_variant(variant)?, fen)
+            .ok_or_else(|| "invalid FEN".to_string())?;
+        Ok(WasmGame { game })
+    }
+
+    /// Load the first game of a PGN, with all of its moves played
+    #[wasm_bindgen(js_name = fromPgn)]
+    pub fn from_pgn(pgn: String) -> Result<WasmGame, String> {
+        let game = parse_pgn(&pgn)
+            .first()
+            .and_then(|pgn_game| pgn_game.to_game())
+            .ok_or_else(|| "invalid PGN".to_string())?;
+        Ok(WasmGame { game })
+    }
+
+    pub fn fen(&self) -> String {
+        self.game.get_fen()
+    }
+
+    pub fn pgn(&self) -> String {
+        PgnGame::from_game(&self.game).to_pgn()
+    }
+
+    pub fn variant(&self) -> String {
+        self.game.get_variant().name().to_string()
+    }
+
+    /// "white" or "black"
+    pub fn turn(&self) -> String {
+        match self.game.turn {
+            Colour::White => "white".to_string(),
+            Colour::Black => "black".to_string(),
+        }
+    }
+
+    /// "inProgress", "check", "checkmate" or "gameOver"
+    pub fn state(&self) -> String {
+        match self.game.get_game_state() {
+            GameState::InProgress => "inProgress",
+            GameState::Check => "check",
+            GameState::Checkmate => "checkmate",
+            GameState::GameOver => "gameOver",
+        }
+        .to_string()
+    }
+
+    /// The result such as "1-0 {White mates}", or undefined while the game goes on
+    pub fn result(&mut self) -> Option<String> {
+        game_result(&mut self.game)
+    }
+
+    /// The piece on a square as a FEN letter such as "N" or "p"
+    #[wasm_bindgen(js_name = pieceAt)]
+    pub fn piece_at(&self, square: String) -> Option<String> {
+        let (x, y) = parse_square(&square)?;
+        self.game.board[x][y].map(|piece| piece_to_char(piece).to_string())
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&mut self) -> Vec<String> {
+        self.game
+            .get_legal_moves()
+            .into_iter()
+            .map(|_move| _move.to_uci())
+            .collect()
+    }
+
+    /// The legal moves in Standard Algebraic Notation
+    #[wasm_bindgen(js_name = legalMovesSan)]
+    pub fn legal_moves_san(&mut self) -> Vec<String> {
+        self.game
+            .get_legal_moves()
+            .into_iter()
+            .map(|_move| self.game.format_san(_move))
+            .collect()
+    }
+
+    /// The squares the piece on a square can move to, empty for a bad square
+    #[wasm_bindgen(js_name = movesFrom)]
+    pub fn moves_from(&mut self, square: String) -> Vec<String> {
+        self.game.get_possible_moves(square).unwrap_or_default()
+    }
+
+    /// Play a move in coordinate notation or Standard Algebraic Notation, returns the move in
+    /// Standard Algebraic Notation
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, text: String) -> Result<String, String> {
+        if self.result().is_some() {
+            return Err("the game is over".to_string());
+        }
+
+        let _move = find_move(&mut self.game, &text).ok_or_else(|| "illegal move".to_string())?;
+        let san = self.game.format_san(_move);
+        self.game.play_move(_move);
+
+        Ok(san)
+    }
+
+    /// Take back the last move, returns false if there is none
+    pub fn undo(&mut self) -> bool {
+        self.game.undo_move().is_some()
+    }
+}
+
+fn parse_variant(name: Option<String>) -> Result<Variant, String> {
+    match name {
+        Some(name) => Variant::from_name(&name).ok_or_else(|| format!("unknown variant {}", name)),
+        None => Ok(Variant::Standard),
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::WasmGame;
+
+    #[test]
+    fn test_wasm_game() {
+        let mut game = WasmGame::new(None).unwrap();
+        assert_eq!(game.legal_moves().len(), 20);
+        assert!(game.legal_moves().contains(&"g1f3".to_string()));
+        assert!(game.legal_moves_san().contains(&"Nf3".to_string()));
+        assert_eq!(game.moves_from("e2".to_string()), vec!["e3", "e4"]);
+
+        assert_eq!(game.make_move("e2e4".to_string()), Ok("e4".to_string()));
+        assert_eq!(game.make_move("Nf6".to_string()), Ok("Nf6".to_string()));
+        assert_eq!(game.turn(), "white");
+        assert_eq!(game.piece_at("f6".to_string()), Some("n".to_string()));
+        assert!(game.undo());
+        assert_eq!(game.piece_at("f6".to_string()), None);
+
+        let mut game = WasmGame::from_pgn(game.pgn()).unwrap();
+        assert_eq!(
+            game.fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        game.make_move("f7f6".to_string()).unwrap();
+        game.make_move("d4".to_string()).unwrap();
+        game.make_move("g7g5".to_string()).unwrap();
+        assert_eq!(
+            game.make_move("d1h6".to_string()),
+            Err("illegal move".to_string())
+        );
+        assert_eq!(game.make_move("d1h5".to_string()), Ok("Qh5#".to_string()));
+        assert_eq!(game.state(), "checkmate");
+        assert!(game.result().is_some());
+        assert!(game.make_move("e8d8".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_bad_input() {
+        assert!(WasmGame::new(Some("bughouse".to_string())).is_err());
+        assert!(WasmGame::from_fen("8/8/8".to_string(), None).is_err());
+        assert!(WasmGame::from_pgn("not a game".to_string()).is_err());
+
+        let mut game = WasmGame::from_fen(
+            "8/8/8/8/8/8/8/4K2k w - - 0 1".to_string(),
+            Some("atomic".to_string()),
+        )
+        .unwrap();
+        assert_eq!(game.variant(), "Atomic");
+        assert!(game.moves_from("z9".to_string()).is_empty());
+        assert_eq!(game.piece_at("".to_string()), None);
+        assert!(game.make_move("e1".to_string()).is_err());
+        assert!(game.make_move("é1é2é3".to_string()).is_err());
+    }
+}
@@ -0,0 +1,465 @@
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::polyglot::Book;
+use crate::search::{search_with_info, search_with_tablebase, SearchLimits, SearchResult};
+use crate::syzygy::Tablebase;
+use crate::variant::{Outcome, Variant};
+use crate::{Colour, Game, Move, KINGSIDE, QUEENSIDE};
+
+// Moves left until the next time control when the GUI doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+// Time kept in reserve for communication with the GUI
+const TIME_MARGIN: Duration = Duration::from_millis(50);
+
+// State of a session with an XBoard/WinBoard compatible GUI
+struct Session<'a> {
+    game: Game,
+    book: Option<&'a Book>,
+    // Varies the book moves between games
+    book_seed: u64,
+    tablebase: Option<&'a Tablebase>,
+    // The colour the engine plays, None in force mode
+    engine_colour: Option<Colour>,
+    post: bool,
+    max_depth: Option<u32>,
+    time_per_move: Option<Duration>,
+    moves_per_session: u32,
+    base_time: Duration,
+    increment: Duration,
+    engine_time: Option<Duration>,
+    // Set by "variant fischerandom", where castling is sent as O-O and O-O-O
+    chess960: bool,
+    variant: Variant,
+}
+
+impl<'a> Session<'a> {
+    fn new(book: Option<&'a Book>, tablebase: Option<&'a Tablebase>) -> Session<'a> {
+        let book_seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Session {
+            game: Game::new(),
+            book,
+            book_seed,
+            tablebase,
+            engine_colour: Some(Colour::Black),
+            post: false,
+            max_depth: None,
+            time_per_move: None,
+            moves_per_session: 0,
+            base_time: Duration::from_secs(300),
+            increment: Duration::default(),
+            engine_time: None,
+            chess960: false,
+            variant: Variant::Standard,
+        }
+    }
+}
+
+/// Talk to a GUI using the Chess Engine Communication Protocol (xboard protocol version 2)
+/// until the input ends or the GUI sends "quit". Moves are taken from the book while it has any,
+/// and endgames in the tablebase are played perfectly.
+pub fn run<R: BufRead, W: Write>(
+    input: R,
+    mut output: W,
+    book: Option<&Book>,
+    tablebase: Option<&Tablebase>,
+) -> io::Result<()> {
+    let mut session = Session::new(book, tablebase);
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.trim().splitn(2, ' ');
+        let command = words.next().unwrap_or("");
+        let arguments = words.next().unwrap_or("").trim();
+
+        match command {
+            "quit" => break,
+            "protover" => {
+                writeln!(output, "feature done=0")?;
+                let mut variants: Vec<&str> = Variant::ALL
+                    .iter()
+                    .map(|variant| variant.xboard_name())
+                    .collect();
+                variants.push("fischerandom");
+
+                writeln!(
+                    output,
+                    "feature myname=\"melg-chess\" ping=1 setboard=1 usermove=1 time=1 draw=0 \
+                     sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 playother=0 san=0 \
+                     variants=\"{}\"",
+                    variants.join(",")
+                )?;
+                writeln!(output, "feature done=1")?;
+            }
+            "new" => {
+                let post = session.post;
+                session = Session::new(book, tablebase);
+                session.post = post;
+            }
+            "force" => session.engine_colour = None,
+            "go" => {
+                session.engine_colour = Some(session.game.turn);
+                engine_move(&mut session, &mut output)?;
+            }
+            "usermove" => user_move(&mut session, arguments, &mut output)?,
+            "variant" => match Variant::from_name(arguments) {
+                Some(variant) => {
+                    session.variant = variant;
+                    session.game = Game::new_variant(variant);
+                }
+                None if arguments == "fischerandom" => {
+                    session.chess960 = true;
+                    session.game = Game::new_chess960(518).unwrap();
+                }
+                None => writeln!(output, "Error (unsupported variant): {}", arguments)?,
+            },
+            "setboard" => match Game::from_variant_fen(session.variant, arguments.to_string()) {
+                Some(mut game) => {
+                    game.chess960 |= session.chess960;
+                    session.game = game;
+                }
+                None => writeln!(output, "tellusererror Illegal position")?,
+            },
+            "level" => {
+                if !set_level(&mut session, arguments) {
+                    writeln!(output, "Error (bad arguments): {}", line.trim())?;
+                }
+            }
+            "st" => match arguments.parse::<f64>().ok().and_then(seconds_to_duration) {
+                Some(time) if !time.is_zero() => session.time_per_move = Some(time),
+                _ => writeln!(output, "Error (bad arguments): {}", line.trim())?,
+            },
+            "sd" => match arguments.parse::<u32>() {
+                Ok(depth) if depth > 0 => session.max_depth = Some(depth),
+                _ => writeln!(output, "Error (bad arguments): {}", line.trim())?,
+            },
+            "time" => match arguments
+                .parse::<u64>()
+                .ok()
+                .and_then(|centiseconds| centiseconds.checked_mul(10))
+            {
+                Some(milliseconds) => {
+                    session.engine_time = Some(Duration::from_millis(milliseconds))
+                }
+                None => writeln!(output, "Error (bad arguments): {}", line.trim())?,
+            },
+            "undo" => {
+                session.game.pop_move();
+            }
+            "remove" => {
+                session.game.pop_move();
+                session.game.pop_move();
+            }
+            "result" => session.engine_colour = None,
+            "ping" => writeln!(output, "pong {}", arguments)?,
+            "post" => session.post = true,
+            "nopost" => session.post = false,
+            // Commands that need no reply from an engine that doesn't ponder or analyse
+            "xboard" | "accepted" | "rejected" | "otim" | "hard" | "easy" | "random"
+            | "computer" | "name" | "rating" | "ics" | "white" | "black" | "holding" | "" => {}
+            _ => {
+                // Protocol version 1 GUIs send moves without the usermove prefix
+                if Move::from_uci(command).is_some() {
+                    user_move(&mut session, command, &mut output)?;
+                } else {
+                    writeln!(output, "Error (unknown command): {}", command)?;
+                }
+            }
+        }
+
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+// Play a move from the GUI and answer it if the engine is on the move
+fn user_move<W: Write>(session: &mut Session, text: &str, output: &mut W) -> io::Result<()> {
+    let legal_move = match text {
+        "O-O" | "O-O-O" => session.game.parse_san(text),
+        _ => Move::from_uci(text).filter(|_move| session.game.get_legal_moves().contains(_move)),
+    };
+
+    match legal_move {
+        Some(_move) => {
+            session.game.play_move(_move);
+
+            if let Some(result) = game_result(&mut session.game) {
+                writeln!(output, "{}", result)?;
+            } else if session.engine_colour == Some(session.game.turn) {
+                engine_move(session, output)?;
+            }
+        }
+        None => writeln!(output, "Illegal move: {}", text)?,
+    }
+
+    Ok(())
+}
+
+// Search for a move and send it to the GUI
+fn engine_move<W: Write>(session: &mut Session, output: &mut W) -> io::Result<()> {
+    if let Some(result) = game_result(&mut session.game) {
+        return writeln!(output, "{}", result);
+    }
+
+    let book_move = session.book.and_then(|book| {
+        let seed = session.book_seed ^ session.game.get_zobrist_key();
+        book.weighted_move(&mut session.game, seed)
+    });
+
+    if let Some(_move) = book_move {
+        let text = format_engine_move(&session.game, _move);
+        session.game.play_move(_move);
+        return writeln!(output, "move {}", text);
+    }
+
+    let limits = SearchLimits {
+        depth: session.max_depth,
+        time: Some(time_for_move(session)),
+        nodes: None,
+    };
+
+    let post = session.post;
+    let mut thinking: Vec<String> = Vec::new();
+    let mut on_iteration = |info: &SearchResult| {
+        if post {
+            thinking.push(format_thinking(info));
+        }
+    };
+    let result = match session.tablebase {
+        Some(tablebase) => {
+            search_with_tablebase(&session.game, limits, tablebase, &mut on_iteration)
+        }
+        None => search_with_info(&session.game, limits, &mut on_iteration),
+    };
+
+    for line in thinking {
+        writeln!(output, "{}", line)?;
+    }
+
+    if let Some(_move) = result.best_move {
+        let text = format_engine_move(&session.game, _move);
+        session.game.play_move(_move);
+        writeln!(output, "move {}", text)?;
+
+        if let Some(game_result) = game_result(&mut session.game) {
+            writeln!(output, "{}", game_result)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Thinking output: depth, score in centipawns, time in centiseconds, nodes and variation
+fn format_thinking(info: &SearchResult) -> String {
+    let variation: Vec<String> = info
+        .principal_variation
+        .iter()
+        .map(|_move| _move.to_uci())
+        .collect();
+
+    format!(
+        "{} {} {} {} {}",
+        info.depth,
+        info.score,
+        info.time.as_millis() / 10,
+        info.nodes,
+        variation.join(" ")
+    )
+}
+
+// Split the remaining time evenly over the moves left until the next time control
+fn time_for_move(session: &Session) -> Duration {
+    if let Some(time) = session.time_per_move {
+        return time.saturating_sub(TIME_MARGIN);
+    }
+
+    let remaining = session.engine_time.unwrap_or(session.base_time);
+    let moves_to_go = if session.moves_per_session > 0 {
+        let moves_played = session.game.fullmove_number.saturating_sub(1);
+        session.moves_per_session - moves_played % session.moves_per_session
+    } else {
+        DEFAULT_MOVES_TO_GO
+    };
+
+    let budget = (remaining / moves_to_go).saturating_add(session.increment.saturating_mul(3) / 4);
+    budget
+        .min(remaining / 2)
+        .saturating_sub(TIME_MARGIN)
+        .max(Duration::from_millis(10))
+}
+
+// level MPS BASE INC, where BASE is minutes or minutes:seconds
+fn set_level(session: &mut Session, arguments: &str) -> bool {
+    let fields: Vec<&str> = arguments.split_whitespace().collect();
+    if fields.len() != 3 {
+        return false;
+    }
+
+    let (minutes, seconds) = fields[1].split_once(':').unwrap_or((fields[1], "0"));
+    let base_seconds = match (minutes.parse::<u64>(), seconds.parse::<u64>()) {
+        (Ok(minutes), Ok(seconds)) => minutes
+            .checked_mul(60)
+            .and_then(|base| base.checked_add(seconds)),
+        _ => None,
+    };
+    let increment = fields[2].parse::<f64>().ok().and_then(seconds_to_duration);
+
+    match (fields[0].parse::<u32>(), base_seconds, increment) {
+        (Ok(moves), Some(base_seconds), Some(increment)) => {
+            session.moves_per_session = moves;
+            session.base_time = Duration::from_secs(base_seconds);
+            session.increment = increment;
+            session.time_per_move = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+// Seconds from the GUI as a Duration, or None if they are negative, not finite or too large
+fn seconds_to_duration(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// The result command for a finished game, or None if the game goes on
+pub(crate) fn game_result(game: &mut Game) -> Option<String> {
+    match game.get_outcome() {
+        Some(Outcome::Win(Colour::White, reason)) => {
+            return Some(format!("1-0 {{White {}}}", reason))
+        }
+        Some(Outcome::Win(Colour::Black, reason)) => {
+            return Some(format!("0-1 {{Black {}}}", reason))
+        }
+        Some(Outcome::Draw(reason)) => return Some(format!("1/2-1/2 {{{}}}", reason)),
+        None => {}
+    }
+
+    if game.halfmove_clock >= 100 {
+        return Some("1/2-1/2 {50 move rule}".to_string());
+    }
+
+    None
+}
+
+// Chess960 castling is sent as O-O or O-O-O as the king taking its rook is ambiguous to GUIs
+fn format_engine_move(game: &Game, _move: Move) -> String {
+    match game.get_castling(_move.from, _move.to) {
+        Some(castling) if game.chess960 && castling.side == KINGSIDE => "O-O".to_string(),
+        Some(castling) if game.chess960 && castling.side == QUEENSIDE => "O-O-O".to_string(),
+        _ => _move.to_uci(),
+    }
+}
+
+// --------------------------
+// ######### TESTS ##########
+// --------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use crate::{Game, Move};
+
+    fn run_commands(commands: &str) -> String {
+        let mut output: Vec<u8> = Vec::new();
+        run(commands.as_bytes(), &mut output, None, None).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_handshake_and_ping() {
+        let output = run_commands("xboard\nprotover 2\nping 7\nquit\nping 8\n");
+
+        assert!(output.contains("feature done=0\n"));
+        assert!(output.contains("setboard=1"));
+        assert!(output.contains("feature done=1\n"));
+        assert!(output.ends_with("pong 7\n"));
+    }
+
+    #[test]
+    fn test_user_moves_and_engine_reply() {
+        let output = run_commands("new\nforce\nusermove e2e4\nusermove e2e5\nsd 1\ngo\n");
+
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("Illegal move: e2e5"));
+
+        // The reply to go is a legal move for black
+        let reply = lines
+            .next()
+            .and_then(|line| line.strip_prefix("move "))
+            .unwrap();
+        let mut game = Game::new();
+        game.make_uci_move("e2e4".to_string());
+        let _move = Move::from_uci(reply).unwrap();
+        assert!(game.get_legal_moves().contains(&_move));
+    }
+
+    #[test]
+    fn test_bad_time_controls() {
+        let output = run_commands(
+            "new\nforce\nst inf\nst -1\nlevel 40 5 inf\nlevel 40 5 -1\n\
+             level 40 999999999999999999999 0\nlevel 40 307445734561825861:0 0\n\
+             time 99999999999999999999\ntime 9999999999999999999\n",
+        );
+        for command in [
+            "st inf",
+            "st -1",
+            "level 40 5 inf",
+            "level 40 5 -1",
+            "level 40 999999999999999999999 0",
+            "level 40 307445734561825861:0 0",
+            "time 99999999999999999999",
+            "time 9999999999999999999",
+        ] {
+            assert!(
+                output.contains(&format!("Error (bad arguments): {}\n", command)),
+                "{}",
+                command
+            );
+        }
+
+        // A fullmove number of 0 is refused rather than breaking the moves left to the control
+        let output = run_commands(
+            "new\nforce\nsetboard 4k3/8/8/8/8/8/8/4K2Q w - - 0 0\nlevel 40 5 0\nsd 1\ngo\n",
+        );
+        assert!(output.starts_with("tellusererror Illegal position\n"));
+        assert!(output.contains("\nmove "));
+    }
+
+    #[test]
+    fn test_engine_mates_after_setboard() {
+        let output =
+            run_commands("new\nforce\nsetboard 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1\nsd 2\ngo\n");
+
+        assert_eq!(output, "move a1a8\n1-0 {White mates}\n");
+    }
+
+    #[test]
+    fn test_fischerandom_castling() {
+        let output = run_commands(
+            "new\nvariant fischerandom\nforce\nsetboard 4k3/8/8/8/8/8/8/RK5R w KQ - 0 1\n\
+             usermove O-O-O\nusermove O-O\n",
+        );
+        assert_eq!(output, "Illegal move: O-O\n");
+
+        // Only castling mates, with the rook ending on d1
+        let output = run_commands(
+            "new\nvariant fischerandom\nforce\nsetboard 2rkr3/2p1p3/8/8/8/8/8/RK6 w Q - 0 1\n\
+             sd 2\ngo\n",
+        );
+        assert_eq!(output, "move O-O-O\n1-0 {White mates}\n");
+    }
+
+    #[test]
+    fn test_undo_and_remove() {
+        let output = run_commands(
+            "new\nforce\nusermove e2e4\nusermove e7e5\nremove\nusermove d2d4\nundo\nusermove e7e5\n",
+        );
+
+        assert_eq!(output, "Illegal move: e7e5\n");
+    }
+}
@@ -0,0 +1,635 @@
+#!/usr/bin/env python3
+"""Write KQvK.rtbw, KQvK.rtbz and KPvK.rtbw, small Syzygy tables used by the tests of
+src/syzygy.rs.
+
+The positions are solved by retrograde analysis with a move generator of their own, and the
+values are written in the Syzygy file format. KQvK has one WDL table for each side to move and
+a DTZ table for white to move, with Huffman coded values and no pairing of symbols. KPvK has a
+WDL table for each side to move and each file of the pawn, with the kings in a different order
+for each side to move and runs of values paired into symbols.
+
+These are not the tables of the official Syzygy set, which can't be downloaded here, so the
+tests also check the probes against known results of the endgames.
+"""
+
+import heapq
+import os
+import struct
+
+WDL_MAGIC = bytes([0x71, 0xE8, 0x23, 0x5D])
+DTZ_MAGIC = bytes([0xD7, 0x66, 0x0C, 0xA5])
+
+WHITE_PAWN, WHITE_QUEEN, WHITE_KING, BLACK_KING = 1, 5, 6, 14
+PIECES = [WHITE_KING, WHITE_QUEEN, BLACK_KING]
+TABLE_SIZE = 31332
+
+# The pawn's rank index for each file times the squares of the two kings
+PAWN_TABLE_SIZE = 6 * 63 * 62
+
+# Pairs of symbols are only made while they save this many codes, and a symbol stands for at
+# most 256 values
+MIN_PAIR_COUNT = 8
+MAX_SYMBOL_VALUES = 256
+
+BLOCK_SIZE_BITS = 6
+SPAN_BITS = 6
+
+
+def file_of(square):
+    return square & 7
+
+
+def rank_of(square):
+    return square >> 3
+
+
+def off_diagonal(square):
+    return rank_of(square) - file_of(square)
+
+
+def flip_diagonal(square):
+    return ((square >> 3) | (square << 3)) & 63
+
+
+def adjacent(a, b):
+    return max(abs(file_of(a) - file_of(b)), abs(rank_of(a) - rank_of(b))) <= 1
+
+
+KING_MOVES = [
+    [to for to in range(64) if to != square and adjacent(square, to)] for square in range(64)
+]
+DIRECTIONS = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)]
+
+
+def queen_moves(queen, blockers):
+    return slider_moves(queen, blockers, DIRECTIONS)
+
+
+def slider_moves(piece, blockers, directions):
+    moves = []
+    for df, dr in directions:
+        f, r = file_of(piece) + df, rank_of(piece) + dr
+        while 0 <= f < 8 and 0 <= r < 8:
+            square = r * 8 + f
+            moves.append(square)
+            if square in blockers:
+                break
+            f, r = f + df, r + dr
+    return moves
+
+
+def queen_attacks(queen, target, blockers):
+    return target in queen_moves(queen, blockers)
+
+
+# Index of a position in the order of the pieces, as the Syzygy encoding of three unique
+# pieces computes it
+def build_maps():
+    map_a1d1d4 = [0] * 64
+    code = 0
+    diagonal = []
+    for square in range(28):
+        if file_of(square) > 3:
+            continue
+        if off_diagonal(square) < 0:
+            map_a1d1d4[square] = code
+            code += 1
+        elif off_diagonal(square) == 0:
+            diagonal.append(square)
+    for square in diagonal:
+        map_a1d1d4[square] = code
+        code += 1
+
+    map_b1h1h7 = [0] * 64
+    code = 0
+    for square in range(64):
+        if off_diagonal(square) < 0:
+            map_b1h1h7[square] = code
+            code += 1
+
+    return map_a1d1d4, map_b1h1h7
+
+
+MAP_A1D1D4, MAP_B1H1H7 = build_maps()
+
+
+def index_of(squares):
+    squares = list(squares)
+    if file_of(squares[0]) > 3:
+        squares = [square ^ 7 for square in squares]
+    if rank_of(squares[0]) > 3:
+        squares = [square ^ 56 for square in squares]
+    for i in range(3):
+        if off_diagonal(squares[i]) == 0:
+            continue
+        if off_diagonal(squares[i]) > 0:
+            squares[i:] = [flip_diagonal(square) for square in squares[i:]]
+        break
+
+    s0, s1, s2 = squares
+    adjust1 = int(s1 > s0)
+    adjust2 = int(s2 > s0) + int(s2 > s1)
+
+    if off_diagonal(s0) != 0:
+        return (MAP_A1D1D4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+    if off_diagonal(s1) != 0:
+        return (6 * 63 + rank_of(s0) * 28 + MAP_B1H1H7[s1]) * 62 + s2 - adjust2
+    if off_diagonal(s2) != 0:
+        return (
+            6 * 63 * 62
+            + 4 * 28 * 62
+            + rank_of(s0) * 7 * 28
+            + (rank_of(s1) - adjust1) * 28
+            + MAP_B1H1H7[s2]
+        )
+    return (
+        6 * 63 * 62
+        + 4 * 28 * 62
+        + 4 * 7 * 28
+        + rank_of(s0) * 7 * 6
+        + (rank_of(s1) - adjust1) * 6
+        + (rank_of(s2) - adjust2)
+    )
+
+
+# Every legal position as (white king, white queen, black king), for each side to move
+def legal_positions(black_to_move):
+    for wk in range(64):
+        for wq in range(64):
+            if wq == wk:
+                continue
+            for bk in range(64):
+                if bk in (wk, wq) or adjacent(wk, bk):
+                    continue
+                if not black_to_move and queen_attacks(wq, bk, {wk, bk}):
+                    continue
+                yield wk, wq, bk
+
+
+def black_moves(wk, wq, bk):
+    """Moves of the black king as (square, captures the queen)."""
+    moves = []
+    for to in KING_MOVES[bk]:
+        if adjacent(to, wk):
+            continue
+        if to == wq:
+            moves.append((to, True))
+        elif not queen_attacks(wq, to, {wk, to}):
+            moves.append((to, False))
+    return moves
+
+
+def white_moves(wk, wq, bk):
+    """Positions after every white move, with black to move."""
+    positions = []
+    for to in KING_MOVES[wk]:
+        if to != wq and not adjacent(to, bk):
+            positions.append((to, wq, bk))
+    for to in queen_moves(wq, {wk, bk}):
+        if to not in (wk, bk):
+            positions.append((wk, to, bk))
+    return positions
+
+
+def solve():
+    """Distance to mate in plies, positive for wins of the side to move, negative for losses
+    and None for draws."""
+    black = {}
+    undecided_black = []
+    for position in legal_positions(True):
+        wk, wq, bk = position
+        moves = black_moves(*position)
+        in_check = queen_attacks(wq, bk, {wk, bk})
+        if not moves:
+            black[position] = 0 if in_check else None
+            continue
+        if any(capture for _, capture in moves):
+            black[position] = None
+            continue
+        undecided_black.append((position, [(wk, wq, to) for to, _ in moves]))
+
+    white = {}
+    undecided_white = list(legal_positions(False))
+    plies = 1
+    while undecided_white:
+        still_undecided = []
+        for position in undecided_white:
+            if any(black.get(after) == -(plies - 1) for after in white_moves(*position)):
+                white[position] = plies
+            else:
+                still_undecided.append(position)
+        if len(still_undecided) == len(undecided_white):
+            break
+        undecided_white = still_undecided
+
+        still_undecided = []
+        for position, afters in undecided_black:
+            if all(after in white for after in afters):
+                black[position] = -(max(white[after] for after in afters) + 1)
+            else:
+                still_undecided.append((position, afters))
+        undecided_black = still_undecided
+        plies += 2
+
+    assert not undecided_white, "every white to move position wins"
+    for position, _ in undecided_black:
+        black[position] = None
+
+    return white, black
+
+
+def fill(values, default):
+    table = [None] * TABLE_SIZE
+    for squares, value in values.items():
+        index = index_of(squares)
+        assert table[index] in (None, value), "symmetric positions with different values"
+        table[index] = value
+    return [default if value is None else value for value in table]
+
+
+def huffman_lengths(frequencies):
+    if len(frequencies) == 1:
+        return {symbol: 1 for symbol in frequencies}
+    heap = [(count, [symbol]) for symbol, count in frequencies.items()]
+    heapq.heapify(heap)
+    lengths = {symbol: 0 for symbol in frequencies}
+    while len(heap) > 1:
+        count1, symbols1 = heapq.heappop(heap)
+        count2, symbols2 = heapq.heappop(heap)
+        for symbol in symbols1 + symbols2:
+            lengths[symbol] += 1
+        heapq.heappush(heap, (count1 + count2, symbols1 + symbols2))
+    return lengths
+
+
+def pair_symbols(sequence, children, expansion, max_pairs):
+    """Replace the most common neighbouring symbols by a new symbol, up to max_pairs times."""
+    for _ in range(max_pairs):
+        counts = {}
+        for pair in zip(sequence, sequence[1:]):
+            if expansion[pair[0]] + expansion[pair[1]] <= MAX_SYMBOL_VALUES:
+                counts[pair] = counts.get(pair, 0) + 1
+        if not counts:
+            break
+        pair, count = max(counts.items(), key=lambda item: (item[1], -item[0][0], -item[0][1]))
+        if count < MIN_PAIR_COUNT:
+            break
+
+        symbol = len(expansion)
+        children.append(pair)
+        expansion.append(expansion[pair[0]] + expansion[pair[1]])
+        paired = []
+        i = 0
+        while i < len(sequence):
+            if i + 1 < len(sequence) and (sequence[i], sequence[i + 1]) == pair:
+                paired.append(symbol)
+                i += 2
+            else:
+                paired.append(sequence[i])
+                i += 1
+        sequence = paired
+    return sequence
+
+
+class Pairs:
+    """The sizes, symbol table and compressed blocks of one table of values."""
+
+    def __init__(self, values, flags, max_pairs=0):
+        self.flags = flags
+        distinct = sorted(set(values))
+        if len(distinct) == 1:
+            self.single = distinct[0]
+            self.blocks = []
+            self.sparse = []
+            return
+        self.single = None
+
+        # The first symbols are the values themselves, the others stand for two symbols each
+        leaf = {value: symbol for symbol, value in enumerate(distinct)}
+        children = [None] * len(distinct)
+        expansion = [1] * len(distinct)
+        sequence = pair_symbols(
+            [leaf[value] for value in values], children, expansion, max_pairs
+        )
+
+        # Symbols only used inside pairs still need a code
+        frequencies = {symbol: 0 for symbol in range(len(expansion))}
+        for symbol in sequence:
+            frequencies[symbol] += 1
+        frequencies = {symbol: max(count, 1) for symbol, count in frequencies.items()}
+        lengths = huffman_lengths(frequencies)
+        min_length, max_length = min(lengths.values()), max(lengths.values())
+        counts = [0] * (max_length - min_length + 1)
+        for length in lengths.values():
+            counts[length - min_length] += 1
+
+        # Longer codes get the lower symbols and the lower code values
+        last = len(counts) - 1
+        lowest = [0] * len(counts)
+        base = [0] * len(counts)
+        for i in range(last - 1, -1, -1):
+            lowest[i] = lowest[i + 1] + counts[i + 1]
+            assert (base[i + 1] + counts[i + 1]) % 2 == 0
+            base[i] = (base[i + 1] + counts[i + 1]) // 2
+        assert base[0] + counts[0] == 1 << min_length
+
+        numbers = {}
+        next_symbol = list(lowest)
+        for symbol in sorted(frequencies, key=lambda symbol: (-lengths[symbol], symbol)):
+            i = lengths[symbol] - min_length
+            numbers[symbol] = next_symbol[i]
+            next_symbol[i] += 1
+
+        self.min_length, self.max_length = min_length, max_length
+        self.lowest = lowest
+        self.tree = [None] * len(expansion)
+        codes = {}
+        for symbol, number in numbers.items():
+            i = lengths[symbol] - min_length
+            if children[symbol] is None:
+                self.tree[number] = (distinct[symbol], None)
+            else:
+                left, right = children[symbol]
+                self.tree[number] = (numbers[left], numbers[right])
+            codes[symbol] = (base[i] + number - lowest[i], lengths[symbol])
+
+        # Fill blocks with as many codes as fit, counting the values they stand for
+        block_bits = (1 << BLOCK_SIZE_BITS) * 8
+        self.blocks = []
+        starts = []
+        bits, used, count = 0, 0, 0
+        position = 0
+        for symbol in sequence:
+            code, length = codes[symbol]
+            if used + length > block_bits or count + expansion[symbol] > 1 << 16:
+                self.blocks.append((bits << (block_bits - used), count))
+                bits, used, count = 0, 0, 0
+            if count == 0:
+                starts.append(position)
+            bits = (bits << length) | code
+            used += length
+            count += expansion[symbol]
+            position += expansion[symbol]
+        self.blocks.append((bits << (block_bits - used), count))
+
+        span = 1 << SPAN_BITS
+        self.sparse = []
+        for k in range((len(values) + span - 1) // span):
+            position = k * span + span // 2
+            block = len(starts) - 1
+            while starts[block] > position:
+                block -= 1
+            self.sparse.append((block, position - starts[block]))
+
+    def sizes(self):
+        if self.single is not None:
+            return bytes([self.flags | 0x80, self.single])
+        data = bytes([self.flags, BLOCK_SIZE_BITS, SPAN_BITS, 0])
+        data += struct.pack("<I", len(self.blocks))
+        data += bytes([self.max_length, self.min_length])
+        data += b"".join(struct.pack("<H", lowest) for lowest in self.lowest)
+        data += struct.pack("<H", len(self.tree))
+        for left, right in self.tree:
+            # A value has 0xfff as its right half
+            right = 0xFFF if right is None else right
+            data += bytes([left & 0xFF, (left >> 8) | (right & 0xF) << 4, right >> 4])
+        if len(self.tree) & 1:
+            data += b"\0"
+        return data
+
+    def sparse_index(self):
+        return b"".join(struct.pack("<IH", block, offset) for block, offset in self.sparse)
+
+    def block_lengths(self):
+        return b"".join(struct.pack("<H", count - 1) for _, count in self.blocks)
+
+    def data(self):
+        size = 1 << BLOCK_SIZE_BITS
+        return b"".join(bits.to_bytes(size, "big") for bits, _ in self.blocks)
+
+
+def write_table(path, magic, split, tables, dtz, files=None):
+    """Files are (order, pieces for white to move, pieces for black to move) for each file of
+    the leading pawn, or None for a table without pawns."""
+    data = bytearray(magic)
+    if files is None:
+        data.append(1 if split else 0)
+        data.append(0)
+        for piece in PIECES:
+            data.append(piece | piece << 4)
+    else:
+        data.append((1 if split else 0) | 2)
+        for order, white_pieces, black_pieces in files:
+            data.append(order | order << 4)
+            for white_piece, black_piece in zip(white_pieces, black_pieces):
+                data.append(white_piece | black_piece << 4)
+    if len(data) & 1:
+        data.append(0)
+
+    for table in tables:
+        data += table.sizes()
+    if dtz and len(data) & 1:
+        data.append(0)
+    for table in tables:
+        data += table.sparse_index()
+    for table in tables:
+        data += table.block_lengths()
+    for table in tables:
+        while len(data) % 64:
+            data.append(0)
+        data += table.data()
+
+    # The decoder reads ahead of the last block
+    data += bytes(8)
+
+    with open(path, "wb") as file:
+        file.write(data)
+
+
+def pawn_attacks(pawn, square):
+    return rank_of(square) == rank_of(pawn) + 1 and abs(file_of(square) - file_of(pawn)) == 1
+
+
+def promotion_wins(wk, square, bk, directions):
+    """Whether a queen or rook on the promotion square wins with black to move: black is mated,
+    or is neither stalemated nor able to take the new piece."""
+    attacked = set(slider_moves(square, {wk}, directions))
+    in_check = bk in attacked
+    moves = []
+    for to in KING_MOVES[bk]:
+        if adjacent(to, wk) or (to in attacked and to != square):
+            continue
+        if to == square and adjacent(square, wk):
+            continue
+        moves.append(to)
+    if not moves:
+        return in_check
+    return square not in moves
+
+
+def solve_pawn():
+    """Outcomes of KPvK for white to move (True for a win) and black to move (True for a
+    loss), for every legal position as (white king, white pawn, black king)."""
+    rook_directions = DIRECTIONS[:4]
+    white_positions, black_positions = [], []
+    for wk in range(64):
+        for wp in range(8, 56):
+            if wp == wk:
+                continue
+            for bk in range(64):
+                if bk in (wk, wp) or adjacent(wk, bk):
+                    continue
+                black_positions.append((wk, wp, bk))
+                if not pawn_attacks(wp, bk):
+                    white_positions.append((wk, wp, bk))
+
+    # White's moves lead to black positions, or win at once by promoting
+    white_moves = {}
+    for wk, wp, bk in white_positions:
+        afters = []
+        wins = False
+        for to in KING_MOVES[wk]:
+            if to != wp and not adjacent(to, bk):
+                afters.append((to, wp, bk))
+        push = wp + 8
+        if push not in (wk, bk):
+            if rank_of(push) == 7:
+                wins = promotion_wins(wk, push, bk, DIRECTIONS) or promotion_wins(
+                    wk, push, bk, rook_directions
+                )
+            else:
+                afters.append((wk, push, bk))
+                if rank_of(wp) == 1 and wp + 16 not in (wk, bk):
+                    afters.append((wk, wp + 16, bk))
+        white_moves[(wk, wp, bk)] = (afters, wins)
+
+    # Black draws by taking the pawn, otherwise black's moves lead to white positions
+    black_moves = {}
+    white_wins = {}
+    black_loses = {}
+    for wk, wp, bk in black_positions:
+        afters = []
+        draws = False
+        for to in KING_MOVES[bk]:
+            if adjacent(to, wk) or pawn_attacks(wp, to):
+                continue
+            if to == wp:
+                draws = True
+            else:
+                afters.append((wk, wp, to))
+        if not afters and not draws:
+            black_loses[(wk, wp, bk)] = pawn_attacks(wp, bk)
+        elif draws:
+            black_loses[(wk, wp, bk)] = False
+        else:
+            black_moves[(wk, wp, bk)] = afters
+
+    undecided_white = set(white_positions)
+    undecided_black = set(black_moves)
+    changed = True
+    while changed:
+        changed = False
+        for position in list(undecided_white):
+            afters, wins = white_moves[position]
+            if wins or any(black_loses.get(after) for after in afters):
+                white_wins[position] = True
+                undecided_white.discard(position)
+                changed = True
+        for position in list(undecided_black):
+            if all(white_wins.get(after) for after in black_moves[position]):
+                black_loses[position] = True
+                undecided_black.discard(position)
+                changed = True
+
+    for position in undecided_white:
+        white_wins[position] = False
+    for position in undecided_black:
+        black_loses[position] = False
+    return white_wins, black_loses
+
+
+def pawn_index(squares, order):
+    """Index of the pawn and the two kings in the order the table lists them, with the group of
+    the leading pawn at place order among the groups."""
+    pawn, first, second = squares
+    groups = [rank_of(pawn) - 1, first - int(first > pawn), second - int(second > pawn) - int(
+        second > first
+    )]
+    sizes = [6, 63, 62]
+    places = [1, 2]
+    places.insert(order, 0)
+
+    index, factor = 0, 1
+    for group in places:
+        index += groups[group] * factor
+        factor *= sizes[group]
+    return index
+
+
+def fill_pawn(values, file, order, kings):
+    """The values of a file in index order, with the kings listed as in kings. Indices of no
+    legal position repeat the value before them, which compresses best."""
+    table = [None] * PAWN_TABLE_SIZE
+    for (wk, wp, bk), value in values.items():
+        if file_of(wp) != file:
+            continue
+        squares = {WHITE_KING: wk, BLACK_KING: bk}
+        index = pawn_index((wp, squares[kings[0]], squares[kings[1]]), order)
+        table[index] = value
+    last = 2
+    for index, value in enumerate(table):
+        if value is None:
+            table[index] = last
+        else:
+            last = value
+    return table
+
+
+def main():
+    white, black = solve()
+    assert max(white.values()) == 19, "the longest KQvK mate is ten moves"
+
+    wdl_white = fill({position: 4 for position in white}, 4)
+    wdl_black = fill(
+        {position: 2 if value is None else 0 for position, value in black.items()}, 0
+    )
+    # DTZ in full moves, wins take an odd number of plies
+    dtz_white = fill({position: (value - 1) // 2 for position, value in white.items()}, 0)
+
+    directory = os.path.dirname(os.path.abspath(__file__))
+    write_table(
+        os.path.join(directory, "KQvK.rtbw"),
+        WDL_MAGIC,
+        True,
+        [Pairs(wdl_white, 0), Pairs(wdl_black, 0)],
+        False,
+    )
+    write_table(
+        os.path.join(directory, "KQvK.rtbz"),
+        DTZ_MAGIC,
+        False,
+        [Pairs(dtz_white, 0)],
+        True,
+    )
+
+    white_wins, black_loses = solve_pawn()
+    wdl_white = {position: 4 if win else 2 for position, win in white_wins.items()}
+    wdl_black = {position: 0 if loss else 2 for position, loss in black_loses.items()}
+
+    # The leading pawn's group comes at a different place for each file, and the two sides to
+    # move list the kings in a different order
+    white_kings = [WHITE_KING, BLACK_KING]
+    black_kings = [BLACK_KING, WHITE_KING]
+    files, tables = [], []
+    for file in range(4):
+        order = file % 3
+        files.append((order, [WHITE_PAWN] + white_kings, [WHITE_PAWN] + black_kings))
+        tables.append(Pairs(fill_pawn(wdl_white, file, order, white_kings), 0, 64))
+        tables.append(Pairs(fill_pawn(wdl_black, file, order, black_kings), 0, 64))
+    write_table(
+        os.path.join(directory, "KPvK.rtbw"), WDL_MAGIC, True, tables, False, files
+    )
+
+
+if __name__ == "__main__":
+    main()